@@ -3,39 +3,106 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::thread;
+use std::time::SystemTime;
 use std::env;
 
 use gl_generator::{Api, Fallbacks, Profile, Registry};
 use vergen::EmitBuilder;
 
-// We can make this configurable in the future if different platforms start to have
-// different needs.
+const SUPPORTED_EGL_VERSIONS: [(u8, u8); 6] = [(1, 0), (1, 1), (1, 2), (1, 3), (1, 4), (1, 5)];
+
+/// Parse `WEBVIEWER_EGL_VERSION` (e.g. "1.4") into a `(major, minor)` tuple,
+/// defaulting to EGL 1.5.
+fn egl_version() -> (u8, u8) {
+    println!("cargo:rerun-if-env-changed=WEBVIEWER_EGL_VERSION");
+    let Ok(version) = env::var("WEBVIEWER_EGL_VERSION") else {
+        return (1, 5);
+    };
+    let parsed = version.trim().split_once('.').and_then(|(major, minor)| {
+        Some((major.parse::<u8>().ok()?, minor.parse::<u8>().ok()?))
+    });
+    match parsed {
+        Some(version) if SUPPORTED_EGL_VERSIONS.contains(&version) => version,
+        _ => panic!(
+            "Invalid WEBVIEWER_EGL_VERSION {:?}. Supported versions are: {}",
+            version,
+            SUPPORTED_EGL_VERSIONS
+                .iter()
+                .map(|(major, minor)| format!("{}.{}", major, minor))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// `WEBVIEWER_EGL_FALLBACKS=none` disables fallbacks to extension entry points,
+/// anything else (or unset) keeps `Fallbacks::All`.
+fn egl_fallbacks() -> Fallbacks {
+    println!("cargo:rerun-if-env-changed=WEBVIEWER_EGL_FALLBACKS");
+    match env::var("WEBVIEWER_EGL_FALLBACKS").as_deref() {
+        Ok("none") | Ok("0") | Ok("false") => Fallbacks::None,
+        Ok("all") | Ok("1") | Ok("true") | Err(_) => Fallbacks::All,
+        Ok(other) => panic!(
+            "Invalid WEBVIEWER_EGL_FALLBACKS {:?}. Expected \"all\" or \"none\"",
+            other
+        ),
+    }
+}
+
+// The EGL version and fallbacks default to EGL 1.5 with all fallbacks, but can be
+// lowered for devices that lack the EGL 1.5 entry points.
 fn generate_egl_bindings(out_dir: &Path) {
     let mut file = File::create(out_dir.join("egl_bindings.rs")).unwrap();
-    Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [])
+    Registry::new(Api::Egl, egl_version(), Profile::Core, egl_fallbacks(), [])
         .write_bindings(gl_generator::StaticStructGenerator, &mut file)
         .unwrap();
     println!("cargo:rustc-link-lib=EGL");
 }
 
+fn python_runs(python: impl AsRef<std::ffi::OsStr>) -> bool {
+    Command::new(python)
+        .arg("--version")
+        .output()
+        .ok()
+        .map_or(false, |out| out.status.success())
+}
+
+/// The interpreter of the activated virtualenv, if any, so that we pick up the
+/// codegen dependencies installed there rather than the system python.
+fn virtualenv_python() -> Option<String> {
+    println!("cargo:rerun-if-env-changed=VIRTUAL_ENV");
+    let venv = PathBuf::from(env::var_os("VIRTUAL_ENV")?);
+    let python = if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    };
+    if python.is_file() && python_runs(&python) {
+        Some(python.to_string_lossy().into_owned())
+    } else {
+        println!(
+            "cargo:warning=Ignoring VIRTUAL_ENV, {} is not a working python",
+            python.display()
+        );
+        None
+    }
+}
+
 fn find_python() -> String {
-    env::var("PYTHON3").ok().unwrap_or_else(|| {
+    println!("cargo:rerun-if-env-changed=PYTHON3");
+    virtualenv_python().or_else(|| env::var("PYTHON3").ok()).unwrap_or_else(|| {
         let candidates = if cfg!(windows) {
             ["python.exe", "python"]
         } else {
             ["python3", "python"]
         };
         for &name in &candidates {
-            if Command::new(name)
-                .arg("--version")
-                .output()
-                .ok()
-                .map_or(false, |out| out.status.success())
-            {
+            if python_runs(name) {
                 return name.to_owned();
             }
         }
@@ -46,35 +113,170 @@ fn find_python() -> String {
     })
 }
 
-// Generate the WebIDL bindings with Servo's codegen.
-fn generate_webidl_bindings() {
+/// Find the `css-properties.json` generated by the `style` crate's build script.
+///
+/// `build_dir` is the `target/{profile}/build` directory of the current build, so the
+/// profile always matches ours. When several `style-*` directories exist (e.g. after a
+/// `style` version bump), the most recently modified output is used.
+fn find_css_properties_json(build_dir: &Path) -> PathBuf {
+    let mut candidates: Vec<(SystemTime, PathBuf)> = fs::read_dir(build_dir)
+        .unwrap_or_else(|error| panic!("Can't read {}: {}", build_dir.display(), error))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("style-"))
+        .map(|entry| entry.path().join("out").join("css-properties.json"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .collect();
+    candidates.sort();
+
+    match candidates.as_slice() {
+        [] => panic!(
+            "Can't find {}/style-*/out/css-properties.json. Make sure the style crate \
+             has been built with the same profile before building the webidl bindings.",
+            build_dir.display()
+        ),
+        [.., (previous, _), (newest, _)] if previous == newest => panic!(
+            "Found several css-properties.json with the same modification time in {}/style-*/out. \
+             Run `cargo clean -p style` to remove the stale ones.",
+            build_dir.display()
+        ),
+        [.., (_, newest)] => newest.clone(),
+    }
+}
+
+/// Feed `bytes` to a 64-bit FNV-1a hash, preceded by their length so that consecutive
+/// inputs can't run into each other.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    (bytes.len() as u64)
+        .to_le_bytes()
+        .iter()
+        .chain(bytes)
+        .fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Hash the names and contents of all the codegen inputs, so that we can tell whether the
+/// previously generated bindings are still up to date. FNV-1a is used rather than
+/// `DefaultHasher`, whose output may change between Rust releases.
+fn webidl_inputs_hash(inputs: &[PathBuf]) -> u64 {
+    inputs.iter().fold(0xcbf29ce484222325, |hash, input| {
+        let name = input.file_name().unwrap_or_default().to_string_lossy();
+        let contents = fs::read(input)
+            .unwrap_or_else(|error| panic!("Can't read {}: {}", input.display(), error));
+        fnv1a(fnv1a(hash, name.as_bytes()), &contents)
+    })
+}
+
+/// Returns true if `hash_file` records `hash`, and every output listed in it still exists.
+///
+/// The hash file contains the inputs hash on its first line, followed by the paths
+/// the codegen wrote into `OUT_DIR` the last time it ran.
+fn webidl_bindings_up_to_date(hash_file: &Path, hash: u64) -> bool {
+    let Ok(contents) = fs::read_to_string(hash_file) else {
+        return false;
+    };
+    let mut lines = contents.lines();
+    if lines.next() != Some(hash.to_string().as_str()) {
+        return false;
+    }
+    let mut outputs = lines.peekable();
+    outputs.peek().is_some() && outputs.all(|output| Path::new(output).exists())
+}
+
+// Generate the WebIDL bindings with Servo's codegen. Returns the hash of the inputs if the
+// bindings were generated, to be recorded with `record_webidl_bindings`.
+fn generate_webidl_bindings(out_dir: &Path, build_dir: &Path) -> Option<u64> {
     let servo_path = if let Some(servo_env_path) = env::var_os("SERVO_PATH") {
         servo_env_path.into_string().unwrap()
     } else {
         panic!("Set SERVO_PATH to the root of your servo repository to build local webidl bindings.");
     };
+    println!("cargo:rerun-if-env-changed=SERVO_PATH");
 
-    // TODO: Don't hardcode..
     let cwd = env::current_dir().unwrap();
-    let style_out_dir = PathBuf::from(format!("{}/target/release/build/style-712769e00544c534/out/css-properties.json", cwd.display()));
+    let webidls_dir = cwd.join("webidls");
+    let css_properties = find_css_properties_json(build_dir);
 
-    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let mut inputs: Vec<PathBuf> = fs::read_dir(&webidls_dir)
+        .expect("Can't read the webidls directory")
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    inputs.sort();
+    inputs.push(css_properties.clone());
+
+    println!("cargo:rerun-if-changed={}", webidls_dir.display());
+    for input in &inputs {
+        println!("cargo:rerun-if-changed={}", input.display());
+    }
+
+    let hash = webidl_inputs_hash(&inputs);
+    let hash_file = out_dir.join(".webidl-hash");
+    if webidl_bindings_up_to_date(&hash_file, hash) {
+        return None;
+    }
 
     let status = Command::new(find_python())
         .arg(format!("{}/components/script/dom/bindings/codegen/run.py", servo_path))
-        .arg(style_out_dir)
-        .arg(cwd.join("webidls"))
-        .arg(&out_dir)
+        .arg(css_properties)
+        .arg(webidls_dir)
+        .arg(out_dir)
         .status()
         .unwrap();
     if !status.success() {
         std::process::exit(1)
     }
+    Some(hash)
+}
+
+/// Record `hash` and the outputs in `out_dir` for `webidl_bindings_up_to_date`. Called once
+/// all the build steps are done, as the others write to `out_dir` at the same time as the
+/// codegen.
+fn record_webidl_bindings(out_dir: &Path, hash: u64) {
+    let hash_file = out_dir.join(".webidl-hash");
+    let mut record = hash.to_string();
+    for entry in fs::read_dir(out_dir).unwrap().filter_map(Result::ok) {
+        if entry.file_name() != ".webidl-hash" {
+            record.push('\n');
+            record.push_str(&entry.path().to_string_lossy());
+        }
+    }
+    if let Err(error) = fs::write(&hash_file, record) {
+        println!("cargo:warning=Could not write {}: {}", hash_file.display(), error);
+    }
 }
 
+// Embed the icon and manifest in the executable. `winres` is only available when the
+// host is Windows, so when cross-compiling (or with `WEBVIEWER_SKIP_WINRES=1`) we skip
+// this step and produce a binary without them.
+fn compile_windows_resources() {
+    println!("cargo:rerun-if-env-changed=WEBVIEWER_SKIP_WINRES");
+    if env::var("WEBVIEWER_SKIP_WINRES").is_ok_and(|value| value == "1") {
+        println!("cargo:warning=WEBVIEWER_SKIP_WINRES is set, not embedding the icon and manifest");
+        return;
+    }
+
+    #[cfg(windows)]
+    {
+        let mut res = winres::WindowsResource::new();
+        res.set_icon("../../resources/servo.ico");
+        res.set_manifest_file("platform/windows/servo.exe.manifest");
+        res.compile().unwrap();
+    }
+    #[cfg(not(windows))]
+    println!(
+        "cargo:warning=Cross-compiling to windows, not embedding the icon and manifest \
+         (winres requires a windows host)"
+    );
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    generate_webidl_bindings();
+    // Emitting any rerun-if-changed disables Cargo's default of rerunning on any change
+    // in the package, so list the other files this script depends on as well.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=platform");
 
     println!("cargo::rustc-check-cfg=cfg(servo_production)");
     println!("cargo::rustc-check-cfg=cfg(servo_do_not_use_in_production)");
@@ -101,46 +303,55 @@ fn main() -> Result<(), Box<dyn Error>> {
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
     let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap();
 
-    if target_os == "windows" {
-        #[cfg(windows)]
+    // run.py emits whole-program files (e.g. InterfaceObjectMap.rs, RegisterBindings.rs)
+    // from the complete set of interfaces, so it can't be sharded across several python
+    // processes. Instead, run it in the background while we do the other build steps.
+    let webidl_hash = thread::scope(|scope| {
+        let codegen = scope.spawn(|| generate_webidl_bindings(out, build));
+
+        if target_os == "windows" {
+            compile_windows_resources();
+        } else if target_os == "macos" {
+            cc::Build::new()
+                .file("platform/macos/count_threads.c")
+                .compile("count_threads");
+        } else if target_os == "android" {
+            generate_egl_bindings(out);
+
+            // FIXME: We need this workaround since jemalloc-sys still links
+            // to libgcc instead of libunwind, but Android NDK 23c and above
+            // don't have libgcc. We can't disable jemalloc for Android as
+            // in 64-bit aarch builds, the system allocator uses tagged
+            // pointers by default which causes the assertions in SM & mozjs
+            // to fail. See https://github.com/servo/servo/issues/32175.
+            let mut libgcc = File::create(out.join("libgcc.a")).unwrap();
+            libgcc.write_all(b"INPUT(-lunwind)").unwrap();
+            println!("cargo:rustc-link-search=native={}", out.display());
+        } else if target_env == "ohos" {
+            generate_egl_bindings(out);
+        }
+
+        if let Err(error) = EmitBuilder::builder()
+            .fail_on_error()
+            .git_sha(true /* short */)
+            .git_branch()
+            .git_dirty(false /* include_untracked */)
+            .emit()
         {
-            let mut res = winres::WindowsResource::new();
-            res.set_icon("../../resources/servo.ico");
-            res.set_manifest_file("platform/windows/servo.exe.manifest");
-            res.compile().unwrap();
+            println!(
+                "cargo:warning=Could not generate git version information: {:?}",
+                error
+            );
+            // Keep these in sync with the placeholders checked in version.rs.
+            println!("cargo:rustc-env=VERGEN_GIT_SHA=nogit");
+            println!("cargo:rustc-env=VERGEN_GIT_BRANCH=nobranch");
+            println!("cargo:rustc-env=VERGEN_GIT_DIRTY=false");
         }
-        #[cfg(not(windows))]
-        panic!("Cross-compiling to windows is currently not supported");
-    } else if target_os == "macos" {
-        cc::Build::new()
-            .file("platform/macos/count_threads.c")
-            .compile("count_threads");
-    } else if target_os == "android" {
-        generate_egl_bindings(out);
-
-        // FIXME: We need this workaround since jemalloc-sys still links
-        // to libgcc instead of libunwind, but Android NDK 23c and above
-        // don't have libgcc. We can't disable jemalloc for Android as
-        // in 64-bit aarch builds, the system allocator uses tagged
-        // pointers by default which causes the assertions in SM & mozjs
-        // to fail. See https://github.com/servo/servo/issues/32175.
-        let mut libgcc = File::create(out.join("libgcc.a")).unwrap();
-        libgcc.write_all(b"INPUT(-lunwind)").unwrap();
-        println!("cargo:rustc-link-search=native={}", out.display());
-    } else if target_env == "ohos" {
-        generate_egl_bindings(out);
-    }
 
-    if let Err(error) = EmitBuilder::builder()
-        .fail_on_error()
-        .git_sha(true /* short */)
-        .emit()
-    {
-        println!(
-            "cargo:warning=Could not generate git version information: {:?}",
-            error
-        );
-        println!("cargo:rustc-env=VERGEN_GIT_SHA=nogit");
+        codegen.join().expect("WebIDL codegen failed")
+    });
+    if let Some(hash) = webidl_hash {
+        record_webidl_bindings(out, hash);
     }
 
     // On MacOS, all dylib dependencies are shipped along with the binary
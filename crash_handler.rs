@@ -18,6 +18,10 @@ pub fn install() {
         // infinite recursion if the printing causes another signal.
         static BEEN_HERE_BEFORE: atomic::AtomicBool = atomic::AtomicBool::new(false);
         if !BEEN_HERE_BEFORE.swap(true, atomic::Ordering::SeqCst) {
+            // This is async-signal-safe, so it goes first, in case what follows crashes.
+            #[cfg(not(target_env = "ohos"))]
+            crate::desktop::crash_reporter::write_signal_report(sig);
+
             // stderr is unbuffered, so we won’t lose output if we crash later
             // in this handler, and the std::io::stderr() call never allocates.
             // std::io::stdout() allocates the first time it’s called, which in
@@ -34,6 +38,7 @@ pub fn install() {
             // we’re handling a non-main-thread (e.g. layout) segfault. Strictly
             // speaking in POSIX terms, this is also undefined behaviour.
             let _ = backtrace::print(&mut stderr);
+            drop(stderr);
         }
 
         // Outside the BEEN_HERE_BEFORE check, we must only call functions we
@@ -6,33 +6,51 @@
 
 use std::cell::{Cell, RefCell, RefMut};
 use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::Instant;
-use std::{env, fs};
+use std::time::{Duration, Instant};
 
+use euclid::{Point2D, Size2D, Vector2D};
 use gleam::gl;
-use log::{info, trace, warn};
+use log::{error, info, trace, warn};
+use serde_json::json;
+use servo::base::id::TopLevelBrowsingContextId as WebViewId;
 use servo::compositing::windowing::EmbedderEvent;
 use servo::compositing::CompositeTarget;
 use servo::config::{opts, set_pref};
 use servo::servo_config::pref;
+use servo::servo_url::ServoUrl;
+use servo::webrender_api::units::{DeviceIntRect, DeviceRect};
 use servo::Servo;
 use surfman::GLApi;
 use webxr::glwindow::GlWindowDiscovery;
 #[cfg(target_os = "windows")]
 use webxr::openxr::{AppInfo, OpenXrDiscovery};
+use winit::dpi::LogicalPosition;
 use winit::event::WindowEvent;
 use winit::event_loop::EventLoopWindowTarget;
 use winit::window::WindowId;
 
+use super::device_emulation::Emulation;
 use super::events_loop::{EventsLoop, WakerEvent};
 use super::minibrowser::Minibrowser;
-use super::webview::WebViewManager;
+use super::webview::{LoadStatus, ServoEventResponse, WebViewManager, WebViewManagerOptions};
 use super::{headed_window, headless_window};
+use crate::desktop::control::{
+    ControlAddress, ControlCommand, ControlReply, ControlRequest, ControlServer, PageRect,
+};
 use crate::desktop::embedder::{EmbedderCallbacks, XrDiscovery};
+use crate::desktop::memory;
+use crate::desktop::power_save::UNFOCUSED_FRAME_INTERVAL;
+use crate::desktop::screenshot;
+use crate::desktop::session::{self, Session};
 use crate::desktop::tracing::trace_winit_event;
+use crate::desktop::url_list::UrlList;
+use crate::desktop::user_scripts::UserScripts;
+use crate::desktop::window_geometry::{self, WindowGeometry};
 use crate::desktop::window_trait::WindowPortsMethods;
-use crate::parser::get_default_url;
+use crate::parser::{get_default_url, location_bar_input_to_url};
 
 pub struct App {
     servo: Option<Servo<dyn WindowPortsMethods>>,
@@ -41,6 +59,69 @@ pub struct App {
     suspended: Cell<bool>,
     windows: HashMap<WindowId, Rc<dyn WindowPortsMethods>>,
     minibrowser: Option<RefCell<Minibrowser>>,
+    exit_after_load: Option<ExitAfterLoad>,
+    url_list: Option<UrlList>,
+    exit_code: Rc<Cell<i32>>,
+    /// Where to save a screenshot of the next frame presented, if any, the rect of the page to
+    /// save if not all of it, and who to tell about it.
+    pending_screenshot: Option<(PathBuf, Option<PageRect>, Option<ControlReply>)>,
+    /// Whether to save the open tabs on shutdown.
+    save_session: bool,
+    /// The open tabs when quitting was requested, before Servo starts closing them.
+    quit_session: Option<Session>,
+    /// Takes commands from automation tools, with `--control-socket` or `--control-port`.
+    control: Option<ControlServer>,
+    /// The scripts injected into every page, if any.
+    user_scripts: Option<UserScripts>,
+}
+
+/// The options of [`App::run`], from the command line.
+pub struct AppOptions {
+    pub no_native_titlebar: bool,
+    pub device_pixel_ratio_override: Option<f32>,
+    pub software_rendering: bool,
+    pub user_agent: Option<String>,
+    /// The pages to open, or the home page if there are none.
+    pub urls: Vec<ServoUrl>,
+    pub exit_after_load: Option<ExitAfterLoad>,
+    pub url_list: Option<UrlList>,
+    /// Whether to open the tabs that were open when the app last shut down.
+    pub restore_session: bool,
+    pub emulation: Option<Emulation>,
+    /// Whether `--window-size` was given, which wins over the size of the last window.
+    pub window_size_given: bool,
+    pub window_position: Option<LogicalPosition<f64>>,
+    /// Where the control server listens, if anywhere.
+    pub control_address: Option<ControlAddress>,
+    pub user_scripts: Option<UserScripts>,
+    pub webviews: WebViewManagerOptions,
+}
+
+/// Options for `--exit-after-load`: wait for the initial page to load, optionally save a
+/// screenshot of it, then exit. Exits with a non-zero code on panic or timeout, or if the page
+/// fails to load.
+///
+/// TODO: Fail on HTTP error statuses too, which Servo doesn't tell the embedder, so a page that
+/// loads as a 404 counts as loaded for now.
+pub struct ExitAfterLoad {
+    /// The tab of the initial page, once Servo was told to open it.
+    webview_id: Option<WebViewId>,
+    screenshot_path: Option<PathBuf>,
+    deadline: Instant,
+    loaded: bool,
+    quit_requested: bool,
+}
+
+impl ExitAfterLoad {
+    pub fn new(screenshot_path: Option<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            webview_id: None,
+            screenshot_path,
+            deadline: Instant::now() + timeout,
+            loaded: false,
+            quit_requested: false,
+        }
+    }
 }
 
 enum Present {
@@ -60,15 +141,40 @@ enum PumpResult {
 }
 
 impl App {
-    pub fn run(
-        no_native_titlebar: bool,
-        device_pixel_ratio_override: Option<f32>,
-        user_agent: Option<String>,
-        url: Option<String>,
-    ) {
+    pub fn run(options: AppOptions) -> i32 {
+        let AppOptions {
+            no_native_titlebar,
+            device_pixel_ratio_override,
+            software_rendering,
+            user_agent,
+            urls,
+            exit_after_load,
+            url_list,
+            restore_session,
+            emulation,
+            window_size_given,
+            window_position,
+            control_address,
+            user_scripts,
+            webviews: webview_options,
+        } = options;
+        let private = webview_options.private;
         let events_loop = EventsLoop::new(opts::get().headless, opts::get().output_file.is_some())
             .expect("Failed to create events loop");
 
+        let control = match control_address {
+            Some(address) => {
+                match ControlServer::start(&address, events_loop.create_event_loop_waker()) {
+                    Ok(control) => Some(control),
+                    Err(error) => {
+                        error!("Failed to start the control server: {error}");
+                        return 1;
+                    },
+                }
+            },
+            None => None,
+        };
+
         // Implements window methods, used by compositor.
         let window = if opts::get().headless {
             // GL video rendering is not supported on headless windows.
@@ -78,19 +184,53 @@ impl App {
                 device_pixel_ratio_override,
             )
         } else {
+            // The window opens where it was when the app last shut down, unless told otherwise.
+            let saved_geometry = if private {
+                None
+            } else {
+                window_geometry::load()
+            };
+            let window_size = match saved_geometry {
+                Some(geometry) if !window_size_given => {
+                    Size2D::new(geometry.width, geometry.height).to_u32()
+                },
+                _ => opts::get().initial_window_size,
+            };
+            let window_position = window_position.or_else(|| {
+                saved_geometry.map(|geometry| LogicalPosition::new(geometry.x, geometry.y))
+            });
             Rc::new(headed_window::Window::new(
-                opts::get().initial_window_size,
+                window_size,
+                window_position,
                 &events_loop,
                 no_native_titlebar,
                 device_pixel_ratio_override,
+                software_rendering,
             ))
         };
+        if emulation.is_some() {
+            window.set_emulation(emulation);
+        }
 
         // Handle browser state.
-        let webviews = WebViewManager::new(window.clone());
-        let initial_url = get_default_url(url.as_deref(), env::current_dir().unwrap(), |path| {
-            fs::metadata(path).is_ok()
-        });
+        let webviews = WebViewManager::new(
+            window.clone(),
+            webview_options,
+            events_loop.create_event_loop_waker(),
+        );
+        let initial_url = urls
+            .first()
+            .cloned()
+            .unwrap_or_else(|| get_default_url(None, env::current_dir().unwrap(), |_| false));
+
+        // Headless runs are usually automated, so they neither restore nor save the session.
+        // Private windows don't either, so they leave no trace of their tabs.
+        let save_session = !opts::get().headless && !private;
+        let mut restored_session = if save_session && restore_session {
+            session::load()
+        } else {
+            None
+        };
 
         let mut app = App {
             event_queue: RefCell::new(vec![]),
@@ -99,7 +239,16 @@ impl App {
             suspended: Cell::new(false),
             windows: HashMap::new(),
             minibrowser: None,
+            exit_after_load,
+            url_list,
+            exit_code: Rc::new(Cell::new(0)),
+            pending_screenshot: None,
+            save_session,
+            quit_session: None,
+            control,
+            user_scripts,
         };
+        let exit_code = app.exit_code.clone();
 
         if opts::get().minibrowser && window.winit_window().is_some() {
             // Make sure the gl context is made current.
@@ -116,7 +265,13 @@ impl App {
             debug_assert_eq!(webrender_gl.get_error(), gleam::gl::NO_ERROR);
 
             app.minibrowser = Some(
-                Minibrowser::new(&rendering_context, &events_loop, initial_url.clone()).into(),
+                Minibrowser::new(
+                    &rendering_context,
+                    &events_loop,
+                    initial_url.clone(),
+                    private,
+                )
+                .into(),
             );
         }
 
@@ -192,10 +347,30 @@ impl App {
                 );
                 let mut servo = servo_data.servo;
 
-                servo.handle_events(vec![EmbedderEvent::NewWebView(
-                    initial_url.to_owned(),
-                    servo_data.browser_id,
-                )]);
+                let new_webview_events = match restored_session.take() {
+                    Some(restored_session) => app.webviews.borrow_mut().tabs_mut().restore(
+                        &restored_session,
+                        urls.clone(),
+                        servo_data.browser_id,
+                    ),
+                    None if urls.is_empty() => vec![EmbedderEvent::NewWebView(
+                        initial_url.to_owned(),
+                        servo_data.browser_id,
+                    )],
+                    None => app.webviews.borrow_mut().tabs_mut().open(
+                        urls.clone(),
+                        0,
+                        servo_data.browser_id,
+                    ),
+                };
+                if let Some(ref mut exit_after_load) = app.exit_after_load {
+                    exit_after_load.webview_id =
+                        new_webview_events.iter().find_map(|event| match event {
+                            EmbedderEvent::NewWebView(_, webview_id) => Some(*webview_id),
+                            _ => None,
+                        });
+                }
+                servo.handle_events(new_webview_events);
                 servo.setup_logging();
 
                 app.windows.insert(window.id(), window.clone());
@@ -228,7 +403,7 @@ impl App {
                     minibrowser.paint(window.winit_window().unwrap());
                 }
 
-                app.servo.as_mut().unwrap().present();
+                app.present(&*window);
             }
 
             // Handle the event
@@ -290,13 +465,42 @@ impl App {
 
             let animating = app.is_animating();
 
-            // Block until the window gets an event
+            // Block until the window gets an event, or a deadline passes.
+            app.webviews.borrow_mut().check_power_save();
+            let discard_deadline = app.webviews.borrow_mut().discard_idle_tabs();
+            let hint_deadline = app
+                .minibrowser()
+                .and_then(|minibrowser| minibrowser.fullscreen_hint_deadline());
+            if hint_deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                window.winit_window().unwrap().request_redraw();
+            }
             if !animating || app.suspended.get() {
-                control_flow.set_wait();
+                let deadline = app
+                    .exit_after_load
+                    .as_ref()
+                    .map(|exit_after_load| exit_after_load.deadline)
+                    .into_iter()
+                    .chain(app.url_list.as_ref().and_then(UrlList::deadline))
+                    .chain(discard_deadline)
+                    .chain(hint_deadline)
+                    .min();
+                match deadline {
+                    Some(deadline) => control_flow.set_wait_until(deadline),
+                    None => control_flow.set_wait(),
+                }
+            } else if app.webviews.borrow().power_saving() &&
+                !window
+                    .winit_window()
+                    .map_or(true, |window| window.has_focus())
+            {
+                // Animate at a lower frame rate while the window is in the background.
+                control_flow.set_wait_until(Instant::now() + UNFOCUSED_FRAME_INTERVAL);
             } else {
                 control_flow.set_poll();
             }
 
+            app.handle_control_requests();
+
             // Consume and handle any events from the Minibrowser.
             if let Some(minibrowser) = app.minibrowser() {
                 let webviews = &mut app.webviews.borrow_mut();
@@ -307,6 +511,25 @@ impl App {
             match app.handle_events() {
                 PumpResult::Shutdown => {
                     control_flow.set_exit();
+                    if app.save_session {
+                        let saved_session = app
+                            .quit_session
+                            .take()
+                            .unwrap_or_else(|| Session::from_tabs(app.webviews.borrow().tabs()));
+                        session::save(&saved_session);
+                    }
+                    app.webviews.borrow_mut().save_page_zooms();
+                    // Like the session, the window geometry isn't saved by private or headless
+                    // runs.
+                    let geometry = app
+                        .windows
+                        .values()
+                        .next()
+                        .and_then(|window| window.winit_window())
+                        .and_then(WindowGeometry::of);
+                    if let Some(geometry) = geometry.filter(|_| app.save_session) {
+                        window_geometry::save(&geometry);
+                    }
                     app.servo.take().unwrap().deinit();
                     if let Some(mut minibrowser) = app.minibrowser() {
                         minibrowser.context.destroy();
@@ -345,7 +568,7 @@ impl App {
                                 );
                                 minibrowser.paint(window.winit_window().unwrap());
                             }
-                            app.servo.as_mut().unwrap().present();
+                            app.present(&*window);
                         },
                         Present::Deferred => {
                             // The compositor has painted to this frame.
@@ -356,14 +579,319 @@ impl App {
                             if let Some(window) = window.winit_window() {
                                 window.request_redraw();
                             } else {
-                                app.servo.as_mut().unwrap().present();
+                                app.present(&*window);
                             }
                         },
                         Present::None => {},
                     }
                 },
             }
+
+            // Make sure we get to process a Quit queued by --exit-after-load or --url-list.
+            if app
+                .exit_after_load
+                .as_ref()
+                .is_some_and(|e| e.quit_requested) ||
+                app.url_list.as_ref().is_some_and(UrlList::is_done)
+            {
+                control_flow.set_poll();
+            }
         });
+
+        exit_code.get()
+    }
+
+    /// Present the frame composited by Servo, first saving a screenshot of it if one was
+    /// requested.
+    fn present(&mut self, window: &dyn WindowPortsMethods) {
+        if let Some((path, clip, reply)) = self.pending_screenshot.take() {
+            let result = self.save_screenshot(window, &path, clip);
+            let exit_after_load = self.exit_after_load.as_ref().is_some_and(|e| e.loaded);
+            match result {
+                Ok(()) => info!("Saved screenshot to {}", path.display()),
+                Err(ref error) => {
+                    error!("Failed to save screenshot: {error}");
+                    if exit_after_load {
+                        self.exit_code.set(1);
+                    }
+                },
+            }
+            if let Some(reply) = reply {
+                match result {
+                    Ok(()) => reply.ok(json!({})),
+                    Err(error) => reply.error(error),
+                }
+            }
+            if exit_after_load {
+                self.request_exit_after_load_quit();
+            }
+        }
+        let start = self
+            .webviews
+            .borrow()
+            .frame_stats()
+            .is_enabled()
+            .then(Instant::now);
+        self.servo.as_mut().unwrap().present();
+        if let Some(start) = start {
+            self.webviews
+                .borrow_mut()
+                .frame_stats_mut()
+                .record_present(start, Instant::now());
+        }
+    }
+
+    /// Save the focused webview's area of the frame that is about to be presented, or only the
+    /// `clip` rect of its page.
+    fn save_screenshot(
+        &self,
+        window: &dyn WindowPortsMethods,
+        path: &Path,
+        clip: Option<PageRect>,
+    ) -> Result<(), String> {
+        let framebuffer_size = window.get_coordinates().framebuffer;
+        let framebuffer_rect = DeviceIntRect::from_size(framebuffer_size);
+
+        // Webview rects have their origin at the top left, but GL's is at the bottom left.
+        let webviews = self.webviews.borrow();
+        let webview = webviews
+            .focused_webview_id()
+            .and_then(|id| webviews.get(id));
+        let rect = webview
+            .and_then(|webview| match clip {
+                // A CSS pixel of the page is this many device pixels, ignoring pinch zoom.
+                Some(clip) => {
+                    let scale = window.hidpi_factor().get() * webview.page_zoom;
+                    DeviceRect::from_origin_and_size(
+                        webview.rect.min + Vector2D::new(clip.x, clip.y) * scale,
+                        Size2D::new(clip.width, clip.height) * scale,
+                    )
+                    .intersection(&webview.rect)
+                },
+                None => Some(webview.rect),
+            })
+            .map(|rect| rect.round_out().to_i32())
+            .and_then(|rect| {
+                DeviceIntRect::new(
+                    Point2D::new(rect.min.x, framebuffer_size.height - rect.max.y),
+                    Point2D::new(rect.max.x, framebuffer_size.height - rect.min.y),
+                )
+                .intersection(&framebuffer_rect)
+            });
+        let rect = match (rect, clip) {
+            (Some(rect), _) => rect,
+            (None, Some(_)) => return Err("The rect is outside of the page".to_owned()),
+            (None, None) => framebuffer_rect,
+        };
+
+        let fbo = self.servo.as_ref().unwrap().offscreen_framebuffer_id();
+        screenshot::save_png(&window.rendering_context(), fbo, rect, path)
+    }
+
+    /// Update the `--exit-after-load` state for the given response from Servo.
+    fn handle_exit_after_load(&mut self, response: &ServoEventResponse) {
+        let Some(ref mut exit_after_load) = self.exit_after_load else {
+            return;
+        };
+        if let Some(ref reason) = response.panic {
+            error!("Panic while loading the page: {reason}");
+            self.exit_code.set(1);
+            self.request_exit_after_load_quit();
+        } else if response.load_complete && !exit_after_load.loaded {
+            // Other tabs, such as popups, may finish loading first.
+            let webviews = self.webviews.borrow();
+            let Some(webview) = exit_after_load
+                .webview_id
+                .and_then(|webview_id| webviews.get(webview_id))
+                .filter(|webview| webview.load_status == LoadStatus::LoadComplete)
+            else {
+                return;
+            };
+            let failure = webview
+                .load_failed
+                .then(|| webview.title.clone().unwrap_or_default());
+            drop(webviews);
+            exit_after_load.loaded = true;
+            if let Some(reason) = failure {
+                error!("Failed to load the page: {reason}");
+                self.exit_code.set(1);
+                self.request_exit_after_load_quit();
+            } else if let Some(path) = exit_after_load.screenshot_path.take() {
+                self.request_screenshot(path, None, None);
+            } else {
+                self.request_exit_after_load_quit();
+            }
+        } else if Instant::now() >= exit_after_load.deadline && !exit_after_load.quit_requested {
+            error!("Timed out waiting for the page to load");
+            self.exit_code.set(1);
+            self.request_exit_after_load_quit();
+        }
+    }
+
+    /// Load the next URL of `--url-list` once the current one is over, or write the report and
+    /// quit once there are none left.
+    fn handle_url_list(&mut self, response: &ServoEventResponse) {
+        let Some(ref mut url_list) = self.url_list else {
+            return;
+        };
+        if url_list.is_done() {
+            return;
+        }
+        if let Some(url) = url_list.update(response) {
+            if let Some(webview_id) = self.webviews.borrow().focused_webview_id() {
+                self.event_queue
+                    .borrow_mut()
+                    .push(EmbedderEvent::LoadUrl(webview_id, url));
+            }
+        } else if url_list.is_done() {
+            match url_list.write_report() {
+                Ok(true) => {},
+                Ok(false) => self.exit_code.set(1),
+                Err(error) => {
+                    error!("{error}");
+                    self.exit_code.set(1);
+                },
+            }
+            self.event_queue.borrow_mut().push(EmbedderEvent::Quit);
+        }
+    }
+
+    /// Save a screenshot to `path` when the next frame is presented, of the `clip` rect of the
+    /// page if given, then send the result to `reply` if given.
+    fn request_screenshot(
+        &mut self,
+        path: PathBuf,
+        clip: Option<PageRect>,
+        reply: Option<ControlReply>,
+    ) {
+        self.pending_screenshot = Some((path, clip, reply));
+        // Make sure a frame gets composited, so it can be captured.
+        self.event_queue.borrow_mut().push(EmbedderEvent::Refresh);
+    }
+
+    /// Run the commands received by the control server since the last call.
+    fn handle_control_requests(&mut self) {
+        let Some(ref control) = self.control else {
+            return;
+        };
+        for ControlRequest { command, reply } in control.take_requests() {
+            match command {
+                ControlCommand::Navigate { url: input } => {
+                    let webview_id = self.webviews.borrow().focused_webview_id();
+                    match (webview_id, location_bar_input_to_url(&input)) {
+                        (Some(webview_id), Some(url)) => {
+                            self.event_queue
+                                .borrow_mut()
+                                .push(EmbedderEvent::LoadUrl(webview_id, url));
+                            reply.ok(json!({}));
+                        },
+                        (None, _) => reply.error("There is no tab to navigate"),
+                        (_, None) => reply.error(format!("Not a URL: {input}")),
+                    }
+                },
+                ControlCommand::OpenTab { url: input } => {
+                    let webviews = self.webviews.borrow();
+                    let url = match input {
+                        Some(ref input) => location_bar_input_to_url(input),
+                        None => Some(webviews.new_tab_url()),
+                    };
+                    match url {
+                        Some(url) => {
+                            self.event_queue
+                                .borrow_mut()
+                                .push(webviews.tabs().new_tab(url));
+                            // Bring the window to the front, as if the app had been launched.
+                            for window in self.windows.values() {
+                                if let Some(winit_window) = window.winit_window() {
+                                    winit_window.focus_window();
+                                }
+                            }
+                            reply.ok(json!({}));
+                        },
+                        None => reply.error(format!("Not a URL: {}", input.unwrap_or_default())),
+                    }
+                },
+                ControlCommand::Screenshot {
+                    selector: Some(_), ..
+                } => {
+                    // TODO: Find the element, scroll it into view, and stitch several captures
+                    // together for elements taller than the viewport, which needs Servo to let
+                    // the embedder query the layout of a page.
+                    reply.error("Servo can't find elements on behalf of the embedder yet")
+                },
+                ControlCommand::Screenshot {
+                    path, rect: clip, ..
+                } => self.request_screenshot(path, clip, Some(reply)),
+                ControlCommand::Eval { script } => {
+                    trace!("Not evaluating {script:?}");
+                    reply.error("Servo can't evaluate scripts on behalf of the embedder yet");
+                },
+                // TODO: Simulate slow and offline networks, with presets like "offline", "slow3g"
+                // and "fast3g", and custom bandwidth and latency, here and in a dev menu. This
+                // needs Servo's network stack to let the embedder delay, throttle and fail the
+                // requests it makes over the network (but not for local resources), which it
+                // has no hook for.
+                ControlCommand::Throttle { profile } => {
+                    trace!("Not throttling to {profile:?}");
+                    reply.error("Servo can't throttle network requests for the embedder yet");
+                },
+                // TODO: Reply with the accessibility tree of the active tab as JSON, with the
+                // role, name, states and bounds of each node, leaving out ids and other fields
+                // that change from run to run so that dumps can be diffed, and export it from a
+                // dev menu too. This needs Servo to build an accessibility tree and hand it to
+                // the embedder, which it doesn't do yet.
+                ControlCommand::A11yTree => {
+                    reply.error("Servo doesn't expose an accessibility tree to the embedder yet");
+                },
+                // TODO: Freeze the page of the active tab, pausing its timers, animation frames
+                // and tasks while it is still painted and scrolled, then deliver the queued
+                // events once unfrozen, here and from a dev menu toggle. This needs Servo to let
+                // the embedder pause the event loop of a webview's script thread, and to keep
+                // frozen pages from being navigated, which it has no way to do. Throttling a
+                // webview, as power saving does, only slows its timers down.
+                ControlCommand::Freeze { frozen } => {
+                    trace!("Not freezing (frozen: {frozen})");
+                    reply.error("Servo can't freeze the scripts of a page for the embedder yet");
+                },
+                ControlCommand::DevicePixelRatio { ratio } => match ratio {
+                    Some(ratio) if !(ratio.is_finite() && ratio > 0.0) => {
+                        reply.error(format!("Invalid device pixel ratio: {ratio}"))
+                    },
+                    ratio => {
+                        self.webviews
+                            .borrow_mut()
+                            .set_device_pixel_ratio_override(ratio);
+                        reply.ok(json!({}));
+                    },
+                },
+                ControlCommand::MemoryReport => {
+                    reply.ok(json!({ "processes": memory::memory_report() }));
+                },
+                ControlCommand::AddUserScript { source } => match self.user_scripts {
+                    Some(ref mut user_scripts) => match user_scripts.add(&source) {
+                        Ok(()) => reply.ok(json!({})),
+                        Err(error) => reply.error(format!("Failed to add user script: {error}")),
+                    },
+                    None => reply.error("User scripts are not enabled"),
+                },
+                ControlCommand::Quit => {
+                    self.event_queue.borrow_mut().push(EmbedderEvent::Quit);
+                    reply.ok(json!({}));
+                },
+                ControlCommand::Hello { .. } => {
+                    unreachable!("The handshake is handled by the control server")
+                },
+            }
+        }
+    }
+
+    fn request_exit_after_load_quit(&mut self) {
+        if let Some(ref mut exit_after_load) = self.exit_after_load {
+            if !exit_after_load.quit_requested {
+                exit_after_load.quit_requested = true;
+                self.event_queue.borrow_mut().push(EmbedderEvent::Quit);
+            }
+        }
     }
 
     fn is_animating(&self) -> bool {
@@ -402,6 +930,14 @@ impl App {
                     if event == winit::event::WindowEvent::RedrawRequested {
                         self.event_queue.borrow_mut().push(EmbedderEvent::Idle);
                     }
+                    // The minibrowser opens dropped files in the tab they were dropped on.
+                    if let winit::event::WindowEvent::DroppedFile(ref path) = event {
+                        if self.minibrowser.is_none() {
+                            self.webviews
+                                .borrow_mut()
+                                .open_dropped_files(None, vec![path.clone()]);
+                        }
+                    }
 
                     window.queue_embedder_events_for_winit_event(event);
                 },
@@ -444,20 +980,30 @@ impl App {
         let mut need_resize = false;
         let mut need_present = false;
         let mut need_update = false;
+        let mut load_response = ServoEventResponse::default();
         loop {
             // Consume and handle those embedder messages.
             let servo_event_response = webviews.handle_servo_events(embedder_messages);
             need_present |= servo_event_response.need_present;
             need_update |= servo_event_response.need_update;
+            load_response.load_complete |= servo_event_response.load_complete;
+            load_response.panic = load_response.panic.or(servo_event_response.panic);
+
+            // Remember the open tabs as of when quitting was requested.
+            let embedder_events = webviews.get_events();
+            if self.save_session &&
+                self.quit_session.is_none() &&
+                embedder_events
+                    .iter()
+                    .any(|event| matches!(event, EmbedderEvent::Quit))
+            {
+                self.quit_session = Some(Session::from_tabs(webviews.tabs()));
+            }
 
             // Route embedder events from the WebViewManager to the relevant Servo components,
             // receives and collects embedder messages from various Servo components,
             // and runs the compositor.
-            need_resize |= self
-                .servo
-                .as_mut()
-                .unwrap()
-                .handle_events(webviews.get_events());
+            need_resize |= self.servo.as_mut().unwrap().handle_events(embedder_events);
             if webviews.shutdown_requested() {
                 return PumpResult::Shutdown;
             }
@@ -468,6 +1014,13 @@ impl App {
                 break;
             }
         }
+        let screenshot_requested = webviews.take_screenshot_request();
+        drop(webviews);
+        if screenshot_requested {
+            self.request_screenshot(screenshot::default_path(), None, None);
+        }
+        self.handle_exit_after_load(&load_response);
+        self.handle_url_list(&load_response);
 
         let present = if need_resize {
             Present::Immediate
@@ -0,0 +1,430 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Bookmarks in nested folders, kept in `bookmarks.json` in the config dir, with import and
+//! export in the Netscape bookmark file format that other browsers use.
+
+use std::path::PathBuf;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::config_dir;
+
+pub type BookmarkId = u64;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Bookmark {
+    pub id: BookmarkId,
+    /// The folder this is in, or None for the top level, which is shown in the bookmarks bar.
+    pub folder: Option<BookmarkId>,
+    pub title: String,
+    /// The bookmarked URL, or None if this is a folder.
+    pub url: Option<String>,
+}
+
+impl Bookmark {
+    pub fn is_folder(&self) -> bool {
+        self.url.is_none()
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct BookmarksFile {
+    next_id: BookmarkId,
+    /// Every bookmark and folder, in the order they are listed in their folders.
+    bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Default)]
+pub struct Bookmarks {
+    path: Option<PathBuf>,
+    file: BookmarksFile,
+    /// Incremented on every change, so the UI knows to redraw.
+    generation: u64,
+}
+
+impl Bookmarks {
+    /// Read the saved bookmarks, or start with none in private windows.
+    pub fn load(private: bool) -> Self {
+        let path = config_dir::get()
+            .filter(|_| !private)
+            .map(|dir| dir.join("bookmarks.json"));
+        let file = path
+            .as_deref()
+            .and_then(config_dir::read_json)
+            .unwrap_or_default();
+        Self {
+            path,
+            file,
+            generation: 0,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&self, id: BookmarkId) -> Option<&Bookmark> {
+        self.file
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.id == id)
+    }
+
+    /// The bookmarks and folders in `folder`, or at the top level if None.
+    pub fn list(&self, folder: Option<BookmarkId>) -> impl Iterator<Item = &Bookmark> {
+        self.file
+            .bookmarks
+            .iter()
+            .filter(move |bookmark| bookmark.folder == folder)
+    }
+
+    /// The bookmarks whose URL or title contains `text`, ignoring case.
+    pub fn search(&self, text: &str, limit: usize) -> Vec<&Bookmark> {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            return vec![];
+        }
+        self.file
+            .bookmarks
+            .iter()
+            .filter(|bookmark| {
+                bookmark
+                    .url
+                    .as_ref()
+                    .is_some_and(|url| url.to_lowercase().contains(&text)) ||
+                    (!bookmark.is_folder() && bookmark.title.to_lowercase().contains(&text))
+            })
+            .take(limit)
+            .collect()
+    }
+
+    /// Bookmark `url` at the end of `folder`, returning the id of the new bookmark.
+    pub fn add(&mut self, url: &str, title: &str, folder: Option<BookmarkId>) -> BookmarkId {
+        self.insert(folder, title, Some(url.to_owned()))
+    }
+
+    /// Add a folder at the end of `folder`, returning the id of the new folder.
+    pub fn add_folder(&mut self, title: &str, folder: Option<BookmarkId>) -> BookmarkId {
+        self.insert(folder, title, None)
+    }
+
+    /// The folder with the given `/` separated path, creating any of its folders that don't
+    /// exist yet. An empty path is the top level.
+    pub fn folder_by_path(&mut self, path: &str) -> Option<BookmarkId> {
+        let mut folder = None;
+        for title in path
+            .split('/')
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+        {
+            let existing = self
+                .list(folder)
+                .find(|bookmark| bookmark.is_folder() && bookmark.title == title)
+                .map(|bookmark| bookmark.id);
+            folder = Some(existing.unwrap_or_else(|| self.add_folder(title, folder)));
+        }
+        folder
+    }
+
+    /// Remove a bookmark, or a folder and everything in it.
+    pub fn remove(&mut self, id: BookmarkId) {
+        let mut removed = vec![id];
+        while let Some(id) = removed.pop() {
+            removed.extend(self.list(Some(id)).map(|bookmark| bookmark.id));
+            self.file.bookmarks.retain(|bookmark| bookmark.id != id);
+        }
+        self.generation += 1;
+    }
+
+    /// Move a bookmark or folder to the end of `folder`. Returns false if there is no such
+    /// bookmark, or if that would move a folder into itself.
+    pub fn move_to(&mut self, id: BookmarkId, folder: Option<BookmarkId>) -> bool {
+        let mut ancestor = folder;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                return false;
+            }
+            ancestor = self.get(ancestor_id).and_then(|bookmark| bookmark.folder);
+        }
+        let Some(index) = self.file.bookmarks.iter().position(|b| b.id == id) else {
+            return false;
+        };
+        let mut bookmark = self.file.bookmarks.remove(index);
+        bookmark.folder = folder;
+        self.file.bookmarks.push(bookmark);
+        self.generation += 1;
+        true
+    }
+
+    /// Write the bookmarks to disk.
+    pub fn save(&self) {
+        let Some(ref path) = self.path else {
+            warn!("No config dir to save the bookmarks to");
+            return;
+        };
+        let result = config_dir::write_json(path, &self.file);
+        if let Err(error) = result {
+            warn!("Failed to save bookmarks to {}: {error}", path.display());
+        }
+    }
+
+    /// Add the bookmarks in a Netscape bookmark file to the top level, returning how many were
+    /// imported.
+    ///
+    /// This is the HTML based format that browsers export bookmarks in, where each folder is a
+    /// `<DT><H3>` followed by a `<DL>` of its contents, and each bookmark is a `<DT><A HREF>`.
+    /// Anything else, like icons and dates, is ignored.
+    pub fn import_html(&mut self, html: &str) -> usize {
+        let mut count = 0;
+        let mut folders = vec![];
+        let mut folder = None;
+        // The folder of the last <H3>, which the next <DL> belongs to.
+        let mut heading = None;
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[..end];
+            rest = &rest[end + 1..];
+            let name = tag
+                .split(|c: char| c.is_whitespace())
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            match &*name {
+                "H3" => {
+                    let title = text_until(rest, "</");
+                    heading = Some(self.add_folder(&unescape(title.trim()), folder));
+                },
+                "DL" => {
+                    folders.push(folder);
+                    // The outermost list is the top level itself.
+                    folder = heading.take().or(folder);
+                },
+                "/DL" => {
+                    folder = folders.pop().flatten();
+                },
+                "A" => {
+                    if let Some(url) = attribute(tag, "HREF") {
+                        let title = text_until(rest, "</");
+                        self.add(&unescape(&url), &unescape(title.trim()), folder);
+                        count += 1;
+                    }
+                },
+                _ => {},
+            }
+        }
+        self.generation += 1;
+        count
+    }
+
+    /// All the bookmarks, as a Netscape bookmark file.
+    pub fn export_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+             <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+             <TITLE>Bookmarks</TITLE>\n\
+             <H1>Bookmarks</H1>\n",
+        );
+        self.export_folder(None, 0, &mut html);
+        html
+    }
+
+    fn export_folder(&self, folder: Option<BookmarkId>, depth: usize, html: &mut String) {
+        let indent = "    ".repeat(depth);
+        html.push_str(&format!("{indent}<DL><p>\n"));
+        for bookmark in self.list(folder) {
+            let title = escape(&bookmark.title);
+            match bookmark.url {
+                Some(ref url) => html.push_str(&format!(
+                    "{indent}    <DT><A HREF=\"{}\">{title}</A>\n",
+                    escape(url)
+                )),
+                None => {
+                    html.push_str(&format!("{indent}    <DT><H3>{title}</H3>\n"));
+                    self.export_folder(Some(bookmark.id), depth + 1, html);
+                },
+            }
+        }
+        html.push_str(&format!("{indent}</DL><p>\n"));
+    }
+
+    fn insert(
+        &mut self,
+        folder: Option<BookmarkId>,
+        title: &str,
+        url: Option<String>,
+    ) -> BookmarkId {
+        let id = self.file.next_id;
+        self.file.next_id += 1;
+        self.file.bookmarks.push(Bookmark {
+            id,
+            folder,
+            title: title.to_owned(),
+            url,
+        });
+        self.generation += 1;
+        id
+    }
+}
+
+/// The text up to `end`, or all of it.
+fn text_until<'a>(text: &'a str, end: &str) -> &'a str {
+    text.find(end).map_or(text, |index| &text[..index])
+}
+
+/// The value of the attribute `name` in the contents of a tag, if it is quoted.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let upper = tag.to_ascii_uppercase();
+    let mut search_from = 0;
+    while let Some(index) = upper[search_from..].find(name) {
+        let start = search_from + index;
+        search_from = start + name.len();
+        let preceded_by_space = tag[..start].ends_with(char::is_whitespace);
+        let value = tag[search_from..].trim_start();
+        if !preceded_by_space || !value.starts_with('=') {
+            continue;
+        }
+        let value = value[1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        return Some(text_until(&value[1..], &quote.to_string()).to_owned());
+    }
+    None
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The bookmarks and folders in `folder` and below, one per line, indented by depth.
+#[cfg(test)]
+fn test_tree(bookmarks: &Bookmarks, folder: Option<BookmarkId>, depth: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for bookmark in bookmarks.list(folder) {
+        let indent = "  ".repeat(depth);
+        match bookmark.url {
+            Some(ref url) => lines.push(format!("{indent}{} {url}", bookmark.title)),
+            None => {
+                lines.push(format!("{indent}{}/", bookmark.title));
+                lines.extend(test_tree(bookmarks, Some(bookmark.id), depth + 1));
+            },
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+fn test_round_trip(bookmarks: &Bookmarks) -> Bookmarks {
+    let mut imported = Bookmarks::default();
+    let count = imported.import_html(&bookmarks.export_html());
+    let urls = bookmarks.file.bookmarks.iter().filter(|b| !b.is_folder());
+    assert_eq!(count, urls.count());
+    assert_eq!(test_tree(&imported, None, 0), test_tree(bookmarks, None, 0));
+    imported
+}
+
+#[test]
+fn test_html_round_trip_of_nested_folders() {
+    let mut bookmarks = Bookmarks::default();
+    bookmarks.add("https://servo.org/", "Servo", None);
+    let rust = bookmarks.add_folder("Rust", None);
+    bookmarks.add("https://doc.rust-lang.org/book/", "The Book", Some(rust));
+    let crates = bookmarks.add_folder("Crates", Some(rust));
+    bookmarks.add("https://crates.io/", "crates.io", Some(crates));
+    bookmarks.add_folder("Empty", Some(crates));
+    bookmarks.add("https://docs.rs/", "Docs", Some(rust));
+    bookmarks.add("https://example.com/", "Last", None);
+
+    test_round_trip(&bookmarks);
+    assert_eq!(
+        test_tree(&bookmarks, None, 0),
+        [
+            "Servo https://servo.org/",
+            "Rust/",
+            "  The Book https://doc.rust-lang.org/book/",
+            "  Crates/",
+            "    crates.io https://crates.io/",
+            "    Empty/",
+            "  Docs https://docs.rs/",
+            "Last https://example.com/",
+        ]
+    );
+}
+
+#[test]
+fn test_html_escaping() {
+    let mut bookmarks = Bookmarks::default();
+    let folder = bookmarks.add_folder("Q&A <\"quoted\">", None);
+    bookmarks.add(
+        "https://example.com/?a=1&b=\"2\"",
+        "Tom & Jerry's \"best\" <episodes>",
+        Some(folder),
+    );
+    bookmarks.add("https://example.com/&amp;", "Already &amp; escaped", None);
+
+    let html = bookmarks.export_html();
+    assert!(html.contains("<H3>Q&amp;A &lt;&quot;quoted&quot;&gt;</H3>"));
+    assert!(html.contains("HREF=\"https://example.com/?a=1&amp;b=&quot;2&quot;\""));
+    assert!(html.contains(">Tom &amp; Jerry's &quot;best&quot; &lt;episodes&gt;</A>"));
+    assert!(html.contains(">Already &amp;amp; escaped</A>"));
+    test_round_trip(&bookmarks);
+
+    // Other browsers escape apostrophes too, and may use single quotes and lower case.
+    let mut imported = Bookmarks::default();
+    let count = imported.import_html(
+        "<dl><p><dt><a href='https://example.com/?q=a&amp;b' ADD_DATE=\"1\">It&#39;s</a></dl>",
+    );
+    assert_eq!(count, 1);
+    assert_eq!(
+        test_tree(&imported, None, 0),
+        ["It's https://example.com/?q=a&b"]
+    );
+}
+
+#[test]
+fn test_html_import_of_malformed_input() {
+    let mut bookmarks = Bookmarks::default();
+    assert_eq!(bookmarks.import_html(""), 0);
+    assert_eq!(bookmarks.import_html("not a bookmark file"), 0);
+    assert_eq!(bookmarks.import_html("<DL><p><DT><H3>Unclosed"), 0);
+    assert!(bookmarks.file.bookmarks.iter().all(Bookmark::is_folder));
+
+    // Unquoted values are skipped, lists closed too often close the top level, and the input
+    // may end in the middle of a tag.
+    let mut bookmarks = Bookmarks::default();
+    let count = bookmarks.import_html(
+        "<DL><p><DT><A HREF=https://unquoted.example/>Unquoted</A></DL></DL></DL>\
+         <DT><A HREF=\"https://a.example/\">A</A><DT><A HREF=\"https://b.example/\">B</a",
+    );
+    assert_eq!(count, 2);
+    assert_eq!(
+        test_tree(&bookmarks, None, 0),
+        ["A https://a.example/", "B https://b.example/"]
+    );
+
+    // A folder heading without a list is an empty folder, and what follows stays where it was.
+    let mut bookmarks = Bookmarks::default();
+    let count = bookmarks
+        .import_html("<DL><DT><H3>Folder</H3><DT><A HREF=\"https://a.example/\">A</A></DL>");
+    assert_eq!(count, 1);
+    assert_eq!(
+        test_tree(&bookmarks, None, 0),
+        ["Folder/", "A https://a.example/"]
+    );
+}
@@ -2,15 +2,38 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{env, panic, process};
 
 use getopts::Options;
 use log::error;
 use servo::config::opts::{self, ArgumentParsingResult};
-use servo::servo_config::pref;
+use servo::servo_config::{pref, set_pref};
+use servo::servo_url::ServoUrl;
+use winit::dpi::LogicalPosition;
 
-use crate::desktop::app::App;
+use crate::desktop::app::{App, AppOptions, ExitAfterLoad};
+use crate::desktop::content_scripts;
+use crate::desktop::control::ControlAddress;
+use crate::desktop::crash_reporter;
+use crate::desktop::device_emulation::Emulation;
+#[cfg(target_os = "linux")]
+use crate::desktop::font_dirs::FontDirs;
+use crate::desktop::fonts::FontSettings;
+use crate::desktop::frame_stats::FrameStats;
+use crate::desktop::new_tab::NEW_TAB_LOCATION;
+use crate::desktop::search_engines::{search_engine_template, SEARCH_ENGINES};
+use crate::desktop::single_instance::{Acquired, SingleInstance};
+use crate::desktop::url_list::{self, UrlList};
+use crate::desktop::user_scripts::UserScripts;
+use crate::desktop::webview::WebViewManagerOptions;
 use crate::panic_hook;
+use crate::parser::get_default_url;
+
+const USER_AGENT_PRESETS: &[&str] = &["desktop", "android", "ios"];
 
 pub fn main() {
     crate::crash_handler::install();
@@ -26,13 +49,29 @@ pub fn main() {
         "Do not shutdown until all threads have finished (macos only)",
     );
     opts.optflag("b", "no-native-titlebar", "Do not use native titlebar");
-    opts.optopt("", "device-pixel-ratio", "Device pixels per px", "");
+    opts.optflag(
+        "",
+        "software-webrender",
+        "Render with the CPU instead of the GPU, which is much slower, for when the GPU glitches",
+    );
+    opts.optopt(
+        "",
+        "device-pixel-ratio",
+        "Device pixels per px, instead of the screen's",
+        "2.0",
+    );
     opts.optopt(
         "u",
         "user-agent",
         "Set custom user agent string (or ios / android / desktop for platform default)",
         "NCSA Mosaic/1.0 (X11;SunOS 4.1.4 sun4m)",
     );
+    opts.optopt(
+        "",
+        "user-agent-preset",
+        "Use the default user agent string of another platform (ignored if --user-agent is given)",
+        "desktop|android|ios",
+    );
     opts.optmulti(
         "",
         "pref",
@@ -45,6 +84,212 @@ pub fn main() {
         "A preference to set to disable",
         "dom.webgpu.enabled=false",
     );
+    opts.optflag(
+        "",
+        "exit-after-load",
+        "Exit once the page has loaded, with a non-zero code on panic or timeout",
+    );
+    opts.optopt(
+        "",
+        "screenshot",
+        "Save a screenshot of the page to this PNG file once loaded, then exit (implies -z)",
+        "screenshot.png",
+    );
+    opts.optopt(
+        "",
+        "load-timeout",
+        "Seconds to wait for the page to load with --exit-after-load, or for each page of \
+         --url-list (default 30)",
+        "30",
+    );
+    opts.optopt(
+        "",
+        "url-list",
+        "Load each URL in this file, one per line, in turn and headless, then report how each \
+         load went and exit, with a non-zero code if any failed",
+        "urls.txt",
+    );
+    opts.optopt(
+        "",
+        "url-list-report",
+        "Write the report of --url-list to this JSON file, or CSV if it ends with .csv, instead \
+         of stdout",
+        "report.json",
+    );
+    opts.optflag(
+        "",
+        "private",
+        "Private mode: keep cookies, storage, history and the open tabs in memory only",
+    );
+    opts.optflag(
+        "",
+        "no-restore",
+        "Do not restore the tabs that were open when the app was last closed",
+    );
+    opts.optopt(
+        "",
+        "search-engine",
+        "Search with this engine, by name or as a URL with %s for the query",
+        "duckduckgo",
+    );
+    opts.optopt(
+        "",
+        "home",
+        "Open this page on startup and with the home button (the shell.homepage pref)",
+        "https://servo.org",
+    );
+    opts.optopt(
+        "",
+        "new-tab-page",
+        "Open this page in new tabs, instead of the recent history and bookmarks (new-tab:)",
+        "new-tab:",
+    );
+    opts.optopt(
+        "",
+        "frame-log",
+        "Log the time taken by every frame to this CSV file",
+        "frames.csv",
+    );
+    opts.optflag(
+        "",
+        "power-save",
+        "Throttle background tabs and pause their media, and animate less while the window is \
+         unfocused, as is done on battery power by default",
+    );
+    // Servo only caches network responses in memory, so this isn't about what is written to disk,
+    // but about loading everything fresh, like a local server being worked on. The cache can be
+    // turned back on from the toolbar.
+    opts.optflag(
+        "",
+        "incognito-network",
+        "Don't cache network responses, while keeping history and cookies unlike a private window",
+    );
+    opts.optopt(
+        "",
+        "discard-after",
+        "Discard background tabs left alone for this many seconds, reloading them when activated",
+        "1800",
+    );
+    opts.optopt(
+        "",
+        "window-position",
+        "Open the window at this position on screen, in px, rather than where it was last closed \
+         (--window-size does the same for its size)",
+        "100,100",
+    );
+    opts.optopt(
+        "",
+        "emulate",
+        "Emulate the screen of a device, by name or as WIDTHxHEIGHT@RATIO in CSS pixels",
+        "Pixel 7",
+    );
+    opts.optflag(
+        "",
+        "links-in-foreground",
+        "Open links Ctrl-clicked (Cmd-clicked on macOS) or middle-clicked in the foreground, and \
+         in the background with Shift too, rather than the other way around",
+    );
+    opts.optflag(
+        "",
+        "deny-all-permissions",
+        "Deny every permission that pages ask for, without prompting",
+    );
+    opts.optopt(
+        "",
+        "autoplay",
+        "Whether pages may play media without being clicked first (default allow). Blocked media \
+         is paused, since Servo can't tell whether it has sound, and sites can be allowed to play \
+         it from the toolbar",
+        "allow|block",
+    );
+    opts.optmulti(
+        "",
+        "user-script",
+        "Run this script in every page once it has been parsed",
+        "script.js",
+    );
+    opts.optopt(
+        "",
+        "scripts-manifest",
+        "Run the scripts and stylesheets listed in this JSON file in the pages matching their \
+         patterns",
+        "scripts.json",
+    );
+    opts.optmulti(
+        "",
+        "fonts-dir",
+        "Load the fonts in this directory too, for systems without fonts (Linux only)",
+        "/path/to/fonts",
+    );
+    opts.optopt(
+        "",
+        "default-font-size",
+        "Size of text that pages don't size themselves, in px",
+        "16",
+    );
+    opts.optopt(
+        "",
+        "min-font-size",
+        "Make text smaller than this many px as large, once the page has been parsed",
+        "12",
+    );
+    opts.optopt(
+        "",
+        "font-family",
+        "Font family of text that pages don't choose a font for",
+        "serif",
+    );
+    opts.optopt(
+        "",
+        "monospace-font-family",
+        "Font family of code that pages don't choose a font for",
+        "monospace",
+    );
+    opts.optmulti(
+        "",
+        "user-script-exclude",
+        "Do not run user scripts in pages of this origin",
+        "https://example.com",
+    );
+    opts.optflag(
+        "",
+        "single-instance",
+        "Open the URLs in the app if it is already running with this config dir, instead of \
+         starting another instance",
+    );
+    opts.optopt(
+        "",
+        "control-socket",
+        "Accept automation commands on this Unix domain socket",
+        "/tmp/servoshell.sock",
+    );
+    opts.optopt(
+        "",
+        "control-port",
+        "Accept automation commands on this TCP port of localhost",
+        "7000",
+    );
+    opts.optopt(
+        "",
+        "profile-dir",
+        "Keep all state, like history, bookmarks, cookies, prefs and the session, in this \
+         directory instead of the default config dir",
+        "profiles/work",
+    );
+    // `--webdriver [port]` is parsed by Servo, which then serves the W3C WebDriver protocol itself
+    // (with the `webdriver` feature), driving the app through the same embedder messages as
+    // pages do, so standard clients work with it.
+    //
+    // TODO: Map the capabilities of new sessions to our options, like headless, the window size
+    // and the user agent. Servo's WebDriver server doesn't hand them to the embedder, and those
+    // options can only be set at startup anyway.
+    //
+    // `--devtools [port]` is parsed by Servo too, which then serves the remote debugging protocol
+    // of Firefox on that port, for Firefox's about:debugging to connect to as a network location.
+    // The port it listens on is printed once it starts. Every tab is a target of its own. The
+    // server is off unless asked for, since anyone who can connect to it can run scripts in any
+    // page. Servo speaks the protocol of the Firefox release it was last tested against, so a
+    // much newer Firefox may fail to show some panels.
 
     let opts_matches;
     let content_process_token;
@@ -60,11 +305,53 @@ pub fn main() {
         },
     };
 
+    // A profile is a config dir of its own, which all state is kept in: Servo's cookies and
+    // local storage, `prefs.json`, and our history, bookmarks, session and the like. Without
+    // either, that's `~/.config/servo` on Linux, `~/Library/Application Support/Servo` on macOS
+    // and `%APPDATA%\Servo` on Windows. Each profile has its own single-instance lock, so
+    // instances of different profiles can run at the same time.
+    if let Some(profile_dir) = opts_matches.opt_str("profile-dir").map(PathBuf::from) {
+        if opts_matches.opt_present("config-dir") {
+            error!("Error parsing option: --profile-dir and --config-dir can't both be given");
+            process::exit(1);
+        }
+        if let Err(error) = fs::create_dir_all(&profile_dir) {
+            error!("Failed to create {}: {error}", profile_dir.display());
+            process::exit(1);
+        }
+        let mut profile_opts = opts::get().clone();
+        profile_opts.config_dir = Some(profile_dir);
+        opts::set_options(profile_opts);
+    }
+
     crate::prefs::register_user_prefs(&opts_matches);
 
+    if let Some(search_engine) = opts_matches.opt_str("search-engine") {
+        let Some(template) = search_engine_template(&search_engine) else {
+            let names: Vec<_> = SEARCH_ENGINES.iter().map(|(name, ..)| *name).collect();
+            error!(
+                "Error parsing option: --search-engine must be one of {} or a URL with %s",
+                names.join(", ")
+            );
+            process::exit(1);
+        };
+        set_pref!(shell.searchpage, template);
+    }
+    if let Some(home) = opts_matches.opt_str("home") {
+        set_pref!(shell.homepage, home);
+    }
+    if opts_matches.opt_present("incognito-network") {
+        set_pref!(network.http_cache.disabled, true);
+    }
+
+    crash_reporter::install();
+
     // TODO: once log-panics is released, can this be replaced by
     // log_panics::init()?
-    panic::set_hook(Box::new(panic_hook::panic_hook));
+    panic::set_hook(Box::new(|info| {
+        crash_reporter::write_panic_report(info);
+        panic_hook::panic_hook(info);
+    }));
 
     if let Some(token) = content_process_token {
         return servo::run_content_process(token);
@@ -78,27 +365,334 @@ pub fn main() {
     let clean_shutdown = opts_matches.opt_present("clean-shutdown");
     let do_not_use_native_titlebar =
         opts_matches.opt_present("no-native-titlebar") || !(pref!(shell.native_titlebar.enabled));
-    let device_pixel_ratio_override = opts_matches.opt_str("device-pixel-ratio").map(|dppx_str| {
-        dppx_str.parse().unwrap_or_else(|err| {
-            error!("Error parsing option: --device-pixel-ratio ({})", err);
+    let device_pixel_ratio_override =
+        opts_matches
+            .opt_str("device-pixel-ratio")
+            .map(|dppx_str| match dppx_str.parse::<f32>() {
+                Ok(dppx) if dppx.is_finite() && dppx > 0.0 => dppx,
+                Ok(dppx) => {
+                    error!(
+                        "Error parsing option: --device-pixel-ratio (must be finite and positive, \
+                         not {dppx})"
+                    );
+                    process::exit(1);
+                },
+                Err(err) => {
+                    error!("Error parsing option: --device-pixel-ratio ({})", err);
+                    process::exit(1);
+                },
+            });
+
+    // An explicit --user-agent beats --user-agent-preset, which beats the default for this
+    // platform. Servo itself knows the "desktop", "android" and "ios" user agent strings.
+    // The user agent can't be changed per webview, because Servo only takes it at startup.
+    let user_agent_preset = opts_matches.opt_str("user-agent-preset").map(|preset| {
+        if !USER_AGENT_PRESETS.contains(&&*preset) {
+            error!(
+                "Error parsing option: --user-agent-preset must be one of {}",
+                USER_AGENT_PRESETS.join(", ")
+            );
+            process::exit(1);
+        }
+        preset
+    });
+    let user_agent = opts_matches.opt_str("u").or(user_agent_preset);
+
+    // --screenshot is a single shot: render headless, save the screenshot and exit. So is
+    // --url-list, for many pages.
+    let screenshot_path = opts_matches.opt_str("screenshot").map(PathBuf::from);
+    let url_list_path = opts_matches.opt_str("url-list");
+    if (screenshot_path.is_some() || url_list_path.is_some()) && !opts::get().headless {
+        let mut headless_opts = opts::get().clone();
+        headless_opts.headless = true;
+        opts::set_options(headless_opts);
+    }
+    let load_timeout =
+        Duration::from_secs(opts_matches.opt_str("load-timeout").map_or(30, |secs_str| {
+            secs_str.parse().unwrap_or_else(|err| {
+                error!("Error parsing option: --load-timeout ({})", err);
+                process::exit(1);
+            })
+        }));
+    let exit_after_load = screenshot_path.is_some() || opts_matches.opt_present("exit-after-load");
+    let exit_after_load =
+        exit_after_load.then(|| ExitAfterLoad::new(screenshot_path, load_timeout));
+
+    // Servo only writes cookies, local storage and the like to disk if it has a config dir.
+    let private = opts_matches.opt_present("private");
+    if private && opts::get().config_dir.is_some() {
+        let mut private_opts = opts::get().clone();
+        private_opts.config_dir = None;
+        opts::set_options(private_opts);
+    }
+
+    let mut control_address = match (
+        opts_matches.opt_str("control-socket"),
+        opts_matches.opt_str("control-port"),
+    ) {
+        (Some(_), Some(_)) => {
+            error!("Error parsing option: --control-socket and --control-port are exclusive");
+            process::exit(1);
+        },
+        #[cfg(unix)]
+        (Some(path), None) => Some(ControlAddress::Unix(PathBuf::from(path))),
+        #[cfg(not(unix))]
+        (Some(_), None) => {
+            error!("Error parsing option: --control-socket needs Unix, use --control-port");
+            process::exit(1);
+        },
+        (None, Some(port)) => Some(ControlAddress::Tcp(port.parse().unwrap_or_else(|err| {
+            error!("Error parsing option: --control-port ({})", err);
+            process::exit(1);
+        }))),
+        (None, None) => None,
+    };
+
+    let font_size = |name: &str| {
+        opts_matches
+            .opt_str(name)
+            .map(|px_str| match px_str.parse::<f32>() {
+                Ok(px) if px.is_finite() && px > 0.0 => px,
+                Ok(px) => {
+                    error!(
+                        "Error parsing option: --{name} (must be finite and positive, not {px})"
+                    );
+                    process::exit(1);
+                },
+                Err(err) => {
+                    error!("Error parsing option: --{} ({})", name, err);
+                    process::exit(1);
+                },
+            })
+    };
+    let fonts = FontSettings {
+        default_size: font_size("default-font-size"),
+        min_size: font_size("min-font-size"),
+        family: opts_matches.opt_str("font-family"),
+        monospace_family: opts_matches.opt_str("monospace-font-family"),
+    };
+    fonts.apply();
+
+    // Fontconfig must be told about the font directories before Servo starts using it.
+    #[cfg(target_os = "linux")]
+    let font_dirs = FontDirs::new(
+        opts_matches
+            .opt_strs("fonts-dir")
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+    )
+    .unwrap_or_else(|err| {
+        error!("Error parsing option: --fonts-dir ({})", err);
+        process::exit(1);
+    });
+    #[cfg(not(target_os = "linux"))]
+    if opts_matches.opt_present("fonts-dir") {
+        error!("Error parsing option: --fonts-dir needs Linux");
+        process::exit(1);
+    }
+
+    // User scripts can also be added through the control server, so set them up for it too.
+    // The minimum font size is applied with one.
+    let user_script_paths = opts_matches.opt_strs("user-script");
+    let content_scripts = opts_matches.opt_str("scripts-manifest").map(|path| {
+        content_scripts::load_manifest(Path::new(&path)).unwrap_or_else(|err| {
+            error!(
+                "Error parsing option: --scripts-manifest {} ({})",
+                path, err
+            );
             process::exit(1);
         })
     });
+    let min_size_script = fonts.min_size_script();
+    let needs_user_scripts = !user_script_paths.is_empty() ||
+        content_scripts.is_some() ||
+        control_address.is_some() ||
+        min_size_script.is_some();
+    let user_scripts = needs_user_scripts.then(|| {
+        let mut user_scripts = UserScripts::new(opts_matches.opt_strs("user-script-exclude"))
+            .unwrap_or_else(|err| {
+                error!("Failed to set up user scripts: {}", err);
+                process::exit(1);
+            });
+        if let Some(source) = min_size_script {
+            if let Err(err) = user_scripts.add(&source) {
+                error!("Failed to apply --min-font-size: {}", err);
+                process::exit(1);
+            }
+        }
+        for path in user_script_paths {
+            let result = fs::read_to_string(&path).and_then(|source| user_scripts.add(&source));
+            if let Err(err) = result {
+                error!("Error parsing option: --user-script {} ({})", path, err);
+                process::exit(1);
+            }
+        }
+        for script in content_scripts.iter().flatten() {
+            if let Err(err) = user_scripts.add_content_script(script) {
+                error!("Failed to apply --scripts-manifest: {}", err);
+                process::exit(1);
+            }
+        }
+        user_scripts
+    });
 
-    let user_agent = opts_matches.opt_str("u");
+    let new_tab_url = opts_matches
+        .opt_str("new-tab-page")
+        .filter(|page| page != NEW_TAB_LOCATION)
+        .map(|page| argument_to_url(&page));
+
+    // Each URL or path opens in its own tab, the first one active. `-` reads them from stdin,
+    // one per line.
+    let mut urls = vec![];
+    for argument in &opts_matches.free {
+        if argument == "-" {
+            let mut input = String::new();
+            if let Err(err) = io::stdin().read_to_string(&mut input) {
+                error!("Failed to read URLs from stdin: {}", err);
+                process::exit(1);
+            }
+            urls.extend(
+                input
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(argument_to_url),
+            );
+        } else {
+            urls.push(argument_to_url(argument));
+        }
+    }
+
+    // The first URL of the list opens in the tab that the list is then loaded in.
+    let url_list = url_list_path.map(|path| {
+        if !urls.is_empty() {
+            error!("Error parsing option: --url-list can't be given with URLs");
+            process::exit(1);
+        }
+        let list = fs::read_to_string(&path).unwrap_or_else(|err| {
+            error!("Error parsing option: --url-list {} ({})", path, err);
+            process::exit(1);
+        });
+        let list: Vec<_> = url_list::lines(&list).map(argument_to_url).collect();
+        if list.is_empty() {
+            error!("Error parsing option: --url-list {} has no URLs", path);
+            process::exit(1);
+        }
+        urls.push(list[0].clone());
+        let report_path = opts_matches.opt_str("url-list-report").map(PathBuf::from);
+        UrlList::new(list, load_timeout, report_path)
+    });
+
+    // Private windows and headless runs are separate instances by design.
+    let single_instance =
+        if opts_matches.opt_present("single-instance") && !private && !opts::get().headless {
+            match SingleInstance::acquire(&mut control_address, &urls) {
+                Ok(Acquired::Lock(lock)) => Some(lock),
+                Ok(Acquired::Forwarded) => process::exit(0),
+                Err(err) => {
+                    error!("Failed to check for a running instance: {}", err);
+                    None
+                },
+            }
+        } else {
+            None
+        };
+
+    // Offer to open the crash reports of previous runs, in tabs of their own.
+    if !opts::get().headless && !private {
+        urls.extend(crash_reporter::review_reports());
+    }
+
+    let frame_log = opts_matches.opt_str("frame-log").map(PathBuf::from);
+    let frame_stats = FrameStats::new(frame_log.as_deref()).unwrap_or_else(|err| {
+        error!("Error parsing option: --frame-log ({})", err);
+        process::exit(1);
+    });
 
-    let url_opt = if !opts_matches.free.is_empty() {
-        Some(&opts_matches.free[0][..])
-    } else {
-        None
+    let discard_after = opts_matches.opt_str("discard-after").map(|secs_str| {
+        let secs = secs_str.parse().unwrap_or_else(|err| {
+            error!("Error parsing option: --discard-after ({})", err);
+            process::exit(1);
+        });
+        Duration::from_secs(secs)
+    });
+
+    let emulation = opts_matches.opt_str("emulate").map(|device| {
+        Emulation::parse(&device).unwrap_or_else(|err| {
+            error!("Error parsing option: --emulate ({})", err);
+            process::exit(1);
+        })
+    });
+
+    let window_position = opts_matches.opt_str("window-position").map(|position| {
+        let parsed = position.split_once(',').and_then(|(x, y)| {
+            Some(LogicalPosition::new(
+                x.trim().parse().ok()?,
+                y.trim().parse().ok()?,
+            ))
+        });
+        parsed.unwrap_or_else(|| {
+            error!("Error parsing option: --window-position must be X,Y, not {position:?}");
+            process::exit(1);
+        })
+    });
+
+    let block_autoplay = match opts_matches.opt_str("autoplay").as_deref() {
+        None | Some("allow") => false,
+        Some("block") => true,
+        Some(policy) => {
+            error!("Error parsing option: --autoplay must be allow or block, not {policy:?}");
+            process::exit(1);
+        },
     };
 
-    App::run(
-        do_not_use_native_titlebar,
+    let webviews = WebViewManagerOptions {
+        private,
+        deny_all_permissions: opts_matches.opt_present("deny-all-permissions"),
+        block_autoplay,
+        new_tab_url,
+        frame_stats,
+        discard_after,
+        links_in_foreground: opts_matches.opt_present("links-in-foreground"),
+        // Servo knows the user agents of these platforms by name, see --user-agent-preset.
+        mobile_by_default: matches!(user_agent.as_deref(), Some("android" | "ios")),
+        power_save: opts_matches.opt_present("power-save"),
+    };
+    let exit_code = App::run(AppOptions {
+        no_native_titlebar: do_not_use_native_titlebar,
         device_pixel_ratio_override,
+        software_rendering: opts_matches.opt_present("software-webrender"),
         user_agent,
-        url_opt.map(|s| s.to_string()),
-    );
+        urls,
+        exit_after_load,
+        url_list,
+        restore_session: !opts_matches.opt_present("no-restore"),
+        emulation,
+        window_size_given: opts_matches.opt_present("window-size"),
+        window_position,
+        control_address,
+        user_scripts,
+        webviews,
+    });
+
+    // Release the lock and remove the font configuration before exiting, which skips
+    // destructors.
+    drop(single_instance);
+    #[cfg(target_os = "linux")]
+    drop(font_dirs);
+    crate::platform::deinit(clean_shutdown);
+
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
 
-    crate::platform::deinit(clean_shutdown)
+/// The URL to open for a positional argument: the canonical path of a file if there is one by
+/// that name, or else a URL or a search.
+fn argument_to_url(argument: &str) -> ServoUrl {
+    fs::canonicalize(argument)
+        .ok()
+        .and_then(|path| ServoUrl::from_file_path(path).ok())
+        .unwrap_or_else(|| get_default_url(Some(argument), env::current_dir().unwrap(), |_| false))
 }
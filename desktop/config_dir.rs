@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The config dir, where the bookmarks, history, session and the like are kept, and the JSON
+//! files in it.
+//!
+//! Private windows keep nothing there: their stores start empty, and never save.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use servo::config::opts;
+use servo::servo_config::basedir;
+
+/// The config dir, given with `--config-dir` or else the default one of the platform, if there
+/// is one.
+pub fn get() -> Option<PathBuf> {
+    opts::get()
+        .config_dir
+        .clone()
+        .or_else(basedir::default_config_dir)
+}
+
+/// Read the JSON file at `path`, if there is one. A file that is unreadable or corrupt is
+/// ignored, with a warning.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    if !path.exists() {
+        return None;
+    }
+    fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|error| error.to_string()))
+        .map_err(|error| warn!("Ignoring {}: {error}", path.display()))
+        .ok()
+}
+
+/// Write `value` to the JSON file at `path`, creating its directory if needed.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|error| error.to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+    }
+    fs::write(path, json).map_err(|error| error.to_string())
+}
@@ -0,0 +1,182 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Scripts and stylesheets from `--scripts-manifest`, for the pages matching their patterns.
+//!
+//! The manifest is a JSON list of entries like the content scripts of browser extensions:
+//!
+//! ```json
+//! [
+//!     {
+//!         "matches": ["https://*.example.com/*"],
+//!         "js": "example.js",
+//!         "css": ["example.css"],
+//!         "run_at": "document_end"
+//!     }
+//! ]
+//! ```
+//!
+//! `js` and `css` are a file or a list of files, relative to the manifest. `run_at` is
+//! `document_start`, `document_end` (once the document has been parsed) or `document_idle`
+//! (once the page has loaded, the default). Stylesheets are always added at the start, so that
+//! pages don't show without them first. Entries are run as [user scripts](super::user_scripts),
+//! which check the patterns against the URL of each document.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The schemes that `<all_urls>`, and `*` as a scheme, stand for.
+const ALL_SCHEMES: &[&str] = &["http", "https", "file", "ftp", "ws", "wss"];
+const WILDCARD_SCHEMES: &[&str] = &["http", "https"];
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunAt {
+    /// As soon as the head of the document is inserted, which is when Servo runs user scripts.
+    DocumentStart,
+    /// Once the document has been parsed.
+    DocumentEnd,
+    /// Once the document and its subresources have loaded.
+    #[default]
+    DocumentIdle,
+}
+
+/// A match pattern of browser extensions, like `https://*.example.com/*`.
+pub struct MatchPattern {
+    /// The JavaScript regular expression that URLs matching the pattern match.
+    pub regex: String,
+}
+
+impl MatchPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        Self::to_regex(pattern)
+            .map(|regex| Self { regex })
+            .map_err(|reason| format!("Invalid match pattern {pattern:?}: {reason}"))
+    }
+
+    fn to_regex(pattern: &str) -> Result<String, &'static str> {
+        if pattern == "<all_urls>" {
+            return Ok(format!("^({}):", ALL_SCHEMES.join("|")));
+        }
+        let (scheme, rest) = pattern.split_once("://").ok_or("missing ://")?;
+        let scheme = match scheme {
+            "*" => format!("({})", WILDCARD_SCHEMES.join("|")),
+            scheme if ALL_SCHEMES.contains(&scheme) => scheme.to_owned(),
+            _ => return Err("unsupported scheme"),
+        };
+        let (host, path) = rest
+            .find('/')
+            .map(|index| rest.split_at(index))
+            .ok_or("missing path")?;
+        let host = host.to_ascii_lowercase();
+        let host = match host.as_str() {
+            "*" => "[^/]*".to_owned(),
+            "" if scheme == "file" => String::new(),
+            "" => return Err("missing host"),
+            host => {
+                let (subdomains, host) = match host.strip_prefix("*.") {
+                    Some(host) => ("([^/]*\\.)?", host),
+                    None => ("", host),
+                };
+                if host.contains('*') {
+                    return Err("* in the host must come first, followed by a dot");
+                }
+                // Any port matches, unless the pattern has one.
+                let port = if host.contains(':') { "" } else { "(:\\d+)?" };
+                format!("{subdomains}{}{port}", escape(host))
+            },
+        };
+        let path: Vec<_> = path.split('*').map(escape).collect();
+        Ok(format!("^{scheme}://{host}{}$", path.join(".*")))
+    }
+}
+
+/// An entry of the manifest, with its files read.
+pub struct ContentScript {
+    pub matches: Vec<MatchPattern>,
+    pub js: Vec<String>,
+    pub css: Vec<String>,
+    pub run_at: RunAt,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Files {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for Files {
+    fn default() -> Self {
+        Files::Many(vec![])
+    }
+}
+
+impl Files {
+    fn read(self, dir: &Path) -> Result<Vec<String>, String> {
+        let paths = match self {
+            Files::One(path) => vec![path],
+            Files::Many(paths) => paths,
+        };
+        paths
+            .into_iter()
+            .map(|path| {
+                let path = dir.join(path);
+                fs::read_to_string(&path).map_err(|error| format!("{}: {error}", path.display()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    matches: Vec<String>,
+    #[serde(default)]
+    js: Files,
+    #[serde(default)]
+    css: Files,
+    #[serde(default)]
+    run_at: RunAt,
+}
+
+/// Read the manifest at `path`, and the files of its entries. Any invalid pattern or missing
+/// file fails the whole manifest, so that mistakes show up at startup.
+pub fn load_manifest(path: &Path) -> Result<Vec<ContentScript>, String> {
+    let json = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let entries: Vec<Entry> = serde_json::from_str(&json).map_err(|error| error.to_string())?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            if entry.matches.is_empty() {
+                return Err(format!("Entry {index} has no match patterns"));
+            }
+            Ok(ContentScript {
+                matches: entry
+                    .matches
+                    .iter()
+                    .map(|pattern| MatchPattern::parse(pattern))
+                    .collect::<Result<_, _>>()?,
+                js: entry.js.read(dir)?,
+                css: entry.css.read(dir)?,
+                run_at: entry.run_at,
+            })
+        })
+        .collect()
+}
+
+/// Escape the characters that have a meaning in JavaScript regular expressions.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
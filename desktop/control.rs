@@ -0,0 +1,375 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A control server, so that test harnesses and other tools can drive the app.
+//!
+//! With `--control-socket <path>` (Unix only) or `--control-port <port>` (localhost only), the
+//! app accepts connections and reads one JSON command per line from each of them. Every command
+//! gets exactly one JSON reply on its own line, in order, with `"ok": true` and any results, or
+//! `"ok": false` and an `"error"` message.
+//!
+//! This is a lighter alternative to WebDriver, which Servo serves itself with `--webdriver`, for
+//! what WebDriver can't do, like reporting memory or adding user scripts.
+//!
+//! Clients must start with a handshake, and the connection is closed if it fails:
+//!
+//! - `{"cmd": "hello", "version": 2}` replies `{"ok": true, "version": 2, "servo": "..."}`
+//!   if the app speaks that version of the protocol, and `{"ok": false, "error": "...",
+//!   "version": 2}` with the version it speaks otherwise.
+//!
+//! Over TCP, the handshake must also have a `"token"`, which the app writes to
+//! `control-<port>.token` in the config dir, readable by the user only, when it starts
+//! listening. Any process could connect to the port, including web pages sending a request to
+//! localhost, so only the user's own clients get in. The connection is closed on any line that
+//! isn't a valid command too, so that whatever else connects can't get anything run.
+//!
+//! Then the commands are:
+//!
+//! - `{"cmd": "navigate", "url": "..."}` loads the URL, or searches for the text, in the active
+//!   tab. It replies once the load has started, not finished.
+//! - `{"cmd": "screenshot", "path": "..."}` saves a PNG of the active tab to the path. It replies
+//!   once the file has been written. With `"rect": {"x": 0, "y": 0, "width": 300,
+//!   "height": 200}`, in CSS pixels from the top left of the page's viewport, only that part of
+//!   the page is saved, cut off where it goes out of the viewport. The PNG has the size of the
+//!   rect times the device pixel ratio and the zoom of the page, so 600x400 at 2x and 100%.
+//!   With a `"selector"`, it always fails for now, since Servo can't find elements on behalf of
+//!   the embedder yet.
+//! - `{"cmd": "eval", "script": "..."}` always fails for now, since Servo can't evaluate scripts
+//!   on behalf of the embedder yet.
+//! - `{"cmd": "open_tab", "url": "..."}` opens the URL, or searches for the text, in a new tab.
+//!   Without a URL, it opens the new tab page.
+//! - `{"cmd": "throttle", "profile": "..."}` always fails for now, since Servo can't delay,
+//!   limit or fail its network requests on behalf of the embedder yet.
+//! - `{"cmd": "a11y_tree"}` always fails for now, since Servo doesn't build an accessibility
+//!   tree for the embedder yet.
+//! - `{"cmd": "freeze", "frozen": true}` always fails for now, since Servo can't pause the
+//!   scripts of a page on behalf of the embedder yet.
+//! - `{"cmd": "device_pixel_ratio", "ratio": 2.0}` forces the device pixel ratio, like
+//!   `--device-pixel-ratio`, and repaints every tab at it without reloading them. With
+//!   `"ratio": null`, the ratio of the screen is used again.
+//! - `{"cmd": "memory_report"}` replies with the memory used by each process, as
+//!   `{"processes": [{"pid": 123, "resident_bytes": 456}]}`.
+//! - `{"cmd": "add_user_script", "source": "..."}` injects the script into every page loaded
+//!   from then on, like `--user-script`.
+//! - `{"cmd": "quit"}` quits the app.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use servo::embedder_traits::EventLoopWaker;
+
+use super::config_dir;
+
+/// The version of the protocol, to be bumped whenever a change could break existing clients.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Where the control server listens.
+pub enum ControlAddress {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Tcp(u16),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Hello {
+        version: u32,
+        token: Option<String>,
+    },
+    Navigate {
+        url: String,
+    },
+    OpenTab {
+        url: Option<String>,
+    },
+    Screenshot {
+        path: PathBuf,
+        rect: Option<PageRect>,
+        selector: Option<String>,
+    },
+    Eval {
+        script: String,
+    },
+    Throttle {
+        profile: String,
+    },
+    A11yTree,
+    Freeze {
+        frozen: bool,
+    },
+    DevicePixelRatio {
+        ratio: Option<f32>,
+    },
+    MemoryReport,
+    AddUserScript {
+        source: String,
+    },
+    Quit,
+}
+
+/// A rect of a page, in CSS pixels from the top left of its viewport.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct PageRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A command from a client, with where to send the reply.
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: ControlReply,
+}
+
+/// Sends the reply to a command back to the client.
+pub struct ControlReply(Sender<Value>);
+
+impl ControlReply {
+    pub fn ok(self, results: Value) {
+        let mut reply = json!({ "ok": true });
+        if let (Value::Object(reply), Value::Object(results)) = (&mut reply, results) {
+            reply.extend(results);
+        }
+        // The client may have hung up, which is its business.
+        let _ = self.0.send(reply);
+    }
+
+    pub fn error(self, error: impl ToString) {
+        let _ = self
+            .0
+            .send(json!({ "ok": false, "error": error.to_string() }));
+    }
+}
+
+/// Accepts clients on background threads, and hands their commands over to the event loop.
+pub struct ControlServer {
+    receiver: Receiver<ControlRequest>,
+    /// The file holding the token of the TCP transport, removed when the server is dropped.
+    token_path: Option<PathBuf>,
+}
+
+impl ControlServer {
+    pub fn start(address: &ControlAddress, waker: Box<dyn EventLoopWaker>) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let mut token_path = None;
+        match address {
+            #[cfg(unix)]
+            ControlAddress::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                use std::os::unix::net::{UnixListener, UnixStream};
+
+                // Clean up after a previous run, but never delete anything but a socket.
+                if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                let listener = UnixListener::bind(path)?;
+                info!("Listening for control commands on {}", path.display());
+                thread::Builder::new()
+                    .name("ControlServer".to_owned())
+                    .spawn(move || {
+                        accept_clients(
+                            listener.incoming(),
+                            UnixStream::try_clone,
+                            None,
+                            sender,
+                            waker,
+                        )
+                    })?;
+            },
+            ControlAddress::Tcp(port) => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, *port))?;
+                let address = listener.local_addr()?;
+                let (path, token) = write_token(address.port())?;
+                token_path = Some(path);
+                let token = Arc::new(token);
+                info!("Listening for control commands on {address}");
+                thread::Builder::new()
+                    .name("ControlServer".to_owned())
+                    .spawn(move || {
+                        accept_clients(
+                            listener.incoming(),
+                            TcpStream::try_clone,
+                            Some(token),
+                            sender,
+                            waker,
+                        )
+                    })?;
+            },
+        }
+        Ok(Self {
+            receiver,
+            token_path,
+        })
+    }
+
+    /// Take the commands received since the last call, to be run on the event loop thread.
+    pub fn take_requests(&self) -> Vec<ControlRequest> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// The path of the file holding the token that clients must send to the control server
+/// listening on `port`.
+pub fn token_path(port: u16) -> Option<PathBuf> {
+    config_dir::get().map(|dir| dir.join(format!("control-{port}.token")))
+}
+
+/// Write a new random token for the control server listening on `port`, that only the user can
+/// read, and return where it was written and the token.
+fn write_token(port: u16) -> io::Result<(PathBuf, String)> {
+    let path = token_path(port)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "There is no config dir"))?;
+    let mut bytes = [0; 32];
+    getrandom::getrandom(&mut bytes).map_err(io::Error::other)?;
+    let token: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Remove any previous token rather than writing to it, so that the new file gets created
+    // with the right permissions, even if someone else made the old one.
+    match fs::remove_file(&path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {},
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(token.as_bytes())?;
+    Ok((path, token))
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.token_path {
+            if let Err(error) = fs::remove_file(path) {
+                warn!("Failed to remove {}: {error}", path.display());
+            }
+        }
+    }
+}
+
+fn accept_clients<S: Read + Write + Send + 'static>(
+    incoming: impl Iterator<Item = io::Result<S>>,
+    try_clone: impl Fn(&S) -> io::Result<S>,
+    token: Option<Arc<String>>,
+    sender: Sender<ControlRequest>,
+    waker: Box<dyn EventLoopWaker>,
+) {
+    for stream in incoming {
+        let streams = stream.and_then(|stream| Ok((try_clone(&stream)?, stream)));
+        match streams {
+            Ok((reader, writer)) => {
+                spawn_client(reader, writer, token.clone(), sender.clone(), waker.clone())
+            },
+            Err(error) => warn!("Failed to accept control client: {error}"),
+        }
+    }
+}
+
+fn spawn_client(
+    reader: impl Read + Send + 'static,
+    writer: impl Write + Send + 'static,
+    token: Option<Arc<String>>,
+    sender: Sender<ControlRequest>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+) {
+    let result = thread::Builder::new()
+        .name("ControlClient".to_owned())
+        .spawn(move || {
+            if let Err(error) = serve_client(reader, writer, token, sender, event_loop_waker) {
+                warn!("Control client disconnected: {error}");
+            }
+        });
+    if let Err(error) = result {
+        warn!("Failed to spawn control client thread: {error}");
+    }
+}
+
+/// Read commands from the client one line at a time, and write their replies. The client is
+/// disconnected if it doesn't start with a handshake, with `token` if there is one, or if it
+/// sends anything that isn't a command.
+fn serve_client(
+    reader: impl Read,
+    mut writer: impl Write,
+    token: Option<Arc<String>>,
+    sender: Sender<ControlRequest>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+) -> io::Result<()> {
+    let mut shook_hands = false;
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, disconnect) = match serde_json::from_str::<ControlCommand>(&line) {
+            // The handshake doesn't involve Servo, so there's no need to bother the event loop.
+            Ok(ControlCommand::Hello { version, .. }) if version != PROTOCOL_VERSION => {
+                let reply = json!({
+                    "ok": false,
+                    "error": format!("Unsupported protocol version {version}"),
+                    "version": PROTOCOL_VERSION,
+                });
+                (reply, true)
+            },
+            Ok(ControlCommand::Hello {
+                token: client_token,
+                ..
+            }) => match token {
+                Some(ref token) if client_token.as_deref() != Some(token.as_str()) => {
+                    (json!({ "ok": false, "error": "Invalid token" }), true)
+                },
+                _ => {
+                    shook_hands = true;
+                    let reply = json!({
+                        "ok": true,
+                        "version": PROTOCOL_VERSION,
+                        "servo": crate::servo_version(),
+                    });
+                    (reply, false)
+                },
+            },
+            Ok(_) if !shook_hands => {
+                let reply = json!({ "ok": false, "error": "The first command must be hello" });
+                (reply, true)
+            },
+            Ok(command) => {
+                let (reply_sender, reply_receiver) = mpsc::channel();
+                let request = ControlRequest {
+                    command,
+                    reply: ControlReply(reply_sender),
+                };
+                if sender.send(request).is_err() {
+                    // The app is shutting down.
+                    return Ok(());
+                }
+                event_loop_waker.wake();
+                let reply = reply_receiver.recv().unwrap_or_else(
+                    |_| json!({ "ok": false, "error": "The command was dropped without a reply" }),
+                );
+                (reply, false)
+            },
+            Err(error) => {
+                let reply = json!({ "ok": false, "error": format!("Invalid command: {error}") });
+                (reply, true)
+            },
+        };
+        writeln!(writer, "{reply}")?;
+        writer.flush()?;
+        if disconnect {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,276 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Crash reports, written when the app panics or gets a fatal signal, if the
+//! `shell.crash_reporter.enabled` pref is set.
+//!
+//! Each report is a text file in `crash_reports` in the config dir, with the build, the platform,
+//! the URL of the active tab, what happened and a backtrace. The next time the app starts, it
+//! offers to open the reports that haven't been looked at yet, then moves them to
+//! `crash_reports/seen`.
+//!
+//! After a fatal signal, the report is written with async-signal-safe calls only, from a path
+//! and a header prepared at startup, so it has no backtrace: that is printed to stderr by the
+//! signal handler afterwards, which may crash. The URL of the active tab is kept where it can be
+//! read without a lock, as the crashed thread may have been holding one.
+//!
+//! TODO: Write minidumps too, with a crate like `minidumper`, and offer to submit the reports
+//! somewhere once there is a server to receive them.
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use std::ffi::CString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write as _};
+use std::panic::PanicInfo;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, process, thread};
+
+use log::warn;
+use servo::servo_config::pref;
+use servo::servo_url::ServoUrl;
+use tinyfiledialogs::{MessageBoxIcon, YesNo};
+
+use super::config_dir;
+
+/// Where reports are written, once enabled.
+static REPORT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The path of the report to write after a fatal signal, and what the report starts with.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+static SIGNAL_REPORT: OnceLock<(CString, String)> = OnceLock::new();
+
+/// URLs of the active tab longer than this are cut off in reports.
+const MAX_URL_LEN: usize = 2048;
+
+/// The URL of the active tab, to include in reports, in atomics rather than behind a lock so
+/// that the signal handler can always read it. It may read a URL that is being replaced.
+static ACTIVE_URL: [AtomicU8; MAX_URL_LEN] = [const { AtomicU8::new(0) }; MAX_URL_LEN];
+static ACTIVE_URL_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Start writing crash reports, if the pref says so.
+pub fn install() {
+    if !pref!(shell.crash_reporter.enabled) {
+        return;
+    }
+    let Some(dir) = config_dir::get().map(|dir| dir.join("crash_reports")) else {
+        return;
+    };
+    if let Err(error) = fs::create_dir_all(&dir) {
+        warn!(
+            "Not writing crash reports, failed to create {}: {error}",
+            dir.display()
+        );
+        return;
+    }
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        // The signal handler can't get the time of the crash into the file name, so it uses the
+        // time the app started, which is as unique since it writes at most one report.
+        let path = dir.join(format!(
+            "crash-{}-{}-signal.txt",
+            unix_time(),
+            process::id()
+        ));
+        match CString::new(path.into_os_string().into_encoded_bytes()) {
+            Ok(path) => {
+                let _ = SIGNAL_REPORT.set((path, report_header()));
+            },
+            Err(error) => warn!("Not writing crash reports for signals: {error}"),
+        }
+    }
+    let _ = REPORT_DIR.set(dir);
+}
+
+/// Remember the URL of the active tab, for the next report.
+pub fn set_active_url(url: &str) {
+    if REPORT_DIR.get().is_none() {
+        return;
+    }
+    let url = &url.as_bytes()[..url.len().min(MAX_URL_LEN)];
+    // Readers see an empty URL until it is fully written, unless it was already being read.
+    ACTIVE_URL_LEN.store(0, Ordering::Release);
+    for (byte, stored) in url.iter().zip(&ACTIVE_URL) {
+        stored.store(*byte, Ordering::Relaxed);
+    }
+    ACTIVE_URL_LEN.store(url.len(), Ordering::Release);
+}
+
+/// The URL of the active tab, as far as it could be read.
+fn active_url() -> impl Iterator<Item = u8> {
+    let len = ACTIVE_URL_LEN.load(Ordering::Acquire);
+    ACTIVE_URL[..len]
+        .iter()
+        .map(|byte| byte.load(Ordering::Relaxed))
+}
+
+pub fn write_panic_report(info: &PanicInfo) {
+    write_report(&format!("Panic: {info}"));
+}
+
+/// Write a report for a fatal signal, which must only make async-signal-safe calls, so it
+/// doesn't allocate, lock or format.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn write_signal_report(signal: i32) {
+    let Some((path, header)) = SIGNAL_REPORT.get() else {
+        return;
+    };
+    let fd = unsafe {
+        libc::open(
+            path.as_ptr(),
+            libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC,
+            0o600,
+        )
+    };
+    if fd < 0 {
+        return;
+    }
+    let mut time: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut time) };
+    let mut url = [0; MAX_URL_LEN];
+    let mut url_len = 0;
+    for (byte, stored) in active_url().zip(&mut url) {
+        *stored = byte;
+        url_len += 1;
+    }
+    write_all(fd, header.as_bytes());
+    write_all(fd, b"Time: ");
+    write_decimal(fd, time.tv_sec as u64);
+    write_all(fd, b"\nURL: ");
+    write_all(fd, &url[..url_len]);
+    write_all(fd, b"\nCaught signal ");
+    write_decimal(fd, signal as u64);
+    write_all(fd, b"\n\nThe backtrace, if any, was printed to stderr.\n");
+    unsafe { libc::close(fd) };
+}
+
+/// Write all of `bytes` to `fd` with no more than `write()`, giving up on errors.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn write_all(fd: libc::c_int, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+        if written < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        }
+        if written <= 0 {
+            return;
+        }
+        bytes = &bytes[written as usize..];
+    }
+}
+
+/// Write `number` to `fd` in decimal, without allocating.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn write_decimal(fd: libc::c_int, mut number: u64) {
+    let mut digits = [0; 20];
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (number % 10) as u8;
+        number /= 10;
+        if number == 0 {
+            break;
+        }
+    }
+    write_all(fd, &digits[start..]);
+}
+
+/// The build and the platform, which every report starts with.
+fn report_header() -> String {
+    let mut header = String::new();
+    let _ = writeln!(header, "{}", crate::servo_version());
+    let _ = writeln!(header, "Git SHA: {}", crate::version::git_sha());
+    let _ = writeln!(
+        header,
+        "Platform: {} {}",
+        env::consts::OS,
+        env::consts::ARCH
+    );
+    header
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+fn write_report(what: &str) {
+    let Some(dir) = REPORT_DIR.get() else {
+        return;
+    };
+    let time = unix_time();
+    let mut report = report_header();
+    let _ = writeln!(report, "Time: {time}");
+    let url: Vec<u8> = active_url().collect();
+    let _ = writeln!(report, "URL: {}", String::from_utf8_lossy(&url));
+    let _ = writeln!(
+        report,
+        "Thread: {}",
+        thread::current().name().unwrap_or("<unnamed>")
+    );
+    let _ = writeln!(report, "{what}\n");
+    let path = dir.join(format!("crash-{time}-{}.txt", process::id()));
+    let result = fs::File::create(path).and_then(|mut file| {
+        file.write_all(report.as_bytes())?;
+        crate::backtrace::print(&mut file)
+    });
+    if let Err(error) = result {
+        let _ = writeln!(io::stderr(), "Failed to write crash report: {error}");
+    }
+}
+
+/// Ask whether to look at the reports written since the last time, moving them out of the way
+/// either way, and return the URLs of the reports to open.
+pub fn review_reports() -> Vec<ServoUrl> {
+    let Some(dir) = REPORT_DIR.get() else {
+        return vec![];
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let reports: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    if reports.is_empty() {
+        return vec![];
+    }
+    let open = matches!(
+        tinyfiledialogs::message_box_yes_no(
+            "Servo crashed",
+            &format!(
+                "Servo crashed since it last started, and wrote {} crash report(s). \
+                 Open them?",
+                reports.len()
+            ),
+            MessageBoxIcon::Question,
+            YesNo::Yes,
+        ),
+        YesNo::Yes
+    );
+    let seen_dir = dir.join("seen");
+    if let Err(error) = fs::create_dir_all(&seen_dir) {
+        warn!("Failed to create {}: {error}", seen_dir.display());
+        return vec![];
+    }
+    let mut urls = vec![];
+    for report in reports {
+        let Some(file_name) = report.file_name() else {
+            continue;
+        };
+        let seen = seen_dir.join(file_name);
+        if let Err(error) = fs::rename(&report, &seen) {
+            warn!("Failed to move {}: {error}", report.display());
+            continue;
+        }
+        if open {
+            urls.extend(ServoUrl::from_file_path(&seen).ok());
+        }
+    }
+    urls
+}
@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Tabs whose page panicked, and the log of those crashes.
+//!
+//! When a page panics, Servo replaces it with its own failure page, and tells the embedder. The
+//! tab then shows a page of our own instead, like the pages of
+//! [view source](super::view_source), with a link that loads the page that crashed again, in a
+//! new pipeline. The other tabs are unaffected.
+//!
+//! Every crash is appended to `crashes.log` in the config dir, one JSON object per line, with
+//! the time, the build and the reason.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde_json::json;
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+use super::view_source::escape;
+
+#[derive(Default)]
+pub struct CrashPages {
+    /// A private temporary directory for the pages, created by the first one.
+    dir: Option<PathBuf>,
+    /// How many crash pages have been written, to name the next one.
+    count: usize,
+}
+
+impl CrashPages {
+    /// Write the page shown in place of `url` after it crashed, returning its URL.
+    pub fn write(&mut self, url: &ServoUrl, reason: &str) -> Option<ServoUrl> {
+        let dir: &PathBuf = match self.dir {
+            Some(ref dir) => dir,
+            None => match tempfile::Builder::new()
+                .prefix("servoshell-crashes-")
+                .tempdir()
+            {
+                Ok(dir) => self.dir.insert(dir.into_path()),
+                Err(error) => {
+                    warn!("Failed to create a directory for crash pages: {error}");
+                    return None;
+                },
+            },
+        };
+        let path = dir.join(format!("{:04}.html", self.count));
+        self.count += 1;
+        let html = format!(
+            "<!DOCTYPE html>\n\
+             <meta charset=utf-8>\n\
+             <title>Crashed: {title}</title>\n\
+             <style>\n\
+             body {{ max-width: 40em; margin: 4em auto; padding: 0 1em; font-family: sans-serif; }}\n\
+             a {{ display: inline-block; padding: 0.5em 1em; border-radius: 4px; background: #2a6fdb; \
+             color: white; text-decoration: none; }}\n\
+             pre {{ white-space: pre-wrap; color: #666; }}\n\
+             </style>\n\
+             <h1>This tab crashed</h1>\n\
+             <p>{title}</p>\n\
+             <p><a href=\"{url}\">Reload</a></p>\n\
+             <details><summary>Details</summary><pre>{reason}</pre></details>\n",
+            title = escape(url.as_str()),
+            url = escape(url.as_str()),
+            reason = escape(reason),
+        );
+        if let Err(error) = fs::write(&path, html) {
+            warn!("Failed to write {}: {error}", path.display());
+            return None;
+        }
+        ServoUrl::from_file_path(&path).ok()
+    }
+
+    /// Whether `url` is one of the pages shown in place of a crashed page.
+    pub fn contains(&self, url: &ServoUrl) -> bool {
+        let Some(ref dir) = self.dir else {
+            return false;
+        };
+        url.to_file_path().is_ok_and(|path| path.starts_with(dir))
+    }
+}
+
+impl Drop for CrashPages {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.dir {
+            if let Err(error) = fs::remove_dir_all(dir) {
+                warn!("Failed to remove {}: {error}", dir.display());
+            }
+        }
+    }
+}
+
+/// Append a crash of the page at `url` to the crash log. Private windows leave the URL out.
+pub fn log_crash(url: Option<&ServoUrl>, reason: &str) {
+    let Some(path) = config_dir::get().map(|dir| dir.join("crashes.log")) else {
+        return;
+    };
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let entry = json!({
+        "time": time,
+        "version": crate::servo_version(),
+        "git_sha": crate::version::git_sha(),
+        "url": url.map(ServoUrl::as_str),
+        "reason": reason,
+    });
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| writeln!(file, "{entry}"));
+    if let Err(error) = result {
+        warn!("Failed to log crash to {}: {error}", path.display());
+    }
+}
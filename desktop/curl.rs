@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! "Copy as cURL" in the context menu, to request the page of the active tab again from a shell.
+//!
+//! The command is quoted for sh and the like, or for cmd.exe on Windows.
+//!
+//! TODO: Copy the method, headers and body of the request that Servo made for the page, such as
+//! the form data of a POST, flagging its Cookie and Authorization headers as sensitive. Servo
+//! doesn't tell the embedder about the requests it makes, so only the URL of the page is known,
+//! and it is requested with a GET and the headers of curl for now.
+
+use servo::servo_url::ServoUrl;
+
+/// A `curl` command line that requests `url`.
+pub fn command(url: &ServoUrl) -> String {
+    format!("curl {}", quote(url.as_str()))
+}
+
+/// Quote `arg` for a POSIX shell, where nothing is special between single quotes.
+#[cfg(not(windows))]
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Quote `arg` for cmd.exe, like browsers do: quotes are escaped for the command line parser of
+/// curl, and every other character that cmd.exe might act on is escaped with a caret.
+#[cfg(windows)]
+fn quote(arg: &str) -> String {
+    let mut quoted = r#"^""#.to_owned();
+    for c in arg.chars() {
+        match c {
+            '"' => quoted.push_str(r#"\^""#),
+            c if c.is_ascii_alphanumeric() || "_-:=+~'/.,?;()*`".contains(c) => quoted.push(c),
+            c => {
+                quoted.push('^');
+                quoted.push(c);
+            },
+        }
+    }
+    quoted.push_str(r#"^""#);
+    quoted
+}
+
+#[cfg(test)]
+fn test_command(url: &str) -> String {
+    command(&ServoUrl::parse(url).unwrap())
+}
+
+#[test]
+#[cfg(not(windows))]
+fn test_curl_command() {
+    assert_eq!(
+        test_command("https://example.com/"),
+        "curl 'https://example.com/'"
+    );
+    // Nothing is special between single quotes, but single quotes themselves.
+    assert_eq!(
+        test_command("https://example.com/?a=1&b=$HOME;`ls`|\"x\""),
+        r#"curl 'https://example.com/?a=1&b=$HOME;`ls`|%22x%22'"#
+    );
+    assert_eq!(
+        test_command("https://example.com/it's"),
+        r"curl 'https://example.com/it'\''s'"
+    );
+    assert_eq!(quote("a'b''c"), r"'a'\''b'\'''\''c'");
+    assert_eq!(quote(""), "''");
+}
+
+#[test]
+#[cfg(windows)]
+fn test_curl_command() {
+    assert_eq!(
+        test_command("https://example.com/"),
+        r#"curl ^"https://example.com/^""#
+    );
+    // Everything that cmd.exe might act on is escaped with a caret, and quotes for curl too.
+    assert_eq!(
+        test_command("https://example.com/?a=1&b=%PATH%|x"),
+        r#"curl ^"https://example.com/?a=1^&b=^%PATH^%^|x^""#
+    );
+    assert_eq!(quote(r#"say "hi" <now>"#), r#"^"say^ \^"hi\^"^ ^<now^>^""#);
+    assert_eq!(quote(""), r#"^"^""#);
+}
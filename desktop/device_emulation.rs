@@ -0,0 +1,211 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Device emulation, for testing how pages respond to the screens of other devices.
+//!
+//! While emulating a device, the page is laid out in a viewport the size of its screen, framed
+//! in the middle of the window, and content sees its screen size and `devicePixelRatio`. The
+//! window renders the page at that device pixel ratio, so the frame is as many device pixels as
+//! the emulated device has.
+//!
+//! TODO: Scale the frame down to fit the window when it doesn't, as it is cut off for now. The
+//! compositor renders at the device pixel ratio it reports to content, so this needs Servo to
+//! render webviews at a scale of their own. Emulating a device also doesn't change the user
+//! agent, which Servo only takes at startup, nor make the page receive touch events.
+
+/// A device to emulate, with the size of its screen in CSS pixels, in portrait.
+pub struct Device {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub device_pixel_ratio: f32,
+}
+
+/// The devices that can be emulated by name, from the dev menu or with `--emulate`.
+pub const DEVICES: &[Device] = &[
+    Device {
+        name: "iPhone SE",
+        width: 375,
+        height: 667,
+        device_pixel_ratio: 2.0,
+    },
+    Device {
+        name: "iPhone 15",
+        width: 393,
+        height: 852,
+        device_pixel_ratio: 3.0,
+    },
+    Device {
+        name: "Pixel 7",
+        width: 412,
+        height: 915,
+        device_pixel_ratio: 2.625,
+    },
+    Device {
+        name: "Galaxy S20",
+        width: 360,
+        height: 800,
+        device_pixel_ratio: 3.0,
+    },
+    Device {
+        name: "iPad Air",
+        width: 820,
+        height: 1180,
+        device_pixel_ratio: 2.0,
+    },
+    Device {
+        name: "Laptop",
+        width: 1280,
+        height: 800,
+        device_pixel_ratio: 1.0,
+    },
+];
+
+/// The screen being emulated, in CSS pixels, in its current orientation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emulation {
+    pub width: u32,
+    pub height: u32,
+    pub device_pixel_ratio: f32,
+}
+
+impl Emulation {
+    /// Parse the name of one of [`DEVICES`], ignoring case, spaces and dashes, or a custom screen
+    /// like `390x844@3`, where the device pixel ratio defaults to 1.
+    pub fn parse(device: &str) -> Result<Self, String> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        if let Some(device) = DEVICES
+            .iter()
+            .find(|preset| normalize(preset.name) == normalize(device))
+        {
+            return Ok(Self::from(device));
+        }
+        let invalid = || {
+            let names: Vec<_> = DEVICES.iter().map(|device| device.name).collect();
+            format!(
+                "expected one of {}, or WIDTHxHEIGHT[@RATIO], not {device:?}",
+                names.join(", ")
+            )
+        };
+        let (size, device_pixel_ratio) = match device.split_once('@') {
+            Some((size, ratio)) => (size, ratio.parse().map_err(|_| invalid())?),
+            None => (device, 1.0),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let emulation = Self {
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+            device_pixel_ratio,
+        };
+        if emulation.width == 0 ||
+            emulation.height == 0 ||
+            !device_pixel_ratio.is_finite() ||
+            device_pixel_ratio <= 0.0
+        {
+            return Err(invalid());
+        }
+        Ok(emulation)
+    }
+
+    /// The same screen, turned between portrait and landscape.
+    pub fn rotated(self) -> Self {
+        Self {
+            width: self.height,
+            height: self.width,
+            ..self
+        }
+    }
+
+    /// The size of the screen in device pixels.
+    pub fn device_size(&self) -> (f32, f32) {
+        (
+            self.width as f32 * self.device_pixel_ratio,
+            self.height as f32 * self.device_pixel_ratio,
+        )
+    }
+}
+
+impl From<&Device> for Emulation {
+    fn from(device: &Device) -> Self {
+        Self {
+            width: device.width,
+            height: device.height,
+            device_pixel_ratio: device.device_pixel_ratio,
+        }
+    }
+}
+
+#[test]
+fn test_parse_device_names() {
+    for device in DEVICES {
+        assert_eq!(Emulation::parse(device.name), Ok(Emulation::from(device)));
+    }
+    let pixel_7 = Emulation {
+        width: 412,
+        height: 915,
+        device_pixel_ratio: 2.625,
+    };
+    assert_eq!(Emulation::parse("pixel7"), Ok(pixel_7));
+    assert_eq!(Emulation::parse("PIXEL-7"), Ok(pixel_7));
+    assert_eq!(
+        Emulation::parse("iphone-se").map(|emulation| emulation.width),
+        Ok(375)
+    );
+}
+
+#[test]
+fn test_parse_custom_screens() {
+    assert_eq!(
+        Emulation::parse("390x844@3"),
+        Ok(Emulation {
+            width: 390,
+            height: 844,
+            device_pixel_ratio: 3.0,
+        })
+    );
+    assert_eq!(
+        Emulation::parse("800x600"),
+        Ok(Emulation {
+            width: 800,
+            height: 600,
+            device_pixel_ratio: 1.0,
+        })
+    );
+    for invalid in [
+        "",
+        "Pixel 8",
+        "800",
+        "800x",
+        "x600",
+        "800X600",
+        "800x600@",
+        "800x600@0",
+        "800x600@-1",
+        "800x600@NaN",
+        "800x600@inf",
+        "0x600",
+        "800x0",
+        "-800x600",
+        "800.5x600",
+    ] {
+        let error = Emulation::parse(invalid).unwrap_err();
+        assert!(error.contains("iPhone SE, iPhone 15"), "{error}");
+        assert!(error.ends_with(&format!("not {invalid:?}")), "{error}");
+    }
+}
+
+#[test]
+fn test_rotated_and_device_size() {
+    let emulation = Emulation::parse("Pixel 7").unwrap();
+    let rotated = emulation.rotated();
+    assert_eq!((rotated.width, rotated.height), (915, 412));
+    assert_eq!(rotated.device_pixel_ratio, 2.625);
+    assert_eq!(rotated.rotated(), emulation);
+    assert_eq!(emulation.device_size(), (1081.5, 2401.875));
+}
@@ -0,0 +1,417 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Downloading links, for Alt-click (Option-click on macOS), listed in the downloads menu of the
+//! toolbar.
+//!
+//! Servo doesn't tell the embedder when a navigation is a download, and can't hand it the body of
+//! a response, so like [saving the page](super::save_page) the link is fetched again outside of
+//! Servo (see [`super::fetch`]), without the cookies of the page. Files are saved in the
+//! downloads directory, named after their `Content-Disposition` or else their URL, with " (1)"
+//! and so on added when a file by that name exists already. Until they have finished, they are
+//! written to a hidden `.part` file next to it, which is removed if they fail or are cancelled.
+//!
+//! TODO: Download the responses that Servo navigates to but can't show, such as those with
+//! `Content-Disposition: attachment`, once it tells the embedder about them.
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use percent_encoding::percent_decode_str;
+use servo::embedder_traits::EventLoopWaker;
+use servo::servo_url::ServoUrl;
+use tempfile::NamedTempFile;
+
+use super::fetch;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the server may send nothing before the download fails.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the progress of a download is reported, so that the UI isn't updated for every
+/// chunk received.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Files are not numbered past this, in case the name can't be used for another reason.
+const MAX_NUMBERED_NAMES: usize = 1000;
+
+pub enum DownloadState {
+    InProgress,
+    /// Saved, at this path.
+    Done(PathBuf),
+    Failed(String),
+    Cancelled,
+}
+
+pub struct Download {
+    id: usize,
+    pub url: ServoUrl,
+    /// The name of the file, guessed from the URL until the response says otherwise.
+    pub name: String,
+    pub received: u64,
+    /// The size of the file, if the server said.
+    pub total: Option<u64>,
+    pub state: DownloadState,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Download {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// How much has been downloaded, like `1.2 MB of 3.4 MB`.
+    pub fn progress_text(&self) -> String {
+        match self.total {
+            Some(total) => format!("{} of {}", format_size(self.received), format_size(total)),
+            None => format_size(self.received),
+        }
+    }
+}
+
+enum Update {
+    /// The response has started, with the name of the file and its size if known.
+    Started(String, Option<u64>),
+    Received(u64),
+    Finished(Result<PathBuf, String>),
+}
+
+pub struct Downloads {
+    /// Every download since the list was last cleared, oldest first.
+    downloads: Vec<Download>,
+    next_id: usize,
+    sender: Sender<(usize, Update)>,
+    receiver: Receiver<(usize, Update)>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+}
+
+impl Downloads {
+    pub fn new(event_loop_waker: Box<dyn EventLoopWaker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            downloads: vec![],
+            next_id: 0,
+            sender,
+            receiver,
+            event_loop_waker,
+        }
+    }
+
+    pub fn list(&self) -> &[Download] {
+        &self.downloads
+    }
+
+    /// Start downloading `url` into the downloads directory on a background thread.
+    pub fn start(&mut self, url: ServoUrl) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.downloads.push(Download {
+            id,
+            url: url.clone(),
+            name: file_name(None, &url),
+            received: 0,
+            total: None,
+            state: DownloadState::InProgress,
+            cancelled: cancelled.clone(),
+        });
+
+        let sender = self.sender.clone();
+        let event_loop_waker = self.event_loop_waker.clone();
+        let result = thread::Builder::new()
+            .name("Download".to_owned())
+            .spawn(move || {
+                let send = |update| {
+                    let _ = sender.send((id, update));
+                    event_loop_waker.wake();
+                };
+                let result = download(&url, &cancelled, &send);
+                match result {
+                    Ok(ref path) => info!("Downloaded {url} to {}", path.display()),
+                    Err(ref error) => warn!("Failed to download {url}: {error}"),
+                }
+                send(Update::Finished(result));
+            });
+        if let Err(error) = result {
+            warn!("Failed to spawn download thread: {error}");
+            self.downloads.last_mut().unwrap().state = DownloadState::Failed(error.to_string());
+        }
+    }
+
+    /// Stop the download `id` if it is in progress, removing what it has saved so far.
+    pub fn cancel(&mut self, id: usize) {
+        let Some(download) = self.downloads.iter_mut().find(|download| download.id == id) else {
+            return;
+        };
+        if let DownloadState::InProgress = download.state {
+            download.cancelled.store(true, Ordering::Relaxed);
+            download.state = DownloadState::Cancelled;
+        }
+    }
+
+    /// Remove the downloads that are no longer in progress from the list. Their files are kept.
+    pub fn clear_finished(&mut self) {
+        self.downloads
+            .retain(|download| matches!(download.state, DownloadState::InProgress));
+    }
+
+    /// Take the progress of the downloads, returning true iff any of them has changed.
+    pub fn take_progress(&mut self) -> bool {
+        let mut changed = false;
+        for (id, update) in self.receiver.try_iter() {
+            let Some(download) = self.downloads.iter_mut().find(|download| download.id == id)
+            else {
+                continue;
+            };
+            match update {
+                Update::Started(name, total) => {
+                    download.name = name;
+                    download.total = total;
+                },
+                Update::Received(received) => download.received = received,
+                Update::Finished(result) => {
+                    let cancelled = matches!(download.state, DownloadState::Cancelled);
+                    download.state = match result {
+                        // A download that was cancelled may have finished before it noticed.
+                        Ok(path) => DownloadState::Done(path),
+                        Err(_) if cancelled => DownloadState::Cancelled,
+                        Err(error) => DownloadState::Failed(error),
+                    };
+                },
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Download `url` into the downloads directory, returning the path it was saved to.
+fn download(
+    url: &ServoUrl,
+    cancelled: &AtomicBool,
+    send: &dyn Fn(Update),
+) -> Result<PathBuf, String> {
+    let dir = dirs_next::download_dir()
+        .or_else(dirs_next::home_dir)
+        .ok_or("No downloads directory")?;
+    let agent = fetch::agent_builder()?
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build();
+    let response = agent
+        .get(url.as_str())
+        .call()
+        .map_err(|error| error.to_string())?;
+    let name = file_name(response.header("content-disposition"), url);
+    let total = response
+        .header("content-length")
+        .and_then(|length| length.parse().ok());
+    send(Update::Started(name.clone(), total));
+
+    fs::create_dir_all(&dir).map_err(|error| error.to_string())?;
+    let mut file = tempfile::Builder::new()
+        .prefix(".")
+        .suffix(".part")
+        .tempfile_in(&dir)
+        .map_err(|error| error.to_string())?;
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut received = 0;
+    let mut reported = Instant::now();
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_owned());
+        }
+        let length = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => length,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.to_string()),
+        };
+        file.write_all(&buffer[..length])
+            .map_err(|error| error.to_string())?;
+        received += length as u64;
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            send(Update::Received(received));
+            reported = Instant::now();
+        }
+    }
+    send(Update::Received(received));
+    persist(file, &dir, &name).map_err(|error| error.to_string())
+}
+
+/// Move the downloaded `file` to `name` in `dir`, or to the first of `name (1)`, `name (2)` and
+/// so on that doesn't exist yet, returning its path.
+fn persist(mut file: NamedTempFile, dir: &Path, name: &str) -> io::Result<PathBuf> {
+    for number in 0..MAX_NUMBERED_NAMES {
+        let path = dir.join(numbered_name(name, number));
+        match file.persist_noclobber(&path) {
+            Ok(_) => return Ok(path),
+            Err(error) if error.error.kind() == ErrorKind::AlreadyExists => file = error.file,
+            Err(error) => return Err(error.error),
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::AlreadyExists,
+        format!("Too many files named {name}"),
+    ))
+}
+
+/// `name` with ` (number)` before its extension, unless `number` is 0.
+fn numbered_name(name: &str, number: usize) -> String {
+    if number == 0 {
+        return name.to_owned();
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem} ({number}).{extension}"),
+        _ => format!("{name} ({number})"),
+    }
+}
+
+/// The name to save a download as, from its `Content-Disposition` if it has one, or else from
+/// the last segment of its URL. It is made safe to use as a file name, which can't be hidden
+/// nor have separators in it.
+fn file_name(content_disposition: Option<&str>, url: &ServoUrl) -> String {
+    content_disposition
+        .and_then(content_disposition_file_name)
+        .or_else(|| {
+            let segment = url.as_url().path_segments()?.last()?;
+            Some(percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        })
+        .map(|name| sanitize_file_name(&name))
+        .filter(|name| !name.is_empty())
+        .or_else(|| url.host_str().map(sanitize_file_name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "download".to_owned())
+}
+
+/// The file name in a `Content-Disposition` header, preferring the `filename*` parameter, which
+/// is percent-encoded UTF-8 (RFC 6266), to the `filename` one.
+fn content_disposition_file_name(header: &str) -> Option<String> {
+    let mut file_name = None;
+    for parameter in header.split(';').skip(1) {
+        let Some((key, value)) = parameter.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match &*key.trim().to_ascii_lowercase() {
+            "filename*" => {
+                let Some((charset, rest)) = value.split_once('\'') else {
+                    continue;
+                };
+                let Some((_language, encoded)) = rest.split_once('\'') else {
+                    continue;
+                };
+                let decoded = percent_decode_str(encoded);
+                if charset.eq_ignore_ascii_case("utf-8") {
+                    return Some(decoded.decode_utf8_lossy().into_owned());
+                }
+                // ISO-8859-1, the other charset that RFC 5987 allows, maps bytes to code points.
+                return Some(decoded.map(char::from).collect());
+            },
+            "filename" => {
+                let value = match value.strip_prefix('"') {
+                    Some(quoted) => quoted
+                        .strip_suffix('"')
+                        .unwrap_or(quoted)
+                        .replace("\\\"", "\""),
+                    None => value.to_owned(),
+                };
+                file_name = Some(value);
+            },
+            _ => {},
+        }
+    }
+    file_name
+}
+
+/// `name` without the directories it may name, and without the characters that Windows doesn't
+/// allow in file names, nor the dots that would hide the file.
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !"<>:\"|?*".contains(*c))
+        .collect();
+    name.trim_start_matches(['.', ' '])
+        .trim_end_matches(['.', ' '])
+        .to_owned()
+}
+
+/// `bytes` in kB, MB or GB like `1.2 MB`, or in bytes under a kB.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["kB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} bytes");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[test]
+fn test_download_file_name() {
+    let url = ServoUrl::parse("https://example.com/files/annual%20report.pdf?v=2").unwrap();
+    assert_eq!(file_name(None, &url), "annual report.pdf");
+    assert_eq!(
+        file_name(Some("attachment; filename=\"q3.pdf\""), &url),
+        "q3.pdf"
+    );
+    assert_eq!(
+        file_name(
+            Some("attachment; filename=\"a.pdf\"; filename*=UTF-8''%E2%82%AC%20rates.pdf"),
+            &url
+        ),
+        "€ rates.pdf"
+    );
+    assert_eq!(
+        file_name(
+            Some("attachment; FILENAME*=iso-8859-1'en'%E9t%E9.txt"),
+            &url
+        ),
+        "été.txt"
+    );
+    assert_eq!(file_name(Some("inline"), &url), "annual report.pdf");
+    // Names can't reach outside of the downloads directory, nor hide the file.
+    assert_eq!(
+        file_name(Some("attachment; filename=\"../../.bashrc\""), &url),
+        "bashrc"
+    );
+    assert_eq!(
+        file_name(Some("attachment; filename=C:\\Windows\\a<b>.exe"), &url),
+        "ab.exe"
+    );
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    assert_eq!(file_name(None, &url), "example.com");
+    let url = ServoUrl::parse("data:text/plain,hello").unwrap();
+    assert_eq!(file_name(None, &url), "download");
+}
+
+#[test]
+fn test_download_numbered_name() {
+    assert_eq!(numbered_name("report.pdf", 0), "report.pdf");
+    assert_eq!(numbered_name("report.pdf", 1), "report (1).pdf");
+    assert_eq!(numbered_name("archive.tar.gz", 2), "archive.tar (2).gz");
+    assert_eq!(numbered_name("README", 1), "README (1)");
+}
+
+#[test]
+fn test_download_format_size() {
+    assert_eq!(format_size(999), "999 bytes");
+    assert_eq!(format_size(1200), "1.2 kB");
+    assert_eq!(format_size(3_400_000), "3.4 MB");
+    assert_eq!(format_size(5_000_000_000_000_000), "5000.0 TB");
+}
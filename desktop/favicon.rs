@@ -0,0 +1,264 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Fetching, decoding and caching the favicon of each origin.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use image::imageops::FilterType;
+use image::RgbaImage;
+use log::{debug, warn};
+use resvg::{tiny_skia, usvg};
+use servo::embedder_traits::EventLoopWaker;
+use servo::servo_config::pref;
+use servo::servo_url::ServoUrl;
+
+use super::fetch;
+
+/// How big favicons are drawn, in device independent pixels.
+pub const FAVICON_SIZE: f32 = 16.0;
+
+/// Favicons bigger than this are not worth downloading.
+const MAX_FAVICON_BYTES: u64 = 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Favicon {
+    /// Not fetched yet, nor in the disk cache.
+    Missing,
+    Fetching,
+    /// The image, and the URL it came from, unless it was read from the disk cache.
+    Loaded(Option<ServoUrl>, Arc<RgbaImage>),
+    Failed,
+}
+
+type FetchResult = (String, ServoUrl, Result<RgbaImage, String>);
+
+/// The favicons of the origins visited, kept in the `favicons` directory of the config dir.
+///
+/// Favicons are fetched on background threads, outside of Servo (see [`super::fetch`]), so that
+/// they never hold up navigation. That also means that they are fetched without the cookies of
+/// the page. While Servo doesn't cache network responses, as with `--incognito-network`, the disk
+/// cache isn't read or written either. Private windows don't fetch favicons at all, so that they
+/// make no requests that Servo doesn't.
+///
+/// TODO: Fetch favicons through Servo's network layer, so that they get its cookies and cache,
+/// once it has an API for the embedder to make requests.
+pub struct Favicons {
+    cache_dir: Option<PathBuf>,
+    /// The favicon of each origin, loaded from the disk cache on first use.
+    icons: RefCell<HashMap<String, Favicon>>,
+    /// Incremented whenever a favicon is fetched, so the UI knows to redraw.
+    generation: u64,
+    sender: Sender<FetchResult>,
+    receiver: Receiver<FetchResult>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+}
+
+impl Favicons {
+    /// If `cache_dir` is None, favicons are only kept in memory.
+    pub fn new(cache_dir: Option<PathBuf>, event_loop_waker: Box<dyn EventLoopWaker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            cache_dir,
+            icons: Default::default(),
+            generation: 0,
+            sender,
+            receiver,
+            event_loop_waker,
+        }
+    }
+
+    /// The favicon of the origin of `url`, if it has been fetched. Otherwise, the UI should show
+    /// a generic icon.
+    pub fn get(&self, url: &ServoUrl) -> Option<Arc<RgbaImage>> {
+        let key = Self::key(url)?;
+        let mut icons = self.icons.borrow_mut();
+        let icon = icons.entry(key).or_insert_with_key(|key| {
+            let cached = self
+                .cache_path(key)
+                .filter(|path| path.exists())
+                .and_then(|path| image::open(path).ok());
+            match cached {
+                Some(image) => Favicon::Loaded(None, Arc::new(image.to_rgba8())),
+                None => Favicon::Missing,
+            }
+        });
+        match icon {
+            Favicon::Loaded(_, image) => Some(image.clone()),
+            Favicon::Missing | Favicon::Fetching | Favicon::Failed => None,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Fetch `icon_url` as the favicon of the origin of `page_url`, decoded at `size` pixels,
+    /// unless it is already being fetched or has been fetched from there.
+    pub fn fetch(&mut self, page_url: &ServoUrl, icon_url: ServoUrl, size: u32) {
+        let Some(key) = Self::key(page_url) else {
+            return;
+        };
+        match self.icons.get_mut().get(&key) {
+            Some(Favicon::Fetching) => return,
+            Some(Favicon::Loaded(Some(url), _)) if *url == icon_url => return,
+            _ => {},
+        }
+        self.icons.get_mut().insert(key.clone(), Favicon::Fetching);
+
+        let cache_path = self.cache_path(&key);
+        let sender = self.sender.clone();
+        let event_loop_waker = self.event_loop_waker.clone();
+        let result = thread::Builder::new()
+            .name("Favicon".to_owned())
+            .spawn(move || {
+                let result = fetch_favicon(&icon_url, size);
+                if let (Ok(image), Some(path)) = (&result, cache_path) {
+                    if let Err(error) = save_favicon(image, &path) {
+                        warn!("Failed to cache favicon {}: {error}", path.display());
+                    }
+                }
+                let _ = sender.send((key, icon_url, result));
+                event_loop_waker.wake();
+            });
+        if let Err(error) = result {
+            warn!("Failed to spawn favicon thread: {error}");
+        }
+    }
+
+    /// Fetch `/favicon.ico` for the origin of `page_url`, if it has no favicon yet and hasn't
+    /// failed to fetch one. This is where browsers look for the favicon of pages that don't
+    /// link to one.
+    pub fn fetch_default(&mut self, page_url: &ServoUrl, size: u32) {
+        if !matches!(page_url.scheme(), "http" | "https") || self.get(page_url).is_some() {
+            return;
+        }
+        let key = Self::key(page_url);
+        if !matches!(
+            key.and_then(|key| self.icons.get_mut().get(&key)),
+            Some(Favicon::Missing)
+        ) {
+            return;
+        }
+        if let Ok(icon_url) = page_url.join("/favicon.ico") {
+            self.fetch(page_url, icon_url, size);
+        }
+    }
+
+    /// Take the results of the fetches that have finished, returning true iff any favicon has
+    /// changed.
+    pub fn take_fetched(&mut self) -> bool {
+        let mut changed = false;
+        for (key, icon_url, result) in self.receiver.try_iter() {
+            let icon = match result {
+                Ok(image) => {
+                    changed = true;
+                    Favicon::Loaded(Some(icon_url), Arc::new(image))
+                },
+                Err(error) => {
+                    debug!("Failed to fetch favicon {icon_url}: {error}");
+                    Favicon::Failed
+                },
+            };
+            self.icons.get_mut().insert(key, icon);
+        }
+        if changed {
+            self.generation += 1;
+        }
+        changed
+    }
+
+    fn cache_path(&self, key: &str) -> Option<PathBuf> {
+        if pref!(network.http_cache.disabled) {
+            return None;
+        }
+        let file_name: String = key
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+                _ => '_',
+            })
+            .collect();
+        let dir = self.cache_dir.as_ref()?;
+        Some(dir.join(file_name + ".png"))
+    }
+
+    /// Favicons are kept per tuple origin; pages with opaque origins don't have one.
+    fn key(url: &ServoUrl) -> Option<String> {
+        let origin = url.origin();
+        origin.is_tuple().then(|| origin.ascii_serialization())
+    }
+}
+
+fn fetch_favicon(url: &ServoUrl, size: u32) -> Result<RgbaImage, String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported scheme {}", url.scheme()));
+    }
+    let response = fetch::agent_builder()?
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .get(url.as_str())
+        .call()
+        .map_err(|error| error.to_string())?;
+    let is_svg = response.content_type() == "image/svg+xml" || url.path().ends_with(".svg");
+    let mut bytes = vec![];
+    response
+        .into_reader()
+        .take(MAX_FAVICON_BYTES)
+        .read_to_end(&mut bytes)
+        .map_err(|error| error.to_string())?;
+    if is_svg {
+        decode_svg(&bytes, size)
+    } else {
+        decode_bitmap(&bytes, size)
+    }
+}
+
+/// Decode a PNG, ICO or other bitmap favicon. ICO files can have several sizes, of which the
+/// largest is decoded, then resized to fit `size` pixels.
+fn decode_bitmap(bytes: &[u8], size: u32) -> Result<RgbaImage, String> {
+    let image = image::load_from_memory(bytes).map_err(|error| error.to_string())?;
+    if image.width() == size && image.height() == size {
+        return Ok(image.to_rgba8());
+    }
+    Ok(image.resize(size, size, FilterType::Lanczos3).to_rgba8())
+}
+
+/// Render an SVG favicon to fit `size` pixels.
+fn decode_svg(bytes: &[u8], size: u32) -> Result<RgbaImage, String> {
+    let tree = usvg::Tree::from_data(bytes, &usvg::Options::default())
+        .map_err(|error| error.to_string())?;
+    let scale = size as f32 / tree.size().width().max(tree.size().height());
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or("Invalid favicon size")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let pixel = pixel.demultiply();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(size, size, pixels).ok_or_else(|| "Invalid favicon size".to_owned())
+}
+
+fn save_favicon(image: &RgbaImage, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+    }
+    image.save(path).map_err(|error| error.to_string())
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The requests that the UI makes itself, outside of Servo: favicons, and the pages fetched again
+//! to show their source, headers or reader view, or to save them.
+//!
+//! Servo has no API for the embedder to make requests, so they are made with ureq. They trust the
+//! same certificates as Servo, those of `--certificate-path` if given, and none of them are
+//! checked with `--ignore-certificate-errors`. They don't have the cookies or the cache of Servo
+//! though, so a page that needs a login is fetched as the server shows it to a logged out user.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, OnceLock};
+
+use log::warn;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use servo::config::opts;
+use ureq::AgentBuilder;
+
+/// The TLS config of the requests, built on first use. None means the defaults of ureq.
+static TLS_CONFIG: OnceLock<Result<Option<Arc<ClientConfig>>, String>> = OnceLock::new();
+
+/// An agent for requests on behalf of the UI, or an error if the certificates that Servo was
+/// told to trust can't be read, rather than fetching with other ones.
+pub fn agent_builder() -> Result<AgentBuilder, String> {
+    let tls_config = TLS_CONFIG.get_or_init(|| {
+        tls_config().map_err(|error| {
+            warn!("Failed to configure TLS for requests outside of Servo: {error}");
+            error
+        })
+    });
+    let builder = AgentBuilder::new();
+    Ok(match tls_config.clone()? {
+        Some(tls_config) => builder.tls_config(tls_config),
+        None => builder,
+    })
+}
+
+fn tls_config() -> Result<Option<Arc<ClientConfig>>, String> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|error| error.to_string())?;
+    let config = if opts::get().ignore_certificate_errors {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(
+                provider.signature_verification_algorithms,
+            )))
+            .with_no_client_auth()
+    } else if let Some(ref path) = opts::get().certificate_path {
+        let file = File::open(path).map_err(|error| format!("{path}: {error}"))?;
+        let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| format!("{path}: {error}"))?;
+        let mut roots = RootCertStore::empty();
+        if roots.add_parsable_certificates(certificates).0 == 0 {
+            return Err(format!("{path}: No certificates"));
+        }
+        builder.with_root_certificates(roots).with_no_client_auth()
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(Arc::new(config)))
+}
+
+/// Trusts every certificate, for `--ignore-certificate-errors`. Handshake signatures are still
+/// checked, as they are with the certificate that was presented.
+#[derive(Debug)]
+struct AcceptAnyCertificate(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, certificate, signature, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, certificate, signature, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Font directories from `--fonts-dir`, for systems with few fonts or none, like minimal
+//! images and headless CI.
+//!
+//! Servo finds fonts with fontconfig on Linux, and has no way for the embedder to add fonts.
+//! Instead, the directories are added by a fontconfig configuration of our own, which includes
+//! the system one, and which fontconfig is told to read with `FONTCONFIG_FILE` before Servo
+//! starts. If there are no system fonts, the `fonts` directory of the resources is added too,
+//! for packages that bundle fonts there.
+//!
+//! TODO: Add fonts on the other platforms, where Servo asks the system for fonts rather than
+//! fontconfig. WOFF2 files are only loaded if FreeType was built with Brotli.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use log::{info, warn};
+
+/// The extensions of the font files that are counted in font directories.
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "woff", "woff2"];
+
+/// Where fontconfig looks for system fonts, by default.
+const SYSTEM_FONT_DIRS: &[&str] = &["/usr/share/fonts", "/usr/local/share/fonts"];
+
+/// The fontconfig configuration adding the font directories, removed once dropped.
+pub struct FontDirs {
+    config: PathBuf,
+}
+
+impl FontDirs {
+    /// Make fontconfig load the fonts in `dirs`, and the bundled fonts if there are no system
+    /// fonts. Returns None if there are no directories to add.
+    pub fn new(mut dirs: Vec<PathBuf>) -> io::Result<Option<Self>> {
+        let system_fonts: usize = system_font_dirs().iter().map(|dir| count_fonts(dir)).sum();
+        if system_fonts == 0 {
+            let bundled = crate::resources::resources_dir_path().join("fonts");
+            if bundled.is_dir() {
+                info!("No system fonts found, using {}", bundled.display());
+                dirs.push(bundled);
+            } else {
+                warn!("No system fonts found, text may not render without --fonts-dir");
+            }
+        }
+        if dirs.is_empty() {
+            return Ok(None);
+        }
+
+        // Keep the configuration that fontconfig would have read otherwise.
+        let system_config = env::var_os("FONTCONFIG_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/etc/fonts/fonts.conf"));
+        let mut config = format!(
+            "<?xml version=\"1.0\"?>\n\
+             <!DOCTYPE fontconfig SYSTEM \"fonts.dtd\">\n\
+             <fontconfig>\n\
+             <include ignore_missing=\"yes\">{}</include>\n",
+            xml_escape(&system_config.to_string_lossy())
+        );
+        for dir in &dirs {
+            let dir = dir.canonicalize().map_err(|error| {
+                io::Error::new(error.kind(), format!("{}: {error}", dir.display()))
+            })?;
+            let faces = count_fonts(&dir);
+            info!("Loading {faces} font files from {}", dir.display());
+            if faces == 0 {
+                warn!("No fonts found in {}", dir.display());
+            }
+            config.push_str(&format!(
+                "<dir>{}</dir>\n",
+                xml_escape(&dir.to_string_lossy())
+            ));
+        }
+        config.push_str("</fontconfig>\n");
+
+        // A new file with a random name, that only the user can write, rather than one that
+        // someone else could have made first.
+        let mut file = tempfile::Builder::new()
+            .prefix("servoshell-fonts-")
+            .suffix(".conf")
+            .tempfile()?;
+        file.write_all(config.as_bytes())?;
+        let path = file.into_temp_path().keep().map_err(|error| error.error)?;
+        env::set_var("FONTCONFIG_FILE", &path);
+        Ok(Some(Self { config: path }))
+    }
+}
+
+impl Drop for FontDirs {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.config) {
+            warn!("Failed to remove {}: {error}", self.config.display());
+        }
+    }
+}
+
+fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = SYSTEM_FONT_DIRS.iter().map(PathBuf::from).collect();
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+    }
+    dirs
+}
+
+/// How many font files there are in `dir` and its subdirectories.
+fn count_fonts(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                count_fonts(&path)
+            } else {
+                let extension = path.extension().and_then(|extension| extension.to_str());
+                extension.map_or(0, |extension| {
+                    FONT_EXTENSIONS.contains(&&*extension.to_ascii_lowercase()) as usize
+                })
+            }
+        })
+        .sum()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,96 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The default fonts of pages, from `--default-font-size`, `--font-family`,
+//! `--monospace-font-family` and `--min-font-size`.
+//!
+//! Servo has no font preferences for the embedder to set, so the defaults are applied with a user
+//! stylesheet, which pages override wherever they choose their own fonts, as they do the
+//! defaults of other browsers. The minimum font size is applied with a user script, which makes
+//! any text smaller than it as large as it once the page has been parsed, so text that pages add
+//! later can still be smaller.
+//!
+//! TODO: Change the fonts at runtime, and for some origins only. Servo reads user stylesheets
+//! once at startup, for all webviews. In the meantime, zooming into a site is remembered for its
+//! origin, which makes its text larger too.
+
+use log::warn;
+use servo::config::opts;
+use servo::servo_url::ServoUrl;
+
+/// The elements that the user agent stylesheet shows in a monospace font.
+const MONOSPACE_ELEMENTS: &str = "pre, code, kbd, samp, tt, listing, plaintext, xmp";
+
+#[derive(Default)]
+pub struct FontSettings {
+    /// The font size of text that pages don't size, in CSS pixels.
+    pub default_size: Option<f32>,
+    /// The size that smaller text is made as large as, in CSS pixels.
+    pub min_size: Option<f32>,
+    /// The font family of text that pages don't choose a font for.
+    pub family: Option<String>,
+    /// The font family of code and other preformatted text that pages don't choose a font for.
+    pub monospace_family: Option<String>,
+}
+
+impl FontSettings {
+    /// Tell Servo to apply the default font size and families to every document, if any is set.
+    pub fn apply(&self) {
+        let Some(stylesheet) = self.stylesheet() else {
+            return;
+        };
+        let url = match ServoUrl::parse("about:fonts") {
+            Ok(url) => url,
+            Err(error) => {
+                warn!("Failed to apply fonts: {error}");
+                return;
+            },
+        };
+        let mut fonts_opts = opts::get().clone();
+        fonts_opts
+            .user_stylesheets
+            .push((stylesheet.into_bytes(), url));
+        opts::set_options(fonts_opts);
+    }
+
+    /// The user script that applies the minimum font size, if there is one.
+    pub fn min_size_script(&self) -> Option<String> {
+        let min_size = self.min_size?;
+        Some(format!(
+            "for (let element of document.querySelectorAll('body, body *')) {{\n\
+             if (parseFloat(getComputedStyle(element).fontSize) < {min_size}) {{\n\
+             element.style.setProperty('font-size', '{min_size}px', 'important');\n\
+             }}\n\
+             }}\n"
+        ))
+    }
+
+    fn stylesheet(&self) -> Option<String> {
+        let mut root = vec![];
+        if let Some(size) = self.default_size {
+            root.push(format!("font-size: {size}px;"));
+        }
+        if let Some(ref family) = self.family {
+            root.push(format!("font-family: {};", css_string(family)));
+        }
+        let mut stylesheet = String::new();
+        if !root.is_empty() {
+            stylesheet.push_str(&format!(":root {{ {} }}\n", root.join(" ")));
+        }
+        if let Some(ref family) = self.monospace_family {
+            // Like the user agent stylesheet, fall back to the default monospace font.
+            stylesheet.push_str(&format!(
+                "{MONOSPACE_ELEMENTS} {{ font-family: {}, monospace; }}\n",
+                css_string(family)
+            ));
+        }
+        (!stylesheet.is_empty()).then_some(stylesheet)
+    }
+}
+
+/// Quote a font family name, so that any name can be given, even one with quotes.
+fn css_string(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Frame timing, for the overlay toggled with F12 and for `--frame-log`.
+//!
+//! Frames are timed around presenting what Servo composited, so the frame time is the time
+//! between two presents, and the present time is how long presenting took. Nothing is timed
+//! while neither the overlay nor the log is enabled.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+/// How many of the most recent frames the overlay is computed from.
+const RECENT_FRAMES: usize = 120;
+
+#[derive(Default)]
+pub struct FrameStats {
+    overlay_visible: bool,
+    /// The CSV file of every frame's timing, from `--frame-log`.
+    log: Option<BufWriter<File>>,
+    last_present: Option<Instant>,
+    /// The time between the most recent presents, oldest first.
+    frame_times: VecDeque<Duration>,
+    composite_count: u64,
+}
+
+impl FrameStats {
+    /// Start timing frames, logging them to the CSV file at `log_path` if given.
+    pub fn new(log_path: Option<&Path>) -> io::Result<Self> {
+        let log = match log_path {
+            Some(path) => {
+                let mut log = BufWriter::new(File::create(path)?);
+                writeln!(log, "frame,frame_time_ms,present_time_ms")?;
+                Some(log)
+            },
+            None => None,
+        };
+        Ok(Self {
+            log,
+            ..Default::default()
+        })
+    }
+
+    /// Whether frames should be timed at all.
+    pub fn is_enabled(&self) -> bool {
+        self.overlay_visible || self.log.is_some()
+    }
+
+    pub fn overlay_visible(&self) -> bool {
+        self.overlay_visible
+    }
+
+    pub fn toggle_overlay(&mut self) {
+        self.overlay_visible = !self.overlay_visible;
+        // Don't count the time the overlay was hidden as a frame.
+        if self.overlay_visible && self.log.is_none() {
+            self.last_present = None;
+            self.frame_times.clear();
+        }
+    }
+
+    /// Record a frame that started presenting at `start` and finished at `end`.
+    pub fn record_present(&mut self, start: Instant, end: Instant) {
+        self.composite_count += 1;
+        let frame_time = self.last_present.map(|last| end - last);
+        self.last_present = Some(end);
+        if let Some(frame_time) = frame_time {
+            if self.frame_times.len() == RECENT_FRAMES {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(frame_time);
+        }
+        let Some(ref mut log) = self.log else {
+            return;
+        };
+        let result = writeln!(
+            log,
+            "{},{},{:.3}",
+            self.composite_count,
+            frame_time.map_or(String::new(), |time| format!("{:.3}", milliseconds(time))),
+            milliseconds(end - start),
+        );
+        if let Err(error) = result {
+            warn!("Failed to log frame times, no longer logging them: {error}");
+            self.log = None;
+        }
+    }
+
+    /// The frames per second, the 99th percentile frame time and the number of frames
+    /// composited, as shown in the overlay.
+    pub fn summary(&self) -> String {
+        let total: Duration = self.frame_times.iter().sum();
+        let fps = if total.is_zero() {
+            0.0
+        } else {
+            self.frame_times.len() as f64 / total.as_secs_f64()
+        };
+        let mut sorted: Vec<_> = self.frame_times.iter().copied().collect();
+        sorted.sort();
+        let p99 = sorted
+            .get((sorted.len() * 99 / 100).min(sorted.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default();
+        format!(
+            "{fps:.1} fps\np99 {:.1} ms\n{} frames",
+            milliseconds(p99),
+            self.composite_count
+        )
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -7,6 +7,7 @@
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Instant;
 
 use euclid::{Angle, Length, Point2D, Rotation3D, Scale, Size2D, UnknownUnit, Vector2D, Vector3D};
 use log::{debug, info, trace};
@@ -16,6 +17,8 @@ use servo::compositing::windowing::{
 };
 use servo::embedder_traits::Cursor;
 use servo::keyboard_types::{Key, KeyState, KeyboardEvent};
+#[cfg(target_os = "macos")]
+use servo::script_traits::TraversalDirection;
 use servo::script_traits::{TouchEventType, WheelDelta, WheelMode};
 use servo::servo_config::{opts, pref};
 use servo::servo_geometry::DeviceIndependentPixel;
@@ -25,14 +28,19 @@ use servo::webrender_api::ScrollLocation;
 use servo::webrender_traits::RenderingContext;
 use surfman::{Connection, Context, Device, SurfaceType};
 use winit::dpi::{LogicalPosition, PhysicalPosition, PhysicalSize};
-use winit::event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase};
+use winit::event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase};
 use winit::keyboard::{Key as LogicalKey, ModifiersState, NamedKey};
 #[cfg(any(target_os = "linux", target_os = "windows"))]
 use winit::window::Icon;
 
+use super::device_emulation::Emulation;
 use super::events_loop::{EventsLoop, WakerEvent};
 use super::geometry::{winit_position_to_euclid_point, winit_size_to_euclid_size};
 use super::keyutils::keyboard_event_from_winit;
+use super::kinetic_scroll::KineticScroll;
+#[cfg(target_os = "macos")]
+use super::swipe::Swipe;
+use super::window_geometry::is_on_screen;
 use super::window_trait::{WindowPortsMethods, LINE_HEIGHT};
 
 pub struct Window {
@@ -51,10 +59,31 @@ pub struct Window {
     /// winit's ReceivedChar events.
     keys_down: RefCell<HashMap<LogicalKey, Key>>,
     animation_state: Cell<AnimationState>,
+    /// Touches, to keep scrolling pages flung with a finger.
+    kinetic_scroll: RefCell<KineticScroll>,
+    /// Trackpad scrolls, to go back or forward when they swipe sideways.
+    #[cfg(target_os = "macos")]
+    swipe: RefCell<Swipe>,
+    /// Where the last swipe navigates to, until the webviews are told.
+    #[cfg(target_os = "macos")]
+    swiped: Cell<Option<TraversalDirection>>,
+    /// Whether the page asked for the window to be fullscreen.
     fullscreen: Cell<bool>,
-    device_pixel_ratio_override: Option<f32>,
+    /// Whether the window was made fullscreen with F11, regardless of the page.
+    browser_fullscreen: Cell<bool>,
+    /// Where the window was and its size before it went fullscreen, to put it back after.
+    windowed_geometry: Cell<Option<(Option<PhysicalPosition<i32>>, PhysicalSize<u32>)>>,
+    /// Whether the window manager took the window out of the page's fullscreen, and the page
+    /// hasn't been told yet.
+    fullscreen_exited: Cell<bool>,
+    device_pixel_ratio_override: Cell<Option<f32>>,
     xr_window_poses: RefCell<Vec<Rc<XRWindowPose>>>,
     modifiers_state: Cell<ModifiersState>,
+    /// Whether the IME is composing text, in which case it gets the keys rather than content.
+    ime_composing: Cell<bool>,
+    /// When the page was last clicked, tapped or typed into.
+    last_user_activation: Cell<Option<Instant>>,
+    emulation: Cell<Option<Emulation>>,
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -73,9 +102,11 @@ fn window_creation_scale_factor() -> Scale<f32, DeviceIndependentPixel, DevicePi
 impl Window {
     pub fn new(
         win_size: Size2D<u32, DeviceIndependentPixel>,
+        position: Option<LogicalPosition<f64>>,
         events_loop: &EventsLoop,
         no_native_titlebar: bool,
         device_pixel_ratio_override: Option<f32>,
+        software_rendering: bool,
     ) -> Window {
         let opts = opts::get();
 
@@ -89,12 +120,18 @@ impl Window {
         let width = win_size.to_untyped().width;
         let height = win_size.to_untyped().height;
 
-        let window_builder = winit::window::WindowBuilder::new()
+        let mut window_builder = winit::window::WindowBuilder::new()
             .with_title("Servo".to_string())
             .with_decorations(!no_native_titlebar)
             .with_transparent(no_native_titlebar)
             .with_inner_size(PhysicalSize::new(width as f64, height as f64))
             .with_visible(visible);
+        // Positions that would put the window out of view, like on a monitor that has since been
+        // unplugged, are left to the window manager.
+        let monitors = events_loop.as_winit().available_monitors();
+        if let Some(position) = position.filter(|&position| is_on_screen(position, monitors)) {
+            window_builder = window_builder.with_position(position);
+        }
 
         let winit_window = window_builder
             .build(events_loop.as_winit())
@@ -121,9 +158,19 @@ impl Window {
             .expect("could not get display handle from window");
         let connection =
             Connection::from_display_handle(display_handle).expect("Failed to create connection");
-        let adapter = connection
-            .create_adapter()
-            .expect("Failed to create adapter");
+        // The software adapter renders with the CPU, typically with Mesa's llvmpipe, so painting
+        // is several times slower and uses up CPU time that would otherwise go to layout and
+        // script, but it doesn't depend on the GPU and its driver.
+        //
+        // TODO: Let the renderer be switched while running, from a debug menu. This needs the
+        // window and Servo to be created again, and Servo can only be initialized once per
+        // process, so for now it takes a restart with or without `--software-webrender`.
+        let adapter = if software_rendering {
+            connection.create_software_adapter()
+        } else {
+            connection.create_adapter()
+        }
+        .expect("Failed to create adapter");
         let window_handle = winit_window
             .window_handle()
             .expect("could not get window handle from window");
@@ -145,13 +192,24 @@ impl Window {
             last_pressed: Cell::new(None),
             keys_down: RefCell::new(HashMap::new()),
             animation_state: Cell::new(AnimationState::Idle),
+            kinetic_scroll: RefCell::new(KineticScroll::default()),
+            #[cfg(target_os = "macos")]
+            swipe: RefCell::new(Swipe::default()),
+            #[cfg(target_os = "macos")]
+            swiped: Cell::new(None),
             fullscreen: Cell::new(false),
+            browser_fullscreen: Cell::new(false),
+            windowed_geometry: Cell::new(None),
+            fullscreen_exited: Cell::new(false),
             inner_size: Cell::new(inner_size),
             primary_monitor,
             screen_size,
-            device_pixel_ratio_override,
+            device_pixel_ratio_override: Cell::new(device_pixel_ratio_override),
             xr_window_poses: RefCell::new(vec![]),
             modifiers_state: Cell::new(ModifiersState::empty()),
+            ime_composing: Cell::new(false),
+            last_user_activation: Cell::new(None),
+            emulation: Cell::new(None),
             toolbar_height: Cell::new(Default::default()),
         }
     }
@@ -201,6 +259,10 @@ impl Window {
     }
 
     fn handle_keyboard_input(&self, input: KeyEvent) {
+        // Keys like Backspace edit the text being composed, not the text in the page.
+        if self.ime_composing.get() {
+            return;
+        }
         if let Some(input_text) = &input.text {
             for ch in input_text.chars() {
                 self.handle_received_character(ch);
@@ -209,6 +271,9 @@ impl Window {
 
         let mut event = keyboard_event_from_winit(&input, self.modifiers_state.get());
         trace!("handling {:?}", event);
+        if event.state == KeyState::Down {
+            self.last_user_activation.set(Some(Instant::now()));
+        }
         if event.state == KeyState::Down && event.key == Key::Unidentified {
             // If pressed and probably printable, we expect a ReceivedCharacter event.
             // Wait for that to be received and don't queue any event right now.
@@ -234,6 +299,30 @@ impl Window {
         }
     }
 
+    /// Handle text input from an IME. Servo has no composition events yet, so the text being
+    /// composed is only shown by the IME, and the page gets the composed text once committed,
+    /// as if each of its characters had been typed.
+    fn handle_ime(&self, ime: Ime) {
+        match ime {
+            Ime::Preedit(text, _cursor) => self.ime_composing.set(!text.is_empty()),
+            Ime::Commit(text) => {
+                self.ime_composing.set(false);
+                let mut event_queue = self.event_queue.borrow_mut();
+                for ch in text.chars() {
+                    let key = Key::Character(ch.to_string());
+                    for state in [KeyState::Down, KeyState::Up] {
+                        event_queue.push(EmbedderEvent::Keyboard(KeyboardEvent {
+                            state,
+                            key: key.clone(),
+                            ..Default::default()
+                        }));
+                    }
+                }
+            },
+            Ime::Enabled | Ime::Disabled => self.ime_composing.set(false),
+        }
+    }
+
     /// Helper function to handle a click
     fn handle_mouse(
         &self,
@@ -282,10 +371,45 @@ impl Window {
             .borrow_mut()
             .push(EmbedderEvent::MouseWindowEventClass(event));
     }
+
+    /// Make the window fullscreen if the page or the user wants it to be, or else put it back
+    /// where it was, at the size it was.
+    fn update_fullscreen(&self) {
+        let fullscreen = self.fullscreen.get() || self.browser_fullscreen.get();
+        if fullscreen == self.winit_window.fullscreen().is_some() {
+            return;
+        }
+        if fullscreen {
+            self.windowed_geometry.set(Some((
+                self.winit_window.outer_position().ok(),
+                self.winit_window.inner_size(),
+            )));
+            self.winit_window
+                .set_fullscreen(Some(winit::window::Fullscreen::Borderless(Some(
+                    self.primary_monitor.clone(),
+                ))));
+        } else {
+            self.winit_window.set_fullscreen(None);
+            if let Some((position, size)) = self.windowed_geometry.take() {
+                let _ = self.winit_window.request_inner_size(size);
+                if let Some(position) = position {
+                    self.winit_window.set_outer_position(position);
+                }
+            }
+        }
+    }
 }
 
 impl WindowPortsMethods for Window {
     fn get_events(&self) -> Vec<EmbedderEvent> {
+        if let Some((delta, point)) = self.kinetic_scroll.borrow_mut().scroll() {
+            let scroll_location = ScrollLocation::Delta(Vector2D::new(delta.x, delta.y));
+            self.event_queue.borrow_mut().push(EmbedderEvent::Scroll(
+                scroll_location,
+                point,
+                TouchEventType::Move,
+            ));
+        }
         std::mem::take(&mut *self.event_queue.borrow_mut())
     }
 
@@ -296,7 +420,14 @@ impl WindowPortsMethods for Window {
     fn device_pixel_ratio_override(
         &self,
     ) -> Option<Scale<f32, DeviceIndependentPixel, DevicePixel>> {
-        self.device_pixel_ratio_override.map(Scale::new)
+        self.device_pixel_ratio_override.get().map(Scale::new)
+    }
+
+    fn set_device_pixel_ratio_override(&self, ratio: Option<f32>) {
+        self.device_pixel_ratio_override.set(ratio);
+        self.event_queue
+            .borrow_mut()
+            .push(EmbedderEvent::WindowResize);
     }
 
     fn page_height(&self) -> f32 {
@@ -332,22 +463,69 @@ impl WindowPortsMethods for Window {
     }
 
     fn set_fullscreen(&self, state: bool) {
-        if self.fullscreen.get() != state {
-            self.winit_window.set_fullscreen(if state {
-                Some(winit::window::Fullscreen::Borderless(Some(
-                    self.primary_monitor.clone(),
-                )))
-            } else {
-                None
-            });
-        }
         self.fullscreen.set(state);
+        self.update_fullscreen();
     }
 
     fn get_fullscreen(&self) -> bool {
         self.fullscreen.get()
     }
 
+    fn set_browser_fullscreen(&self, state: bool) {
+        self.browser_fullscreen.set(state);
+        self.update_fullscreen();
+    }
+
+    fn get_browser_fullscreen(&self) -> bool {
+        self.browser_fullscreen.get()
+    }
+
+    fn take_fullscreen_exited(&self) -> bool {
+        self.fullscreen_exited.take()
+    }
+
+    fn modifiers_state(&self) -> ModifiersState {
+        self.modifiers_state.get()
+    }
+
+    fn last_user_activation(&self) -> Option<Instant> {
+        self.last_user_activation.get()
+    }
+
+    fn emulation(&self) -> Option<Emulation> {
+        self.emulation.get()
+    }
+
+    fn set_emulation(&self, emulation: Option<Emulation>) {
+        self.emulation.set(emulation);
+        self.event_queue
+            .borrow_mut()
+            .push(EmbedderEvent::WindowResize);
+    }
+
+    #[cfg(target_os = "macos")]
+    fn swipe_progress(&self) -> Option<f32> {
+        self.swipe.borrow().progress()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn take_swipe(&self) -> Option<TraversalDirection> {
+        self.swiped.take()
+    }
+
+    fn set_ime_area(&self, area: Option<DeviceIntRect>) {
+        let Some(area) = area else {
+            self.winit_window.set_ime_allowed(false);
+            return;
+        };
+        self.winit_window.set_ime_allowed(true);
+        // Put the candidate window right below the focused field.
+        self.winit_window.set_ime_cursor_area(
+            PhysicalPosition::new(area.min.x, area.min.y),
+            PhysicalSize::new(area.width(), area.height()),
+        );
+    }
+
     fn set_cursor(&self, cursor: Cursor) {
         use winit::window::CursorIcon;
 
@@ -396,7 +574,8 @@ impl WindowPortsMethods for Window {
     }
 
     fn is_animating(&self) -> bool {
-        self.animation_state.get() == AnimationState::Animating
+        self.animation_state.get() == AnimationState::Animating ||
+            self.kinetic_scroll.borrow().is_flinging()
     }
 
     fn id(&self) -> winit::window::WindowId {
@@ -408,13 +587,20 @@ impl WindowPortsMethods for Window {
             winit::event::WindowEvent::KeyboardInput { event, .. } => {
                 self.handle_keyboard_input(event)
             },
+            winit::event::WindowEvent::Ime(ime) => self.handle_ime(ime),
             winit::event::WindowEvent::ModifiersChanged(modifiers) => {
                 self.modifiers_state.set(modifiers.state())
             },
             winit::event::WindowEvent::MouseInput { state, button, .. } => {
-                if button == MouseButton::Left || button == MouseButton::Right {
+                if matches!(
+                    button,
+                    MouseButton::Left | MouseButton::Right | MouseButton::Middle
+                ) {
                     self.handle_mouse(button, state, self.mouse_pos.get());
                 }
+                if state == ElementState::Released {
+                    self.last_user_activation.set(Some(Instant::now()));
+                }
             },
             winit::event::WindowEvent::CursorMoved { position, .. } => {
                 let position = winit_position_to_euclid_point(position);
@@ -446,6 +632,13 @@ impl WindowPortsMethods for Window {
                 let position = Point2D::new(pos.x as f32, pos.y as f32);
                 let wheel_event = EmbedderEvent::Wheel(wheel_delta, position);
 
+                #[cfg(target_os = "macos")]
+                if let MouseScrollDelta::PixelDelta(_) = delta {
+                    if let Some(direction) = self.swipe.borrow_mut().on_scroll(phase, dx, dy) {
+                        self.swiped.set(Some(direction));
+                    }
+                }
+
                 // Scroll events snap to the major axis of movement, with vertical
                 // preferred over horizontal.
                 if dy.abs() >= dx.abs() {
@@ -466,6 +659,10 @@ impl WindowPortsMethods for Window {
             winit::event::WindowEvent::Touch(touch) => {
                 use servo::script_traits::TouchId;
 
+                self.kinetic_scroll.borrow_mut().on_touch(&touch);
+                if touch.phase == TouchPhase::Ended {
+                    self.last_user_activation.set(Some(Instant::now()));
+                }
                 let phase = winit_phase_to_touch_event_type(touch.phase);
                 let id = TouchId(touch.id as i32);
                 let position = touch.location;
@@ -484,6 +681,16 @@ impl WindowPortsMethods for Window {
                 self.event_queue.borrow_mut().push(EmbedderEvent::Quit);
             },
             winit::event::WindowEvent::Resized(physical_size) => {
+                // The window manager can take the window out of fullscreen too.
+                if self.winit_window.fullscreen().is_none() &&
+                    (self.fullscreen.get() || self.browser_fullscreen.get())
+                {
+                    self.browser_fullscreen.set(false);
+                    self.windowed_geometry.set(None);
+                    if self.fullscreen.replace(false) {
+                        self.fullscreen_exited.set(true);
+                    }
+                }
                 let (width, height) = physical_size.into();
                 let new_size = Size2D::new(width, height);
                 if self.inner_size.get() != new_size {
@@ -545,7 +752,13 @@ impl WindowMethods for Window {
         let viewport_origin = DeviceIntPoint::zero(); // bottom left
         let viewport_size = winit_size_to_euclid_size(self.winit_window.inner_size()).to_f32();
         let viewport = DeviceIntRect::from_origin_and_size(viewport_origin, viewport_size.to_i32());
-        let screen = self.screen_size.to_i32();
+        let screen = match self.emulation.get() {
+            Some(emulation) => {
+                let (width, height) = emulation.device_size();
+                Size2D::new(width, height).to_i32()
+            },
+            None => self.screen_size.to_i32(),
+        };
 
         EmbedderCoordinates {
             viewport,
@@ -20,6 +20,7 @@ use servo::webrender_api::units::{DeviceIntRect, DeviceIntSize};
 use servo::webrender_traits::RenderingContext;
 use surfman::{Connection, Context, Device, SurfaceType};
 
+use super::device_emulation::Emulation;
 use super::events_loop::WakerEvent;
 use crate::desktop::window_trait::WindowPortsMethods;
 
@@ -27,9 +28,10 @@ pub struct Window {
     rendering_context: RenderingContext,
     animation_state: Cell<AnimationState>,
     fullscreen: Cell<bool>,
-    device_pixel_ratio_override: Option<f32>,
+    device_pixel_ratio_override: Cell<Option<f32>>,
     inner_size: Cell<Size2D<i32, UnknownUnit>>,
     event_queue: RwLock<Vec<EmbedderEvent>>,
+    emulation: Cell<Option<Emulation>>,
 }
 
 impl Window {
@@ -52,9 +54,10 @@ impl Window {
             rendering_context,
             animation_state: Cell::new(AnimationState::Idle),
             fullscreen: Cell::new(false),
-            device_pixel_ratio_override,
+            device_pixel_ratio_override: Cell::new(device_pixel_ratio_override),
             inner_size: Cell::new(size.to_i32()),
             event_queue: RwLock::new(Vec::new()),
+            emulation: Cell::new(None),
         };
 
         Rc::new(window)
@@ -104,7 +107,14 @@ impl WindowPortsMethods for Window {
     fn device_pixel_ratio_override(
         &self,
     ) -> Option<Scale<f32, DeviceIndependentPixel, DevicePixel>> {
-        self.device_pixel_ratio_override.map(Scale::new)
+        self.device_pixel_ratio_override.get().map(Scale::new)
+    }
+
+    fn set_device_pixel_ratio_override(&self, ratio: Option<f32>) {
+        self.device_pixel_ratio_override.set(ratio);
+        if let Ok(ref mut queue) = self.event_queue.write() {
+            queue.push(EmbedderEvent::WindowResize);
+        }
     }
 
     fn page_height(&self) -> f32 {
@@ -118,6 +128,22 @@ impl WindowPortsMethods for Window {
         height as f32 * dpr.get()
     }
 
+    fn emulation(&self) -> Option<Emulation> {
+        self.emulation.get()
+    }
+
+    /// There is no window to frame the page in, so the window takes the size of the screen.
+    fn set_emulation(&self, emulation: Option<Emulation>) {
+        self.emulation.set(emulation);
+        if let Some(emulation) = emulation {
+            let (width, height) = emulation.device_size();
+            self.request_inner_size(Size2D::new(width, height).to_i32());
+        }
+        if let Ok(ref mut queue) = self.event_queue.write() {
+            queue.push(EmbedderEvent::WindowResize);
+        }
+    }
+
     fn set_fullscreen(&self, state: bool) {
         self.fullscreen.set(state);
     }
@@ -0,0 +1,323 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Browsing history, kept in an SQLite database in the config dir.
+
+use std::ops::{Bound, RangeBounds};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use rusqlite::{params, Connection};
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+
+/// A page in the history, with its most recent title.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    /// Open the history database, creating it if needed.
+    pub fn open() -> Option<Self> {
+        let path = config_dir::get()?.join("history.sqlite");
+        if let Some(dir) = path.parent() {
+            if let Err(error) = std::fs::create_dir_all(dir) {
+                warn!("Failed to create {}: {error}", dir.display());
+                return None;
+            }
+        }
+        match Connection::open(&path).and_then(Self::with_connection) {
+            Ok(history) => Some(history),
+            Err(error) => {
+                warn!("Failed to open history {}: {error}", path.display());
+                None
+            },
+        }
+    }
+
+    /// Keep the history in the given database, creating its table if needed.
+    fn with_connection(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS visits (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                title TEXT,
+                visited_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS visits_url ON visits (url);
+            CREATE INDEX IF NOT EXISTS visits_visited_at ON visits (visited_at);",
+        )?;
+        Ok(Self { connection })
+    }
+
+    /// Record a visit to `url` now.
+    pub fn record_visit(&self, url: &ServoUrl, title: Option<&str>) {
+        self.record_visit_at(url, title, SystemTime::now());
+    }
+
+    fn record_visit_at(&self, url: &ServoUrl, title: Option<&str>, time: SystemTime) {
+        // Internal and inline pages aren't worth remembering.
+        if matches!(url.scheme(), "about" | "data" | "blob" | "javascript") {
+            return;
+        }
+        let result = self.connection.execute(
+            "INSERT INTO visits (url, title, visited_at) VALUES (?1, ?2, ?3)",
+            params![url.as_str(), title, unix_time(time)],
+        );
+        if let Err(error) = result {
+            warn!("Failed to record visit to {url}: {error}");
+        }
+    }
+
+    /// Update the title of the most recent visit to `url`, since pages often set their title
+    /// after they have loaded.
+    pub fn set_title(&self, url: &ServoUrl, title: Option<&str>) {
+        let result = self.connection.execute(
+            "UPDATE visits SET title = ?2 WHERE id =
+                (SELECT id FROM visits WHERE url = ?1 ORDER BY visited_at DESC, id DESC LIMIT 1)",
+            params![url.as_str(), title],
+        );
+        if let Err(error) = result {
+            warn!("Failed to update history title of {url}: {error}");
+        }
+    }
+
+    /// Find up to `limit` pages whose URL starts with `prefix`, with or without the scheme and
+    /// `www.`, or whose title contains it. Pages visited often and recently come first.
+    pub fn search(&self, prefix: &str, limit: usize) -> Vec<HistoryEntry> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return vec![];
+        }
+        let pattern = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        // The score is the number of visits, divided by the number of days since the last one.
+        let result = self
+            .connection
+            .prepare_cached(
+                "SELECT url,
+                    (SELECT title FROM visits AS latest WHERE latest.url = visits.url
+                        ORDER BY visited_at DESC, id DESC LIMIT 1)
+                FROM visits
+                WHERE url LIKE ?1 || '%' ESCAPE '\\'
+                    OR substr(url, instr(url, '://') + 3) LIKE ?1 || '%' ESCAPE '\\'
+                    OR substr(url, instr(url, '://') + 3) LIKE 'www.' || ?1 || '%' ESCAPE '\\'
+                    OR title LIKE '%' || ?1 || '%' ESCAPE '\\'
+                GROUP BY url
+                ORDER BY COUNT(*) / (1.0 + (?2 - MAX(visited_at)) / 86400.0) DESC
+                LIMIT ?3",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(
+                        params![pattern, unix_time(SystemTime::now()), limit as i64],
+                        |row| {
+                            Ok(HistoryEntry {
+                                url: row.get(0)?,
+                                title: row.get(1)?,
+                            })
+                        },
+                    )?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        result.unwrap_or_else(|error| {
+            warn!("Failed to search history: {error}");
+            vec![]
+        })
+    }
+
+    /// The `limit` pages visited most recently, most recent first.
+    pub fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        let result = self
+            .connection
+            .prepare_cached(
+                "SELECT url,
+                    (SELECT title FROM visits AS latest WHERE latest.url = visits.url
+                        ORDER BY visited_at DESC, id DESC LIMIT 1)
+                FROM visits
+                GROUP BY url
+                ORDER BY MAX(visited_at) DESC, MAX(id) DESC
+                LIMIT ?1",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![limit as i64], |row| {
+                        Ok(HistoryEntry {
+                            url: row.get(0)?,
+                            title: row.get(1)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        result.unwrap_or_else(|error| {
+            warn!("Failed to list recent history: {error}");
+            vec![]
+        })
+    }
+
+    /// Forget the visits made in the given time range.
+    pub fn clear_history(&self, range: impl RangeBounds<SystemTime>) {
+        let start = match range.start_bound() {
+            Bound::Included(time) => unix_time(*time),
+            Bound::Excluded(time) => unix_time(*time) + 1,
+            Bound::Unbounded => i64::MIN,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(time) => unix_time(*time),
+            Bound::Excluded(time) => unix_time(*time) - 1,
+            Bound::Unbounded => i64::MAX,
+        };
+        let result = self.connection.execute(
+            "DELETE FROM visits WHERE visited_at BETWEEN ?1 AND ?2",
+            params![start, end],
+        );
+        if let Err(error) = result {
+            warn!("Failed to clear history: {error}");
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, which is how visit times are stored.
+fn unix_time(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+#[cfg(test)]
+fn test_history(visits: &[(&str, Option<&str>, u64)]) -> History {
+    let history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+    for (url, title, days_ago) in visits {
+        let time = SystemTime::now() - std::time::Duration::from_secs(days_ago * 86400);
+        history.record_visit_at(&ServoUrl::parse(url).unwrap(), *title, time);
+    }
+    history
+}
+
+#[cfg(test)]
+fn test_urls(entries: Vec<HistoryEntry>) -> Vec<String> {
+    entries.into_iter().map(|entry| entry.url).collect()
+}
+
+#[test]
+fn test_history_search_matches() {
+    let history = test_history(&[
+        ("https://www.example.com/a", Some("First page"), 0),
+        ("http://example.org/b", Some("Second Page"), 0),
+        ("https://rust-lang.org/", Some("100% Rust"), 0),
+        ("about:blank", None, 0),
+        ("data:text/html,example", None, 0),
+    ]);
+    let search = |prefix| {
+        let mut urls = test_urls(history.search(prefix, 10));
+        urls.sort();
+        urls
+    };
+    // With the scheme, without it, and without www.
+    assert_eq!(search("https://www.ex"), ["https://www.example.com/a"]);
+    assert_eq!(search("www.example"), ["https://www.example.com/a"]);
+    assert_eq!(
+        search("example"),
+        ["http://example.org/b", "https://www.example.com/a"]
+    );
+    // Not in the middle of the URL, but anywhere in the title, whatever the case.
+    assert!(search("com/a").is_empty());
+    assert_eq!(
+        search("PAGE"),
+        ["http://example.org/b", "https://www.example.com/a"]
+    );
+    // Wildcards are taken literally.
+    assert_eq!(search("100%"), ["https://rust-lang.org/"]);
+    assert!(search("1_0").is_empty());
+    assert!(search("  ").is_empty());
+    // Internal and inline pages aren't recorded.
+    assert!(search("about:").is_empty());
+    assert!(search("data:").is_empty());
+    assert_eq!(history.search("example", 1).len(), 1);
+}
+
+#[test]
+fn test_history_search_frecency() {
+    let history = test_history(&[
+        // Visited often, but long ago.
+        ("https://a.example/", None, 30),
+        ("https://a.example/", None, 30),
+        ("https://a.example/", None, 30),
+        // Visited once, today.
+        ("https://b.example/", None, 0),
+        // Visited often, lately.
+        ("https://c.example/", None, 1),
+        ("https://c.example/", None, 1),
+        ("https://c.example/", None, 0),
+    ]);
+    assert_eq!(
+        test_urls(history.search("https://", 10)),
+        [
+            "https://c.example/",
+            "https://b.example/",
+            "https://a.example/"
+        ]
+    );
+}
+
+#[test]
+fn test_history_titles_and_recent() {
+    let history = test_history(&[
+        ("https://a.example/", Some("Old title"), 2),
+        ("https://b.example/", None, 1),
+        ("https://a.example/", Some("Loading"), 0),
+    ]);
+    history.set_title(
+        &ServoUrl::parse("https://a.example/").unwrap(),
+        Some("New title"),
+    );
+    assert_eq!(
+        history.recent(10),
+        [
+            HistoryEntry {
+                url: "https://a.example/".to_owned(),
+                title: Some("New title".to_owned()),
+            },
+            HistoryEntry {
+                url: "https://b.example/".to_owned(),
+                title: None,
+            },
+        ]
+    );
+    // Pages are found by the titles they had before too, but show the latest one.
+    assert_eq!(
+        history.search("Old", 10),
+        [HistoryEntry {
+            url: "https://a.example/".to_owned(),
+            title: Some("New title".to_owned()),
+        }]
+    );
+    assert_eq!(test_urls(history.search("new", 10)), ["https://a.example/"]);
+    assert_eq!(test_urls(history.recent(1)), ["https://a.example/"]);
+}
+
+#[test]
+fn test_clear_history() {
+    let history = test_history(&[
+        ("https://a.example/", None, 10),
+        ("https://b.example/", None, 3),
+        ("https://c.example/", None, 0),
+    ]);
+    let day = std::time::Duration::from_secs(86400);
+    history.clear_history(SystemTime::now() - 5 * day..SystemTime::now() - day);
+    assert_eq!(
+        test_urls(history.recent(10)),
+        ["https://c.example/", "https://a.example/"]
+    );
+    history.clear_history(..);
+    assert!(history.recent(10).is_empty());
+}
@@ -0,0 +1,131 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Kinetic scrolling on touchscreens: a page flung with a finger keeps scrolling for a while,
+//! slowing down, like on phones.
+//!
+//! Servo scrolls the page as a finger moves and zooms it as two fingers pinch, but it stops
+//! scrolling as soon as the finger is lifted. So the window keeps track of how fast a lone finger
+//! moves, and if it is still moving fast when it is lifted, the window goes on scrolling with the
+//! speed of the finger, decaying exponentially. Touching the screen again stops it.
+//!
+//! Trackpads don't need this, since the platform sends scroll events with their own momentum.
+//!
+//! TODO: Don't fling pages that cancel touch events or opt out of panning with `touch-action`.
+//! Servo doesn't support `touch-action` yet, nor tell the embedder whether a page cancelled a
+//! touch event.
+
+use std::time::{Duration, Instant};
+
+use euclid::{Point2D, Vector2D};
+use servo::style_traits::DevicePixel;
+use servo::webrender_api::units::DeviceIntPoint;
+use winit::event::{Touch, TouchPhase};
+
+/// How fast a finger must be moving when lifted for the page to be flung, in pixels per second.
+const MIN_FLING_VELOCITY: f32 = 300.0;
+
+/// How fast a fling must still be going to keep it going, in pixels per second.
+const MIN_VELOCITY: f32 = 20.0;
+
+/// A finger that hasn't moved for this long before it is lifted doesn't fling the page.
+const MAX_FLING_STILLNESS: Duration = Duration::from_millis(100);
+
+/// How long it takes a fling to slow down to about a third of its speed, in seconds.
+const FLING_TIME_CONSTANT: f32 = 0.325;
+
+type Velocity = Vector2D<f32, DevicePixel>;
+
+/// A finger on the touchscreen, while it is the only one.
+struct Finger {
+    id: u64,
+    position: Point2D<f32, DevicePixel>,
+    time: Instant,
+    /// In pixels per second, smoothed over the last few moves.
+    velocity: Velocity,
+}
+
+/// A page that is scrolling on its own after being flung.
+struct Fling {
+    velocity: Velocity,
+    /// Where the finger was lifted, which is what gets scrolled.
+    point: DeviceIntPoint,
+    last_scroll: Instant,
+}
+
+#[derive(Default)]
+pub struct KineticScroll {
+    /// How many fingers are on the touchscreen.
+    touches: usize,
+    finger: Option<Finger>,
+    fling: Option<Fling>,
+}
+
+impl KineticScroll {
+    pub fn is_flinging(&self) -> bool {
+        self.fling.is_some()
+    }
+
+    pub fn on_touch(&mut self, touch: &Touch) {
+        let now = Instant::now();
+        let position = Point2D::new(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches += 1;
+                self.fling = None;
+                // A second finger makes it a pinch, which doesn't fling.
+                self.finger = (self.touches == 1).then_some(Finger {
+                    id: touch.id,
+                    position,
+                    time: now,
+                    velocity: Velocity::zero(),
+                });
+            },
+            TouchPhase::Moved => {
+                let Some(finger) = self.finger.as_mut().filter(|finger| finger.id == touch.id)
+                else {
+                    return;
+                };
+                let elapsed = (now - finger.time).as_secs_f32();
+                if elapsed > 0.0 {
+                    let velocity = (position - finger.position) / elapsed;
+                    finger.velocity = finger.velocity.lerp(velocity, 0.8);
+                }
+                finger.position = position;
+                finger.time = now;
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches = self.touches.saturating_sub(1);
+                let Some(finger) = self.finger.take() else {
+                    return;
+                };
+                if touch.phase == TouchPhase::Ended &&
+                    finger.id == touch.id &&
+                    now - finger.time < MAX_FLING_STILLNESS &&
+                    finger.velocity.length() >= MIN_FLING_VELOCITY
+                {
+                    self.fling = Some(Fling {
+                        velocity: finger.velocity,
+                        point: finger.position.to_i32(),
+                        last_scroll: now,
+                    });
+                }
+            },
+        }
+    }
+
+    /// How much to scroll by now, and where, while the page is flung.
+    pub fn scroll(&mut self) -> Option<(Vector2D<f32, DevicePixel>, DeviceIntPoint)> {
+        let fling = self.fling.as_mut()?;
+        let now = Instant::now();
+        let elapsed = (now - fling.last_scroll).as_secs_f32();
+        fling.last_scroll = now;
+        fling.velocity *= (-elapsed / FLING_TIME_CONSTANT).exp();
+        let scroll = (fling.velocity * elapsed, fling.point);
+        if fling.velocity.length() < MIN_VELOCITY {
+            self.fling = None;
+        }
+        Some(scroll)
+    }
+}
@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Memory usage as the operating system sees it, for the `memory_report` control command.
+//!
+//! TODO: Show Servo's own memory reports next to this, as a sortable tree in a panel of the
+//! minibrowser that can be saved as JSON. With `--memory-profile`, Servo's memory profiler only
+//! prints its reports to stdout now and then, and the embedder has no way to ask it for one. Servo
+//! also doesn't tell the embedder the ids of its content processes with `--multiprocess`, so
+//! only this process is reported for now.
+
+use std::process;
+
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ProcessMemory {
+    pub pid: u32,
+    /// The physical memory used by the process, or None if it can't be found out on this
+    /// platform.
+    pub resident_bytes: Option<u64>,
+}
+
+/// The memory used by this process.
+pub fn memory_report() -> Vec<ProcessMemory> {
+    vec![ProcessMemory {
+        pid: process::id(),
+        resident_bytes: resident_size(),
+    }]
+}
+
+#[cfg(target_os = "macos")]
+fn resident_size() -> Option<u64> {
+    crate::platform::macos::resident_size()
+}
+
+/// The second field of `/proc/self/statm` is the number of resident pages.
+#[cfg(target_os = "linux")]
+fn resident_size() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(target_os = "windows")]
+fn resident_size() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    counters.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, counters.cb) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn resident_size() -> Option<u64> {
+    None
+}
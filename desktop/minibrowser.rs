@@ -4,20 +4,26 @@
 
 use std::cell::{Cell, RefCell};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use egui::popup::popup_below_widget;
 use egui::{
-    pos2, CentralPanel, Color32, Frame, Key, Label, Modifiers, PaintCallback, Pos2, Spinner,
-    TopBottomPanel, Vec2,
+    pos2, Button, CentralPanel, Color32, ColorImage, DragValue, Frame, Key, Label, Modifiers,
+    PaintCallback, Pos2, RichText, Spinner, Stroke, TextureHandle, TextureOptions, TopBottomPanel,
+    Ui, Vec2,
 };
 use egui_glow::CallbackFn;
 use egui_winit::EventResponse;
 use euclid::{Box2D, Length, Point2D, Scale, Size2D};
 use gleam::gl;
 use glow::NativeFramebuffer;
+use image::RgbaImage;
 use log::{trace, warn};
+use servo::base::id::TopLevelBrowsingContextId as WebViewId;
 use servo::compositing::windowing::EmbedderEvent;
+use servo::embedder_traits::ContextMenuResult;
 use servo::script_traits::TraversalDirection;
 use servo::servo_geometry::DeviceIndependentPixel;
 use servo::servo_url::ServoUrl;
@@ -25,12 +31,20 @@ use servo::style_traits::DevicePixel;
 use servo::webrender_traits::RenderingContext;
 use winit::event::{ElementState, MouseButton};
 
+use super::bookmarks::{BookmarkId, Bookmarks};
+use super::device_emulation::{Emulation, DEVICES};
+use super::downloads::DownloadState;
 use super::egui_glue::EguiGlow;
 use super::events_loop::EventsLoop;
+use super::favicon::FAVICON_SIZE;
 use super::geometry::winit_position_to_euclid_point;
-use super::webview::{LoadStatus, WebViewManager};
+use super::mobile_sites::MOBILE_VIEWPORT_WIDTH;
+use super::new_tab::NEW_TAB_LOCATION;
+use super::view_source::{ViewSource, VIEW_SOURCE_PREFIX};
+use super::webview::{LoadStatus, WebView, WebViewManager};
 use super::window_trait::WindowPortsMethods;
-use crate::parser::location_bar_input_to_url;
+use super::zoom::is_default_zoom;
+use crate::parser::{fix_up_url, location_bar_input_to_url};
 
 pub struct Minibrowser {
     pub context: EguiGlow,
@@ -48,23 +62,231 @@ pub struct Minibrowser {
     /// Whether the location has been edited by the user without clicking Go.
     location_dirty: Cell<bool>,
 
+    /// Pages from the history and bookmarks matching the edited location.
+    suggestions: Vec<Suggestion>,
+
+    /// The suggestion chosen with the arrow keys, if any.
+    selected_suggestion: Option<usize>,
+
     load_status: LoadStatus,
 
     status_text: Option<String>,
+
+    page_zoom: f32,
+
+    /// How many popups were blocked in the active tab, as of the last update.
+    blocked_popups: usize,
+
+    /// Whether media was paused in the active tab by `--autoplay block`, as of the last update.
+    autoplay_blocked: bool,
+
+    /// Whether the active tab is in reader mode, or None if it can't be, as of the last update.
+    reader_mode: Option<bool>,
+
+    /// Whether this is a private window.
+    private: bool,
+
+    /// The id and title of each tab as of the last update, whether it is discarded, playing
+    /// media and muted, and which tab is active.
+    tab_strip: (
+        Vec<(WebViewId, String, bool, bool, bool)>,
+        Option<WebViewId>,
+    ),
+
+    /// The tab being dragged along the tab strip to move it, if any.
+    dragged_tab: Option<WebViewId>,
+
+    /// The session history of the active tab, and the index of its current entry.
+    session_history: (Vec<ServoUrl>, usize),
+
+    /// The favicons generation as of the last update.
+    favicons_generation: u64,
+
+    /// The textures of the favicons shown so far, for each favicon image.
+    favicon_textures: Vec<(Arc<RgbaImage>, TextureHandle)>,
+
+    /// Whether the bookmarks bar is visible, and the bookmarks generation, as of the last update.
+    bookmarks_bar: (bool, u64),
+
+    /// Whether the site permissions window is open.
+    permissions_open: bool,
+
+    /// Whether the site settings window, for the origin of the active tab, is open.
+    site_settings_open: bool,
+
+    /// Whether the device emulation window is open.
+    emulation_open: bool,
+
+    /// The custom screen being edited in the device emulation window.
+    custom_emulation: Emulation,
+
+    /// The device pixel ratio being edited in the device emulation window.
+    custom_device_pixel_ratio: f32,
+
+    /// Whether the response headers window is open.
+    response_headers_open: bool,
+
+    /// Where the context menu is shown, while it is.
+    context_menu_position: Option<Pos2>,
+
+    /// The frame timing shown in the overlay, if it is visible, and when it was last updated.
+    frame_stats: Option<(String, Instant)>,
+
+    /// Whether the page made the window fullscreen, as of the last update.
+    content_fullscreen: bool,
+
+    /// When the page made the window fullscreen, while the hint to press Esc is shown.
+    fullscreen_hint: Option<Instant>,
+
+    /// How far a trackpad swipe has gone towards going back or forward, while there is one.
+    swipe_progress: Option<f32>,
 }
 
 pub enum MinibrowserEvent {
     /// Go button clicked.
     Go,
+    /// Go back this many entries in the session history.
+    Back(usize),
+    /// Go forward this many entries in the session history.
+    Forward(usize),
+    /// Reload button clicked, with shift held for a hard reload.
+    Reload {
+        hard: bool,
+    },
+    /// New tab button clicked.
+    NewTab,
+    /// Home button clicked.
+    Home,
+    /// A tab in the tab strip was clicked.
+    ActivateTab(WebViewId),
+    /// The close button of a tab was clicked.
+    CloseTab(WebViewId),
+    /// "Sleep" was chosen in the context menu of a tab.
+    DiscardTab(WebViewId),
+    /// The speaker of a tab was clicked, to mute or unmute it.
+    ToggleMute(WebViewId),
+    /// "Request mobile site" or "Request desktop site" was chosen in the menu of a tab.
+    ToggleMobileSite(WebViewId),
+    /// A tab was dragged to another place in the tab strip, before the tab at that index.
+    MoveTab(WebViewId, usize),
+    /// The zoom level was clicked.
+    ResetZoom,
+    /// A device to emulate, or none, was chosen in the device emulation window.
+    Emulate(Option<Emulation>),
+    /// The device pixel ratio was overridden, or not, in the device emulation window.
+    SetDevicePixelRatio(Option<f32>),
+    /// The reader mode button was clicked.
+    ToggleReaderMode,
+    /// "Always allow" was chosen in the menu of blocked popups.
+    AllowPopups,
+    /// Blocked media was played from its menu, and always allowed to autoplay if true.
+    PlayBlockedMedia(bool),
+    /// The power saving button was clicked, to turn power saving on (true) or off (false).
+    SetPowerSaving(bool),
+    /// The cache button was clicked, to turn the HTTP cache on (true) or off (false).
+    SetHttpCache(bool),
+    /// The response headers window was opened (true) or closed (false).
+    InspectResponseHeaders(bool),
+    /// "Copy headers" was clicked in the response headers window.
+    CopyResponseHeaders,
+    /// A download was cancelled from the downloads menu.
+    CancelDownload(usize),
+    /// "Clear finished" was clicked in the downloads menu.
+    ClearDownloads,
+    /// A bookmark was clicked.
+    OpenBookmark(String),
+    MoveBookmark(BookmarkId),
+    RemoveBookmark(BookmarkId),
+    ImportBookmarks,
+    ExportBookmarks,
+    /// Files were dropped onto the window, and onto a tab in the tab strip if Some.
+    DropFiles(Option<WebViewId>, Vec<PathBuf>),
+    /// An entry of the context menu was chosen, or it was dismissed.
+    ContextMenu(ContextMenuAction),
+    /// Forget the decision for a feature in an origin, in the site permissions window.
+    RevokePermission {
+        origin: String,
+        feature: String,
+    },
+    /// The zoom of an origin was reset in the site settings window.
+    ResetSiteZoom(ServoUrl),
+    /// The mobile or desktop site of an origin was reset in the site settings window.
+    ResetMobileSite(ServoUrl),
+}
+
+#[derive(Clone, Copy)]
+pub enum ContextMenuAction {
+    /// One of the items that the page added to the menu.
+    Item(usize),
     Back,
     Forward,
+    Reload,
+    CopyAddress,
+    CopyAsCurl,
+    Bookmark,
+    SavePage,
+    ViewSource,
+    Dismiss,
+}
+
+/// An entry of the suggestions shown while editing the location.
+struct Suggestion {
+    label: String,
+    /// What choosing this suggestion sets the location to.
+    location: String,
+    /// The URL of the suggested page, if this is a page, to show its favicon.
+    url: Option<ServoUrl>,
+}
+
+impl Suggestion {
+    fn page(url: &str, title: Option<&str>) -> Self {
+        let label = match title {
+            Some(title) if !title.is_empty() => format!("{title} — {url}"),
+            _ => url.to_owned(),
+        };
+        Self {
+            label,
+            location: url.to_owned(),
+            url: ServoUrl::parse(url).ok(),
+        }
+    }
 }
 
+/// The color of the private mode indicator in the toolbar.
+const PRIVATE_COLOR: Color32 = Color32::from_rgb(0xa0, 0x70, 0xff);
+
+/// The most history entries suggested while editing the location.
+const MAX_SUGGESTIONS: usize = 8;
+
+/// The most session history entries listed in the back and forward menus.
+const MAX_SESSION_HISTORY_MENU_ITEMS: usize = 15;
+
+/// The height of the load progress bar, and how long it takes to sweep across the window.
+const LOAD_PROGRESS_HEIGHT: f32 = 2.0;
+const LOAD_PROGRESS_PERIOD: f64 = 1.5;
+
+/// The longest tab title shown in the tab strip, in characters.
+const MAX_TAB_TITLE_LENGTH: usize = 24;
+
+/// How often the frame timing overlay is updated. Updating it makes a frame of its own, so
+/// updating it every frame would keep the minibrowser busy.
+const FRAME_STATS_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The longest status text shown at the bottom of the window, in characters.
+const MAX_STATUS_TEXT_LENGTH: usize = 96;
+
+/// The width of the outline around the webview while files are dragged over the window.
+const DROP_HIGHLIGHT_WIDTH: f32 = 3.0;
+
+/// How long the hint to press Esc is shown after a page makes the window fullscreen.
+const FULLSCREEN_HINT_DURATION: Duration = Duration::from_secs(3);
+
 impl Minibrowser {
     pub fn new(
         rendering_context: &RenderingContext,
         events_loop: &EventsLoop,
         initial_url: ServoUrl,
+        private: bool,
     ) -> Self {
         let gl = unsafe {
             glow::Context::from_loader_function(|s| rendering_context.get_proc_address(s))
@@ -88,8 +310,36 @@ impl Minibrowser {
             last_mouse_position: None,
             location: RefCell::new(initial_url.to_string()),
             location_dirty: false.into(),
+            suggestions: vec![],
+            selected_suggestion: None,
             load_status: LoadStatus::LoadComplete,
             status_text: None,
+            page_zoom: 1.0,
+            blocked_popups: 0,
+            autoplay_blocked: false,
+            reader_mode: None,
+            private,
+            tab_strip: Default::default(),
+            dragged_tab: None,
+            session_history: Default::default(),
+            favicons_generation: 0,
+            favicon_textures: vec![],
+            bookmarks_bar: Default::default(),
+            permissions_open: false,
+            site_settings_open: false,
+            emulation_open: false,
+            custom_emulation: Emulation {
+                width: 390,
+                height: 844,
+                device_pixel_ratio: 3.0,
+            },
+            custom_device_pixel_ratio: 2.0,
+            response_headers_open: false,
+            context_menu_position: None,
+            frame_stats: None,
+            content_fullscreen: false,
+            fullscreen_hint: None,
+            swipe_progress: None,
         }
     }
 
@@ -119,7 +369,7 @@ impl Minibrowser {
             } => {
                 self.event_queue
                     .borrow_mut()
-                    .push(MinibrowserEvent::Forward);
+                    .push(MinibrowserEvent::Forward(1));
                 true
             },
             winit::event::WindowEvent::MouseInput {
@@ -127,13 +377,19 @@ impl Minibrowser {
                 button: MouseButton::Back,
                 ..
             } => {
-                self.event_queue.borrow_mut().push(MinibrowserEvent::Back);
+                self.event_queue
+                    .borrow_mut()
+                    .push(MinibrowserEvent::Back(1));
                 true
             },
+            // Popups like the location suggestions can be over the webview.
             winit::event::WindowEvent::MouseWheel { .. } |
-            winit::event::WindowEvent::MouseInput { .. } => self
-                .last_mouse_position
-                .map_or(false, |p| self.is_in_browser_rect(p)),
+            winit::event::WindowEvent::MouseInput { .. } => {
+                self.context.egui_ctx.is_pointer_over_area() ||
+                    self
+                        .last_mouse_position
+                        .map_or(false, |p| self.is_in_browser_rect(p))
+            },
             _ => true,
         };
         result
@@ -160,6 +416,18 @@ impl Minibrowser {
             now - self.last_update,
             reason
         );
+        let content_fullscreen = webviews.content_fullscreen();
+        if content_fullscreen != self.content_fullscreen {
+            self.content_fullscreen = content_fullscreen;
+            self.fullscreen_hint = content_fullscreen.then_some(now);
+        }
+        if self
+            .fullscreen_hint
+            .is_some_and(|shown| now - shown >= FULLSCREEN_HINT_DURATION)
+        {
+            self.fullscreen_hint = None;
+        }
+        self.swipe_progress = webviews.swipe_progress();
         let Self {
             context,
             event_queue,
@@ -168,33 +436,363 @@ impl Minibrowser {
             last_update,
             location,
             location_dirty,
+            suggestions,
+            selected_suggestion,
+            favicon_textures,
+            dragged_tab,
             ..
         } = self;
         let widget_fbo = *widget_surface_fbo;
         let _duration = context.run(window, |ctx| {
+            // Files dropped onto a tab are opened in that tab, if the platform tells us where
+            // the pointer is while dragging, otherwise in the active tab.
+            let hovering_files = ctx.input(|i| !i.raw.hovered_files.is_empty());
+            let dropped_files: Vec<PathBuf> = ctx.input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|file| file.path.clone())
+                    .collect()
+            });
+            let mut drop_target = None;
             // TODO: While in fullscreen add some way to mitigate the increased phishing risk
             // when not displaying the URL bar: https://github.com/servo/servo/issues/32443
-            if window.fullscreen().is_none() {
-                TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            if !self.content_fullscreen {
+                TopBottomPanel::top("tabs").show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let active_webview_id = webviews.tabs().active_id();
+                        let mut tab_rects = vec![];
+                        let mut dropped = false;
+                        for webview in webviews.tabs().iter() {
+                            let url = webview.pending_url.as_ref().or(webview.url.as_ref());
+                            show_favicon(ui, favicon_textures, webviews, url);
+                            // Muted tabs keep their muted speaker, even once they stop playing.
+                            let speaker = if webview.muted {
+                                Some(("🔇", "Unmute tab"))
+                            } else {
+                                webview.media_playing.then_some(("🔊", "Mute tab"))
+                            };
+                            if let Some((icon, hint)) = speaker {
+                                if ui.small_button(icon).on_hover_text(hint).clicked() {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::ToggleMute(webview.id));
+                                }
+                            }
+                            let selected = Some(webview.id) == active_webview_id;
+                            // Discarded tabs are dimmed until they load again.
+                            let mut title = RichText::new(tab_title(webview));
+                            if webview.is_discarded() {
+                                title = title.italics().weak();
+                            }
+                            let tab = ui
+                                .selectable_label(selected, title)
+                                .interact(egui::Sense::drag())
+                                .on_hover_text(webview.display_title());
+                            if tab.drag_started() {
+                                *dragged_tab = Some(webview.id);
+                            }
+                            dropped |= tab.drag_stopped();
+                            tab_rects.push(tab.rect);
+                            tab.context_menu(|ui| {
+                                let can_discard = !selected && !webview.is_discarded();
+                                if ui.add_enabled(can_discard, Button::new("Sleep")).clicked() {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::DiscardTab(webview.id));
+                                    ui.close_menu();
+                                }
+                                let site = if webview.mobile {
+                                    "Request desktop site"
+                                } else {
+                                    "Request mobile site"
+                                };
+                                if ui.button(site).clicked() {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::ToggleMobileSite(webview.id));
+                                    ui.close_menu();
+                                }
+                            });
+                            let tab = if (hovering_files || !dropped_files.is_empty()) &&
+                                tab.contains_pointer()
+                            {
+                                drop_target = Some(webview.id);
+                                tab.highlight()
+                            } else {
+                                tab
+                            };
+                            if tab.clicked() {
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::ActivateTab(webview.id));
+                            }
+                            if ui.small_button("×").clicked() {
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::CloseTab(webview.id));
+                            }
+                            ui.separator();
+                        }
+                        // Show where the dragged tab will go, before the first tab right of the
+                        // pointer, and move it there once it is dropped.
+                        if let Some(webview_id) = *dragged_tab {
+                            if let Some(pointer) = ui.ctx().pointer_interact_pos() {
+                                let index = tab_rects
+                                    .iter()
+                                    .filter(|rect| rect.center().x < pointer.x)
+                                    .count();
+                                let x = match (tab_rects.get(index), tab_rects.last()) {
+                                    (Some(rect), _) => rect.left() - 2.0,
+                                    (None, Some(rect)) => rect.right() + 2.0,
+                                    (None, None) => pointer.x,
+                                };
+                                let stroke = Stroke::new(2.0, ui.visuals().selection.stroke.color);
+                                ui.painter().vline(x, ui.max_rect().y_range(), stroke);
+                                if dropped {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::MoveTab(webview_id, index));
+                                }
+                            }
+                            if dropped || !ui.ctx().input(|i| i.pointer.primary_down()) {
+                                *dragged_tab = None;
+                            }
+                        }
+                        if ui.button("+").clicked() {
+                            event_queue.borrow_mut().push(MinibrowserEvent::NewTab);
+                        }
+                    });
+                });
+                let toolbar = TopBottomPanel::top("toolbar").show(ctx, |ui| {
                     ui.allocate_ui_with_layout(
                         ui.available_size(),
                         egui::Layout::left_to_right(egui::Align::Center),
                         |ui| {
-                            if ui.button("back").clicked() {
-                                event_queue.borrow_mut().push(MinibrowserEvent::Back);
+                            if self.private {
+                                ui.label(RichText::new("private").color(PRIVATE_COLOR))
+                                    .on_hover_text("Nothing about this window is saved to disk");
+                            }
+                            // Right clicking or long pressing (on touch screens) the back and
+                            // forward buttons lists the entries they can go to.
+                            let (ref entries, current) = self.session_history;
+                            let back = ui.add_enabled(current > 0, Button::new("back"));
+                            if back.clicked() {
+                                event_queue.borrow_mut().push(MinibrowserEvent::Back(1));
                             }
-                            if ui.button("forward").clicked() {
-                                event_queue.borrow_mut().push(MinibrowserEvent::Forward);
+                            back.context_menu(|ui| {
+                                let older = entries[..current].iter().rev();
+                                for (steps, url) in
+                                    (1..).zip(older).take(MAX_SESSION_HISTORY_MENU_ITEMS)
+                                {
+                                    if ui.button(url.as_str()).clicked() {
+                                        event_queue
+                                            .borrow_mut()
+                                            .push(MinibrowserEvent::Back(steps));
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            let forward =
+                                ui.add_enabled(current + 1 < entries.len(), Button::new("forward"));
+                            if forward.clicked() {
+                                event_queue.borrow_mut().push(MinibrowserEvent::Forward(1));
+                            }
+                            forward.context_menu(|ui| {
+                                let newer = entries.iter().skip(current + 1);
+                                for (steps, url) in
+                                    (1..).zip(newer).take(MAX_SESSION_HISTORY_MENU_ITEMS)
+                                {
+                                    if ui.button(url.as_str()).clicked() {
+                                        event_queue
+                                            .borrow_mut()
+                                            .push(MinibrowserEvent::Forward(steps));
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            if ui
+                                .button("reload")
+                                .on_hover_text("Hold shift to clear the cache and reload")
+                                .clicked()
+                            {
+                                let hard = ui.input(|i| i.modifiers.shift);
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::Reload { hard });
+                            }
+                            if ui.button("home").clicked() {
+                                event_queue.borrow_mut().push(MinibrowserEvent::Home);
                             }
                             ui.allocate_ui_with_layout(
                                 ui.available_size(),
                                 egui::Layout::right_to_left(egui::Align::Center),
                                 |ui| {
+                                    ui.toggle_value(&mut self.permissions_open, "🔑")
+                                        .on_hover_text("Site permissions");
+                                    ui.toggle_value(&mut self.emulation_open, "📱")
+                                        .on_hover_text("Device emulation");
+                                    let power_saving = webviews.power_saving();
+                                    if ui
+                                        .selectable_label(power_saving, "🔋")
+                                        .on_hover_text("Power saving for background tabs")
+                                        .clicked()
+                                    {
+                                        event_queue
+                                            .borrow_mut()
+                                            .push(MinibrowserEvent::SetPowerSaving(!power_saving));
+                                    }
+                                    let http_cache = webviews.http_cache_enabled();
+                                    if ui
+                                        .selectable_label(!http_cache, "no cache")
+                                        .on_hover_text("Don't cache network responses")
+                                        .clicked()
+                                    {
+                                        event_queue
+                                            .borrow_mut()
+                                            .push(MinibrowserEvent::SetHttpCache(!http_cache));
+                                    }
+                                    if ui
+                                        .toggle_value(&mut self.response_headers_open, "🗒")
+                                        .on_hover_text("Response headers")
+                                        .clicked()
+                                    {
+                                        event_queue.borrow_mut().push(
+                                            MinibrowserEvent::InspectResponseHeaders(
+                                                self.response_headers_open,
+                                            ),
+                                        );
+                                    }
+                                    if let Some(reader_mode) = self.reader_mode {
+                                        let hover_text = if reader_mode {
+                                            "Leave reader mode"
+                                        } else {
+                                            "Reader mode"
+                                        };
+                                        if ui
+                                            .selectable_label(reader_mode, "📖")
+                                            .on_hover_text(hover_text)
+                                            .clicked()
+                                        {
+                                            event_queue
+                                                .borrow_mut()
+                                                .push(MinibrowserEvent::ToggleReaderMode);
+                                        }
+                                    }
                                     if ui.button("go").clicked() {
                                         event_queue.borrow_mut().push(MinibrowserEvent::Go);
                                         location_dirty.set(false);
                                     }
 
+                                    if self.blocked_popups > 0 {
+                                        let text = match self.blocked_popups {
+                                            1 => "1 popup blocked".to_owned(),
+                                            count => format!("{count} popups blocked"),
+                                        };
+                                        ui.menu_button(format!("🚫 {text}"), |ui| {
+                                            ui.label(
+                                                "This page tried to open a popup without being \
+                                                 clicked.",
+                                            );
+                                            if ui
+                                                .button("Always allow popups from this site")
+                                                .clicked()
+                                            {
+                                                event_queue
+                                                    .borrow_mut()
+                                                    .push(MinibrowserEvent::AllowPopups);
+                                                ui.close_menu();
+                                            }
+                                        });
+                                    }
+
+                                    if self.autoplay_blocked {
+                                        ui.menu_button("⏸ Autoplay blocked", |ui| {
+                                            ui.label(
+                                                "This page tried to play media without being \
+                                                 clicked.",
+                                            );
+                                            for (text, always) in [
+                                                ("Play", false),
+                                                ("Always allow autoplay on this site", true),
+                                            ] {
+                                                if ui.button(text).clicked() {
+                                                    event_queue.borrow_mut().push(
+                                                        MinibrowserEvent::PlayBlockedMedia(always),
+                                                    );
+                                                    ui.close_menu();
+                                                }
+                                            }
+                                        });
+                                    }
+
+                                    let downloads = webviews.downloads().list();
+                                    if !downloads.is_empty() {
+                                        ui.menu_button("⬇ Downloads", |ui| {
+                                            for download in downloads {
+                                                ui.horizontal(|ui| {
+                                                    let status = match download.state {
+                                                        DownloadState::InProgress => {
+                                                            download.progress_text()
+                                                        },
+                                                        DownloadState::Done(_) => "Done".to_owned(),
+                                                        DownloadState::Failed(ref error) => {
+                                                            format!("Failed: {error}")
+                                                        },
+                                                        DownloadState::Cancelled => {
+                                                            "Cancelled".to_owned()
+                                                        },
+                                                    };
+                                                    let hover_text = match download.state {
+                                                        DownloadState::Done(ref path) => {
+                                                            path.display().to_string()
+                                                        },
+                                                        _ => download.url.to_string(),
+                                                    };
+                                                    ui.label(&download.name)
+                                                        .on_hover_text(hover_text);
+                                                    ui.weak(status);
+                                                    if let DownloadState::InProgress =
+                                                        download.state
+                                                    {
+                                                        if ui.small_button("Cancel").clicked() {
+                                                            event_queue.borrow_mut().push(
+                                                                MinibrowserEvent::CancelDownload(
+                                                                    download.id(),
+                                                                ),
+                                                            );
+                                                        }
+                                                    }
+                                                });
+                                            }
+                                            ui.separator();
+                                            if ui.button("Clear finished").clicked() {
+                                                event_queue
+                                                    .borrow_mut()
+                                                    .push(MinibrowserEvent::ClearDownloads);
+                                                ui.close_menu();
+                                            }
+                                        })
+                                        .response
+                                        .on_hover_text(
+                                            "Alt-click (Option-click on macOS) links to download \
+                                             them",
+                                        );
+                                    }
+
+                                    if !is_default_zoom(self.page_zoom) {
+                                        let zoom_level = format!("{:.0}%", self.page_zoom * 100.0);
+                                        if ui
+                                            .button(zoom_level)
+                                            .on_hover_text("Reset zoom")
+                                            .clicked()
+                                        {
+                                            event_queue
+                                                .borrow_mut()
+                                                .push(MinibrowserEvent::ResetZoom);
+                                        }
+                                    }
+
                                     match self.load_status {
                                         LoadStatus::LoadStart => {
                                             ui.add(Spinner::new().color(Color32::GRAY));
@@ -205,14 +803,81 @@ impl Minibrowser {
                                         LoadStatus::LoadComplete => { /* No Spinner */ },
                                     }
 
+                                    ui.toggle_value(&mut self.site_settings_open, "ℹ")
+                                        .on_hover_text("Site settings");
+
+                                    // Keep the focus on Tab, which completes the location instead.
                                     let location_field = ui.add_sized(
                                         ui.available_size(),
-                                        egui::TextEdit::singleline(&mut *location.borrow_mut()),
+                                        egui::TextEdit::singleline(&mut *location.borrow_mut())
+                                            .lock_focus(true),
                                     );
 
+                                    let suggestions_id = ui.make_persistent_id("suggestions");
                                     if location_field.changed() {
                                         location_dirty.set(true);
+                                        *suggestions = suggest(webviews, &location.borrow());
+                                        *selected_suggestion = None;
+                                        if suggestions.is_empty() {
+                                            ui.memory_mut(|m| m.close_popup());
+                                        } else {
+                                            ui.memory_mut(|m| m.open_popup(suggestions_id));
+                                        }
+                                    }
+                                    let popup_open = ui.memory(|m| m.is_popup_open(suggestions_id));
+                                    if location_field.has_focus() && popup_open {
+                                        let (down, up, tab) = ui.input_mut(|i| {
+                                            (
+                                                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                                                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                                                i.consume_key(Modifiers::NONE, Key::Tab),
+                                            )
+                                        });
+                                        let last = suggestions.len().saturating_sub(1);
+                                        if down {
+                                            *selected_suggestion = Some(
+                                                selected_suggestion
+                                                    .map_or(0, |i| (i + 1).min(last)),
+                                            );
+                                        }
+                                        if up {
+                                            *selected_suggestion =
+                                                selected_suggestion.and_then(|i| i.checked_sub(1));
+                                        }
+                                        let completion =
+                                            suggestions.get(selected_suggestion.unwrap_or(0));
+                                        if let (true, Some(completion)) = (tab, completion) {
+                                            *location.borrow_mut() = completion.location.clone();
+                                            move_cursor_to_end(
+                                                ui,
+                                                &location_field,
+                                                &location.borrow(),
+                                            );
+                                        }
                                     }
+                                    popup_below_widget(ui, suggestions_id, &location_field, |ui| {
+                                        for (index, suggestion) in suggestions.iter().enumerate() {
+                                            let selected = *selected_suggestion == Some(index);
+                                            let clicked = ui
+                                                .horizontal(|ui| {
+                                                    show_favicon(
+                                                        ui,
+                                                        favicon_textures,
+                                                        webviews,
+                                                        suggestion.url.as_ref(),
+                                                    );
+                                                    ui.selectable_label(selected, &suggestion.label)
+                                                        .clicked()
+                                                })
+                                                .inner;
+                                            if clicked {
+                                                *location.borrow_mut() =
+                                                    suggestion.location.clone();
+                                                event_queue.borrow_mut().push(MinibrowserEvent::Go);
+                                                location_dirty.set(false);
+                                            }
+                                        }
+                                    });
                                     if ui.input(|i| {
                                         i.clone().consume_key(Modifiers::COMMAND, Key::L)
                                     }) {
@@ -221,6 +886,12 @@ impl Minibrowser {
                                     if location_field.lost_focus() &&
                                         ui.input(|i| i.clone().key_pressed(Key::Enter))
                                     {
+                                        let selected = selected_suggestion
+                                            .take()
+                                            .and_then(|index| suggestions.get(index));
+                                        if let Some(suggestion) = selected {
+                                            *location.borrow_mut() = suggestion.location.clone();
+                                        }
                                         event_queue.borrow_mut().push(MinibrowserEvent::Go);
                                         location_dirty.set(false);
                                     }
@@ -229,7 +900,334 @@ impl Minibrowser {
                         },
                     );
                 });
+                match self.load_status {
+                    LoadStatus::LoadStart => {
+                        paint_load_progress(ctx, toolbar.response.rect, Color32::GRAY)
+                    },
+                    LoadStatus::HeadParsed => {
+                        paint_load_progress(ctx, toolbar.response.rect, Color32::WHITE)
+                    },
+                    LoadStatus::LoadComplete => { /* No progress bar */ },
+                }
+                if webviews.bookmarks_bar_visible() {
+                    TopBottomPanel::top("bookmarks").show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            show_bookmarks(ui, webviews.bookmarks(), None, event_queue);
+                            ui.menu_button("⋯", |ui| {
+                                if ui.button("Import bookmarks…").clicked() {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::ImportBookmarks);
+                                    ui.close_menu();
+                                }
+                                if ui.button("Export bookmarks…").clicked() {
+                                    event_queue
+                                        .borrow_mut()
+                                        .push(MinibrowserEvent::ExportBookmarks);
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                    });
+                }
             };
+            if !dropped_files.is_empty() {
+                event_queue
+                    .borrow_mut()
+                    .push(MinibrowserEvent::DropFiles(drop_target, dropped_files));
+            }
+            match webviews.context_menu() {
+                None => self.context_menu_position = None,
+                Some(menu) => {
+                    let position = *self.context_menu_position.get_or_insert_with(|| {
+                        ctx.input(|i| i.pointer.latest_pos()).unwrap_or_default()
+                    });
+                    let choose = |ui: &mut Ui, enabled: bool, label: &str, action| {
+                        if ui.add_enabled(enabled, Button::new(label)).clicked() {
+                            event_queue
+                                .borrow_mut()
+                                .push(MinibrowserEvent::ContextMenu(action));
+                        }
+                    };
+                    let (ref entries, current) = self.session_history;
+                    let is_http = webviews
+                        .current_url()
+                        .is_some_and(|url| matches!(url.scheme(), "http" | "https"));
+                    let has_source = webviews.current_url().is_some_and(ViewSource::can_open);
+                    let area = egui::Area::new(egui::Id::new("context menu"))
+                        .order(egui::Order::Foreground)
+                        .fixed_pos(position)
+                        .show(ctx, |ui| {
+                            Frame::menu(ui.style()).show(ui, |ui| {
+                                if let Some(ref title) = menu.title {
+                                    ui.label(RichText::new(title).strong());
+                                }
+                                for (index, item) in menu.items.iter().enumerate() {
+                                    choose(ui, true, item, ContextMenuAction::Item(index));
+                                }
+                                if menu.title.is_some() || !menu.items.is_empty() {
+                                    ui.separator();
+                                }
+                                choose(ui, current > 0, "Back", ContextMenuAction::Back);
+                                let can_go_forward = current + 1 < entries.len();
+                                choose(ui, can_go_forward, "Forward", ContextMenuAction::Forward);
+                                choose(ui, true, "Reload", ContextMenuAction::Reload);
+                                ui.separator();
+                                choose(
+                                    ui,
+                                    true,
+                                    "Copy page address",
+                                    ContextMenuAction::CopyAddress,
+                                );
+                                choose(ui, is_http, "Copy as cURL", ContextMenuAction::CopyAsCurl);
+                                choose(ui, true, "Bookmark page", ContextMenuAction::Bookmark);
+                                choose(ui, true, "Save page as…", ContextMenuAction::SavePage);
+                                choose(
+                                    ui,
+                                    has_source,
+                                    "View page source",
+                                    ContextMenuAction::ViewSource,
+                                );
+                            });
+                        });
+                    if area.response.clicked_elsewhere() ||
+                        ctx.input(|i| i.key_pressed(Key::Escape))
+                    {
+                        event_queue
+                            .borrow_mut()
+                            .push(MinibrowserEvent::ContextMenu(ContextMenuAction::Dismiss));
+                    }
+                },
+            }
+            egui::Window::new("Site permissions")
+                .open(&mut self.permissions_open)
+                .show(ctx, |ui| {
+                    let permissions = webviews.permissions();
+                    if permissions.is_empty() {
+                        ui.label("No site has asked for a permission yet");
+                    }
+                    egui::Grid::new("permissions").striped(true).show(ui, |ui| {
+                        for (origin, feature, granted) in permissions.iter() {
+                            ui.label(origin);
+                            ui.label(feature);
+                            ui.label(if granted { "allowed" } else { "blocked" });
+                            if ui
+                                .button("revoke")
+                                .on_hover_text("Ask again next time")
+                                .clicked()
+                            {
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::RevokePermission {
+                                        origin: origin.to_owned(),
+                                        feature: feature.to_owned(),
+                                    });
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+            // TODO: Show how many cookies and how much localStorage the origin has, to clear
+            // them from here. Servo keeps both in threads of its own that the embedder can't
+            // reach. Autoplay that was always allowed is listed with the permissions.
+            egui::Window::new("Site settings")
+                .open(&mut self.site_settings_open)
+                .show(ctx, |ui| {
+                    let Some(url) = webviews.current_url().filter(|url| url.origin().is_tuple())
+                    else {
+                        ui.label("This page has no site settings");
+                        return;
+                    };
+                    let origin = url.origin().ascii_serialization();
+                    ui.label(RichText::new(&origin).strong());
+                    egui::Grid::new("site settings")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (feature, granted) in webviews.permissions().for_origin(url) {
+                                ui.label(feature);
+                                ui.label(if granted { "allowed" } else { "blocked" });
+                                if ui
+                                    .button("reset")
+                                    .on_hover_text("Ask again next time")
+                                    .clicked()
+                                {
+                                    event_queue.borrow_mut().push(
+                                        MinibrowserEvent::RevokePermission {
+                                            origin: origin.clone(),
+                                            feature: feature.to_owned(),
+                                        },
+                                    );
+                                }
+                                ui.end_row();
+                            }
+
+                            let zoom = webviews.site_zoom(url);
+                            ui.label("Zoom");
+                            ui.label(format!("{:.0}%", zoom * 100.0));
+                            if ui
+                                .add_enabled(!is_default_zoom(zoom), Button::new("reset"))
+                                .clicked()
+                            {
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::ResetSiteZoom(url.clone()));
+                            }
+                            ui.end_row();
+
+                            let (mobile, chosen) = webviews.site_mobile(url);
+                            ui.label("Site");
+                            ui.label(if mobile { "mobile" } else { "desktop" });
+                            if ui.add_enabled(chosen, Button::new("reset")).clicked() {
+                                event_queue
+                                    .borrow_mut()
+                                    .push(MinibrowserEvent::ResetMobileSite(url.clone()));
+                            }
+                            ui.end_row();
+                        });
+                });
+
+            egui::Window::new("Device emulation")
+                .open(&mut self.emulation_open)
+                .show(ctx, |ui| {
+                    let current = webviews.emulation();
+                    let emulate = |emulation| {
+                        event_queue
+                            .borrow_mut()
+                            .push(MinibrowserEvent::Emulate(emulation));
+                    };
+                    if ui.radio(current.is_none(), "Off").clicked() {
+                        emulate(None);
+                    }
+                    for device in DEVICES {
+                        let emulation = Emulation::from(device);
+                        let selected = current.is_some_and(|current| {
+                            current == emulation || current == emulation.rotated()
+                        });
+                        let label = format!(
+                            "{} ({}×{}, {}x)",
+                            device.name, device.width, device.height, device.device_pixel_ratio
+                        );
+                        if ui.radio(selected, label).clicked() {
+                            emulate(Some(emulation));
+                        }
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let custom = &mut self.custom_emulation;
+                        ui.add(DragValue::new(&mut custom.width).clamp_range(1..=8192));
+                        ui.label("×");
+                        ui.add(DragValue::new(&mut custom.height).clamp_range(1..=8192));
+                        ui.add(
+                            DragValue::new(&mut custom.device_pixel_ratio)
+                                .clamp_range(0.5..=8.0)
+                                .speed(0.05)
+                                .suffix("x"),
+                        );
+                        if ui.button("Emulate").clicked() {
+                            emulate(Some(*custom));
+                        }
+                    });
+                    if ui
+                        .add_enabled(current.is_some(), Button::new("Rotate"))
+                        .on_hover_text("Switch between portrait and landscape")
+                        .clicked()
+                    {
+                        emulate(current.map(Emulation::rotated));
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let current = webviews.device_pixel_ratio_override();
+                        let mut overridden = current.is_some();
+                        let ratio = &mut self.custom_device_pixel_ratio;
+                        if let Some(current) = current {
+                            *ratio = current;
+                        }
+                        let toggled = ui
+                            .checkbox(&mut overridden, "Device pixel ratio")
+                            .on_hover_text(format!(
+                                "Instead of the {}x of the screen",
+                                webviews.device_hidpi_factor()
+                            ))
+                            .changed();
+                        let edited = ui
+                            .add_enabled(
+                                overridden,
+                                DragValue::new(ratio)
+                                    .clamp_range(0.5..=8.0)
+                                    .speed(0.05)
+                                    .suffix("x"),
+                            )
+                            .changed();
+                        if toggled || edited {
+                            event_queue
+                                .borrow_mut()
+                                .push(MinibrowserEvent::SetDevicePixelRatio(
+                                    overridden.then_some(*ratio),
+                                ));
+                        }
+                    });
+                });
+
+            let response_headers_open = self.response_headers_open;
+            egui::Window::new("Response headers")
+                .open(&mut self.response_headers_open)
+                .show(ctx, |ui| {
+                    let headers = webviews.response_headers();
+                    let responses = match headers.responses() {
+                        Some(Ok(responses)) => responses,
+                        Some(Err(error)) => {
+                            ui.label(error);
+                            return;
+                        },
+                        None => {
+                            if headers.url().is_some() {
+                                ui.horizontal(|ui| {
+                                    ui.add(Spinner::new());
+                                    ui.label("Fetching the page again…");
+                                });
+                            } else {
+                                ui.label("No page has been loaded yet");
+                            }
+                            return;
+                        },
+                    };
+                    let Some((response, redirects)) = responses.split_last() else {
+                        return;
+                    };
+                    for redirect in redirects {
+                        ui.label(format!(
+                            "{} {} → {}",
+                            redirect.status,
+                            redirect.url,
+                            redirect.location().unwrap_or_default()
+                        ));
+                    }
+                    ui.label(RichText::new(&response.status_line).strong());
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("response headers")
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for (name, value) in &response.headers {
+                                        ui.label(RichText::new(name).monospace());
+                                        ui.label(RichText::new(value).monospace());
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    if ui.button("Copy headers").clicked() {
+                        event_queue
+                            .borrow_mut()
+                            .push(MinibrowserEvent::CopyResponseHeaders);
+                    }
+                });
+            if response_headers_open && !self.response_headers_open {
+                event_queue
+                    .borrow_mut()
+                    .push(MinibrowserEvent::InspectResponseHeaders(false));
+            }
 
             // The toolbar height is where the Context’s available rect starts.
             // For reasons that are unclear, the TopBottomPanel’s ui cursor exceeds this by one egui
@@ -238,6 +1236,7 @@ impl Minibrowser {
 
             let scale =
                 Scale::<_, DeviceIndependentPixel, DevicePixel>::new(ctx.pixels_per_point());
+            let emulation = webviews.emulation();
             let Some(focused_webview_id) = webviews.focused_webview_id() else {
                 return;
             };
@@ -249,24 +1248,35 @@ impl Minibrowser {
             CentralPanel::default()
                 .frame(Frame::none())
                 .show(ctx, |ui| {
-                    let Pos2 { x, y } = ui.cursor().min;
-                    let Vec2 {
-                        x: width,
-                        y: height,
-                    } = ui.available_size();
-                    let rect = Box2D::from_origin_and_size(
-                        Point2D::new(x, y),
-                        Size2D::new(width, height),
+                    let mut min = ui.cursor().min;
+                    let mut size = ui.available_size();
+                    ui.allocate_space(size);
+                    // Emulated screens are framed in the middle, as many device pixels as they
+                    // have, and cut off if they don't fit, since their viewport must stay the same
+                    // size. Mobile sites are laid out at the width of a phone, in the middle.
+                    let mut webview_size = size;
+                    if let Some(emulation) = emulation {
+                        let (width, height) = emulation.device_size();
+                        webview_size = Vec2::new(width, height) / ctx.pixels_per_point();
+                        min += ((size - webview_size) / 2.0).max(Vec2::ZERO);
+                        size = size.min(webview_size);
+                    } else if webview.mobile && size.x > MOBILE_VIEWPORT_WIDTH {
+                        min.x += (size.x - MOBILE_VIEWPORT_WIDTH) / 2.0;
+                        size.x = MOBILE_VIEWPORT_WIDTH;
+                        webview_size = size;
+                    }
+                    let webview_rect = Box2D::from_origin_and_size(
+                        Point2D::new(min.x, min.y),
+                        Size2D::new(webview_size.x, webview_size.y),
                     ) * scale;
-                    if rect != webview.rect {
-                        webview.rect = rect;
-                        embedder_events
-                            .push(EmbedderEvent::MoveResizeWebView(focused_webview_id, rect));
+                    if webview_rect != webview.rect {
+                        webview.rect = webview_rect;
+                        embedder_events.push(EmbedderEvent::MoveResizeWebView(
+                            focused_webview_id,
+                            webview_rect,
+                        ));
                     }
-                    let min = ui.cursor().min;
-                    let size = ui.available_size();
                     let rect = egui::Rect::from_min_size(min, size);
-                    ui.allocate_space(size);
 
                     let Some(servo_fbo) = servo_framebuffer_id else {
                         return;
@@ -278,7 +1288,7 @@ impl Minibrowser {
                             ui.layer_id(),
                             "tooltip layer".into(),
                             pos2(0.0, ctx.available_rect().max.y),
-                            |ui| ui.add(Label::new(status_text.clone()).extend()),
+                            |ui| ui.add(Label::new(elide_middle(status_text)).extend()),
                         );
                     }
 
@@ -321,6 +1331,27 @@ impl Minibrowser {
                             }
                         })),
                     });
+
+                    if let Some((ref text, _)) = self.frame_stats {
+                        paint_frame_stats(ui, rect, text);
+                    }
+
+                    if self.fullscreen_hint.is_some() {
+                        paint_fullscreen_hint(ui, rect);
+                    }
+
+                    if let Some(progress) = self.swipe_progress {
+                        paint_swipe_progress(ui, rect, progress);
+                    }
+
+                    if hovering_files && drop_target.is_none() {
+                        let color = ui.visuals().selection.stroke.color;
+                        ui.painter().rect_stroke(
+                            rect,
+                            0.0,
+                            Stroke::new(DROP_HIGHLIGHT_WIDTH, color),
+                        );
+                    }
                 });
 
             if !embedder_events.is_empty() {
@@ -347,7 +1378,7 @@ impl Minibrowser {
     /// routing those to the App event queue.
     pub fn queue_embedder_events_for_minibrowser_events(
         &self,
-        browser: &WebViewManager<dyn WindowPortsMethods>,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
         app_event_queue: &mut Vec<EmbedderEvent>,
     ) {
         for event in self.event_queue.borrow_mut().drain(..) {
@@ -355,27 +1386,126 @@ impl Minibrowser {
                 MinibrowserEvent::Go => {
                     let browser_id = browser.webview_id().unwrap();
                     let location = self.location.borrow();
-                    if let Some(url) = location_bar_input_to_url(&location.clone()) {
+                    if let Some(source_url) = location.strip_prefix(VIEW_SOURCE_PREFIX) {
+                        match location_bar_input_to_url(source_url) {
+                            Some(url) => browser.view_source(url),
+                            None => warn!("failed to parse location"),
+                        }
+                    } else if location.trim() == NEW_TAB_LOCATION {
+                        if let Some(url) = browser.new_tab_page_url() {
+                            app_event_queue.push(EmbedderEvent::LoadUrl(browser_id, url));
+                        }
+                    } else if let Some(url) = browser.location_to_url(&location) {
                         app_event_queue.push(EmbedderEvent::LoadUrl(browser_id, url));
                     } else {
                         warn!("failed to parse location");
                         break;
                     }
                 },
-                MinibrowserEvent::Back => {
+                MinibrowserEvent::Back(steps) => {
                     let browser_id = browser.webview_id().unwrap();
                     app_event_queue.push(EmbedderEvent::Navigation(
                         browser_id,
-                        TraversalDirection::Back(1),
+                        TraversalDirection::Back(steps),
                     ));
                 },
-                MinibrowserEvent::Forward => {
+                MinibrowserEvent::Forward(steps) => {
                     let browser_id = browser.webview_id().unwrap();
                     app_event_queue.push(EmbedderEvent::Navigation(
                         browser_id,
-                        TraversalDirection::Forward(1),
+                        TraversalDirection::Forward(steps),
                     ));
                 },
+                MinibrowserEvent::Reload { hard } => {
+                    browser.reload(hard);
+                },
+                MinibrowserEvent::NewTab => {
+                    let url = browser.new_tab_url();
+                    app_event_queue.push(browser.tabs().new_tab(url));
+                },
+                MinibrowserEvent::Home => browser.go_home(),
+                MinibrowserEvent::ActivateTab(webview_id) => {
+                    app_event_queue.extend(browser.tabs().activate(webview_id));
+                },
+                MinibrowserEvent::CloseTab(webview_id) => {
+                    app_event_queue.extend(browser.tabs().close_tab(webview_id));
+                },
+                MinibrowserEvent::DiscardTab(webview_id) => {
+                    browser.discard_tab(webview_id);
+                },
+                MinibrowserEvent::ToggleMute(webview_id) => {
+                    browser.toggle_mute(webview_id);
+                },
+                MinibrowserEvent::ToggleMobileSite(webview_id) => {
+                    browser.toggle_mobile_site(webview_id);
+                },
+                MinibrowserEvent::MoveTab(webview_id, index) => {
+                    browser.tabs_mut().move_tab(webview_id, index);
+                },
+                MinibrowserEvent::ResetZoom => {
+                    browser.reset_zoom();
+                },
+                MinibrowserEvent::AllowPopups => browser.allow_popups(),
+                MinibrowserEvent::PlayBlockedMedia(always) => browser.play_blocked_media(always),
+                MinibrowserEvent::ToggleReaderMode => browser.toggle_reader_mode(),
+                MinibrowserEvent::SetPowerSaving(enabled) => browser.set_power_saving(enabled),
+                MinibrowserEvent::SetHttpCache(enabled) => browser.set_http_cache_enabled(enabled),
+                MinibrowserEvent::InspectResponseHeaders(inspect) => {
+                    browser.inspect_response_headers(inspect)
+                },
+                MinibrowserEvent::CopyResponseHeaders => browser.copy_response_headers(),
+                MinibrowserEvent::CancelDownload(id) => browser.downloads_mut().cancel(id),
+                MinibrowserEvent::ClearDownloads => browser.downloads_mut().clear_finished(),
+                MinibrowserEvent::Emulate(emulation) => browser.set_emulation(emulation),
+                MinibrowserEvent::SetDevicePixelRatio(ratio) => {
+                    browser.set_device_pixel_ratio_override(ratio)
+                },
+                MinibrowserEvent::OpenBookmark(url) => match ServoUrl::parse(&url) {
+                    Ok(url) => {
+                        let browser_id = browser.webview_id().unwrap();
+                        app_event_queue.push(EmbedderEvent::LoadUrl(browser_id, url));
+                    },
+                    Err(error) => warn!("Not opening bookmark {url:?}: {error}"),
+                },
+                MinibrowserEvent::MoveBookmark(id) => browser.move_bookmark(id),
+                MinibrowserEvent::RemoveBookmark(id) => browser.remove_bookmark(id),
+                MinibrowserEvent::ImportBookmarks => browser.import_bookmarks(),
+                MinibrowserEvent::ExportBookmarks => browser.export_bookmarks(),
+                MinibrowserEvent::DropFiles(webview_id, paths) => {
+                    browser.open_dropped_files(webview_id, paths)
+                },
+                MinibrowserEvent::ContextMenu(action) => {
+                    browser.close_context_menu(match action {
+                        ContextMenuAction::Item(index) => ContextMenuResult::Selected(index),
+                        _ => ContextMenuResult::Dismissed,
+                    });
+                    match action {
+                        ContextMenuAction::Back | ContextMenuAction::Forward => {
+                            let direction = match action {
+                                ContextMenuAction::Back => TraversalDirection::Back(1),
+                                _ => TraversalDirection::Forward(1),
+                            };
+                            let browser_id = browser.webview_id().unwrap();
+                            app_event_queue.push(EmbedderEvent::Navigation(browser_id, direction));
+                        },
+                        ContextMenuAction::Reload => browser.reload(false),
+                        ContextMenuAction::CopyAddress => browser.copy_current_url(),
+                        ContextMenuAction::CopyAsCurl => browser.copy_as_curl(),
+                        ContextMenuAction::Bookmark => browser.bookmark_current_page(),
+                        ContextMenuAction::SavePage => browser.save_current_page(),
+                        ContextMenuAction::ViewSource => {
+                            if let Some(url) = browser.current_url().cloned() {
+                                browser.view_source(url);
+                            }
+                        },
+                        ContextMenuAction::Item(_) | ContextMenuAction::Dismiss => {},
+                    }
+                },
+                MinibrowserEvent::RevokePermission { origin, feature } => {
+                    browser.revoke_permission(&origin, &feature)
+                },
+                MinibrowserEvent::ResetSiteZoom(url) => browser.reset_site_zoom(&url),
+                MinibrowserEvent::ResetMobileSite(url) => browser.reset_mobile_site(&url),
             }
         }
     }
@@ -420,6 +1550,159 @@ impl Minibrowser {
         need_update
     }
 
+    /// Updates the frame timing overlay from the given [WebViewManager], returning true iff it
+    /// has changed (needing an egui update).
+    pub fn update_frame_stats(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let stats = browser.frame_stats();
+        if !stats.overlay_visible() {
+            return self.frame_stats.take().is_some();
+        }
+        let recently_updated = self
+            .frame_stats
+            .as_ref()
+            .is_some_and(|(_, updated)| updated.elapsed() < FRAME_STATS_UPDATE_INTERVAL);
+        if recently_updated {
+            return false;
+        }
+        self.frame_stats = Some((stats.summary(), Instant::now()));
+        true
+    }
+
+    /// Updates the zoom level from the given [WebViewManager], returning true iff it has changed
+    /// (needing an egui update).
+    pub fn update_zoom_in_toolbar(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let need_update = browser.page_zoom() != self.page_zoom;
+        self.page_zoom = browser.page_zoom();
+        need_update
+    }
+
+    /// Updates whether the active tab is in reader mode from the given [WebViewManager],
+    /// returning true iff it has changed (needing an egui update).
+    pub fn update_reader_mode(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let reader_mode = browser.reader_mode();
+        let need_update = reader_mode != self.reader_mode;
+        self.reader_mode = reader_mode;
+        need_update
+    }
+
+    /// Updates the count of blocked popups from the given [WebViewManager], returning true iff
+    /// it has changed (needing an egui update).
+    pub fn update_blocked_popups(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let blocked_popups = browser.blocked_popups();
+        let need_update = blocked_popups != self.blocked_popups;
+        self.blocked_popups = blocked_popups;
+        need_update
+    }
+
+    /// Updates whether autoplay was blocked in the active tab from the given [WebViewManager],
+    /// returning true iff it has changed (needing an egui update).
+    pub fn update_autoplay_blocked(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let autoplay_blocked = browser.autoplay_blocked();
+        let need_update = autoplay_blocked != self.autoplay_blocked;
+        self.autoplay_blocked = autoplay_blocked;
+        need_update
+    }
+
+    /// Updates the tab strip from the given [WebViewManager], returning true iff it has changed
+    /// (needing an egui update).
+    pub fn update_tab_strip(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let tabs = browser.tabs();
+        let tab_strip = (
+            tabs.iter()
+                .map(|webview| {
+                    (
+                        webview.id,
+                        tab_title(webview),
+                        webview.is_discarded(),
+                        webview.media_playing,
+                        webview.muted,
+                    )
+                })
+                .collect(),
+            tabs.active_id(),
+        );
+        let need_update = tab_strip != self.tab_strip;
+        self.tab_strip = tab_strip;
+        need_update
+    }
+
+    /// Updates the back and forward buttons from the given [WebViewManager], returning true iff
+    /// they have changed (needing an egui update).
+    pub fn update_session_history(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let session_history = browser
+            .tabs()
+            .active()
+            .map(|webview| {
+                (
+                    webview.session_history.clone(),
+                    webview.session_history_index,
+                )
+            })
+            .unwrap_or_default();
+        let need_update = session_history != self.session_history;
+        self.session_history = session_history;
+        need_update
+    }
+
+    /// Checks whether any favicons have been fetched since the last update, returning true iff
+    /// so (needing an egui update).
+    pub fn update_favicons(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let generation = browser.favicons_generation();
+        let need_update = generation != self.favicons_generation;
+        self.favicons_generation = generation;
+        if need_update {
+            // Forget the textures of favicons that have been replaced.
+            self.favicon_textures
+                .retain(|(image, _)| Arc::strong_count(image) > 1);
+        }
+        need_update
+    }
+
+    /// Updates the bookmarks bar from the given [WebViewManager], returning true iff it has
+    /// changed (needing an egui update).
+    pub fn update_bookmarks_bar(
+        &mut self,
+        browser: &mut WebViewManager<dyn WindowPortsMethods>,
+    ) -> bool {
+        let bookmarks_bar = (
+            browser.bookmarks_bar_visible(),
+            browser.bookmarks().generation(),
+        );
+        let need_update = bookmarks_bar != self.bookmarks_bar;
+        self.bookmarks_bar = bookmarks_bar;
+        need_update
+    }
+
+    /// When the hint to press Esc should be hidden, needing an update, if it is shown.
+    pub fn fullscreen_hint_deadline(&self) -> Option<Instant> {
+        self.fullscreen_hint
+            .map(|shown| shown + FULLSCREEN_HINT_DURATION)
+    }
+
     /// Updates all fields taken from the given [WebViewManager], such as the location field.
     /// Returns true iff the egui needs an update.
     pub fn update_webview_data(
@@ -432,6 +1715,245 @@ impl Minibrowser {
         //       does not short-circuit.
         self.update_location_in_toolbar(browser) |
             self.update_spinner_in_toolbar(browser) |
-            self.update_status_text(browser)
+            self.update_status_text(browser) |
+            self.update_frame_stats(browser) |
+            self.update_zoom_in_toolbar(browser) |
+            self.update_blocked_popups(browser) |
+            self.update_autoplay_blocked(browser) |
+            self.update_reader_mode(browser) |
+            self.update_tab_strip(browser) |
+            self.update_session_history(browser) |
+            self.update_favicons(browser) |
+            self.update_bookmarks_bar(browser)
+    }
+}
+
+/// Paint the frame timing in the top right corner of `rect`, on a dark background.
+fn paint_frame_stats(ui: &Ui, rect: egui::Rect, text: &str) {
+    let painter = ui.painter();
+    let galley = painter.layout_no_wrap(
+        text.to_owned(),
+        egui::FontId::monospace(12.0),
+        Color32::WHITE,
+    );
+    let position = rect.right_top() + egui::vec2(-galley.size().x - 8.0, 8.0);
+    let background = egui::Rect::from_min_size(position, galley.size()).expand(4.0);
+    painter.rect_filled(background, 2.0, Color32::from_black_alpha(160));
+    painter.galley(position, galley, Color32::WHITE);
+}
+
+/// Paint the hint to press Esc at the top of `rect`, while a page is fullscreen.
+fn paint_fullscreen_hint(ui: &Ui, rect: egui::Rect) {
+    let painter = ui.painter();
+    let galley = painter.layout_no_wrap(
+        "Press Esc to exit fullscreen".to_owned(),
+        egui::FontId::proportional(16.0),
+        Color32::WHITE,
+    );
+    let position = rect.center_top() + egui::vec2(-galley.size().x / 2.0, 24.0);
+    let background = egui::Rect::from_min_size(position, galley.size()).expand(8.0);
+    painter.rect_filled(background, 4.0, Color32::from_black_alpha(192));
+    painter.galley(position, galley, Color32::WHITE);
+}
+
+/// Paint an arrow that slides in from the side of `rect` as a trackpad swipe goes towards going
+/// back (`progress` 1) or forward (-1), and lights up once it is far enough.
+fn paint_swipe_progress(ui: &Ui, rect: egui::Rect, progress: f32) {
+    let painter = ui.painter();
+    let radius = 20.0;
+    let offset = progress.abs() * (radius * 2.0 + 16.0) - radius;
+    let (center, arrow) = if progress > 0.0 {
+        (pos2(rect.left() + offset, rect.center().y), "⬅")
+    } else {
+        (pos2(rect.right() - offset, rect.center().y), "➡")
+    };
+    let (background, color) = if progress.abs() >= 1.0 {
+        (ui.visuals().selection.bg_fill, Color32::WHITE)
+    } else {
+        (
+            Color32::from_black_alpha(160),
+            Color32::from_white_alpha(192),
+        )
+    };
+    painter.circle_filled(center, radius, background);
+    painter.text(
+        center,
+        egui::Align2::CENTER_CENTER,
+        arrow,
+        egui::FontId::proportional(20.0),
+        color,
+    );
+}
+
+/// Paint a progress bar along the bottom of `rect` while the page loads.
+///
+/// Servo only reports when a load starts, when the head has been parsed and when the load is
+/// complete, not how much of the page and its subresources have loaded, so the progress bar is
+/// indeterminate: a segment sweeping across, which turns from gray to white with the head.
+fn paint_load_progress(ctx: &egui::Context, rect: egui::Rect, color: Color32) {
+    let phase = (ctx.input(|i| i.time) / LOAD_PROGRESS_PERIOD).fract() as f32;
+    let segment_width = rect.width() / 4.0;
+    let left = rect.left() - segment_width + phase * (rect.width() + segment_width);
+    let segment = egui::Rect::from_min_max(
+        pos2(left.max(rect.left()), rect.bottom() - LOAD_PROGRESS_HEIGHT),
+        pos2((left + segment_width).min(rect.right()), rect.bottom()),
+    );
+    let layer = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("load progress"));
+    ctx.layer_painter(layer).rect_filled(segment, 0.0, color);
+    ctx.request_repaint();
+}
+
+/// Show the favicon of the origin of `url`, or a globe if it has none (or not yet).
+fn show_favicon(
+    ui: &mut Ui,
+    textures: &mut Vec<(Arc<RgbaImage>, TextureHandle)>,
+    webviews: &WebViewManager<dyn WindowPortsMethods>,
+    url: Option<&ServoUrl>,
+) {
+    let Some(image) = url.and_then(|url| webviews.favicon(url)) else {
+        ui.label("🌐");
+        return;
+    };
+    let texture = match textures
+        .iter()
+        .find(|(other, _)| Arc::ptr_eq(other, &image))
+    {
+        Some((_, texture)) => texture.clone(),
+        None => {
+            let size = [image.width() as usize, image.height() as usize];
+            let pixels = ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+            let texture = ui
+                .ctx()
+                .load_texture("favicon", pixels, TextureOptions::LINEAR);
+            textures.push((image, texture.clone()));
+            texture
+        },
+    };
+    ui.image((texture.id(), Vec2::splat(FAVICON_SIZE)));
+}
+
+/// Show the bookmarks in `folder`, with a menu for each of its folders. Right clicking a
+/// bookmark or folder offers to move or delete it.
+fn show_bookmarks(
+    ui: &mut Ui,
+    bookmarks: &Bookmarks,
+    folder: Option<BookmarkId>,
+    event_queue: &RefCell<Vec<MinibrowserEvent>>,
+) {
+    for bookmark in bookmarks.list(folder) {
+        let response = match bookmark.url {
+            Some(ref url) => {
+                let title = if bookmark.title.is_empty() {
+                    url
+                } else {
+                    &bookmark.title
+                };
+                let response = ui.button(title).on_hover_text(url);
+                if response.clicked() {
+                    event_queue
+                        .borrow_mut()
+                        .push(MinibrowserEvent::OpenBookmark(url.clone()));
+                    ui.close_menu();
+                }
+                response
+            },
+            None => {
+                ui.menu_button(&bookmark.title, |ui| {
+                    show_bookmarks(ui, bookmarks, Some(bookmark.id), event_queue);
+                })
+                .response
+            },
+        };
+        response.context_menu(|ui| {
+            if ui.button("Move to folder…").clicked() {
+                event_queue
+                    .borrow_mut()
+                    .push(MinibrowserEvent::MoveBookmark(bookmark.id));
+                ui.close_menu();
+            }
+            if ui.button("Delete").clicked() {
+                event_queue
+                    .borrow_mut()
+                    .push(MinibrowserEvent::RemoveBookmark(bookmark.id));
+                ui.close_menu();
+            }
+        });
+    }
+    if folder.is_some() && bookmarks.list(folder).next().is_none() {
+        ui.label("(empty)");
+    }
+}
+
+/// The pages from the history, then the bookmarks, that match the edited location, and a search
+/// for it, unless it is a URL.
+fn suggest(webviews: &WebViewManager<dyn WindowPortsMethods>, input: &str) -> Vec<Suggestion> {
+    let mut suggestions: Vec<_> = webviews
+        .search_history(input, MAX_SUGGESTIONS)
+        .into_iter()
+        .map(|entry| Suggestion::page(&entry.url, entry.title.as_deref()))
+        .collect();
+    for bookmark in webviews.bookmarks().search(input, MAX_SUGGESTIONS) {
+        let Some(ref url) = bookmark.url else {
+            continue;
+        };
+        if suggestions.len() < MAX_SUGGESTIONS &&
+            !suggestions
+                .iter()
+                .any(|suggestion| suggestion.location == *url)
+        {
+            suggestions.push(Suggestion::page(url, Some(&bookmark.title)));
+        }
+    }
+    let input = input.trim();
+    if !input.is_empty() && fix_up_url(input).is_none() {
+        suggestions.push(Suggestion {
+            label: format!("Search for “{input}”"),
+            location: input.to_owned(),
+            url: None,
+        });
+    }
+    suggestions
+}
+
+/// Put the text cursor of the location field after its text, as after typing it.
+fn move_cursor_to_end(ui: &Ui, location_field: &egui::Response, location: &str) {
+    if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), location_field.id) {
+        let end = egui::text::CCursor::new(location.chars().count());
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(end)));
+        state.store(ui.ctx(), location_field.id);
+    }
+}
+
+/// Shorten `text` to [MAX_STATUS_TEXT_LENGTH] by leaving out its middle, keeping the scheme and
+/// host of a URL at the start, and as much of the end as fits.
+fn elide_middle(text: &str) -> String {
+    let length = text.chars().count();
+    if length <= MAX_STATUS_TEXT_LENGTH {
+        return text.to_owned();
+    }
+    let host_end = text.find("://").and_then(|scheme_end| {
+        let path_start = scheme_end + 3 + text[scheme_end + 3..].find('/')? + 1;
+        Some(text[..path_start].chars().count())
+    });
+    let head = host_end
+        .unwrap_or(MAX_STATUS_TEXT_LENGTH / 2)
+        .min(MAX_STATUS_TEXT_LENGTH * 2 / 3);
+    let tail = MAX_STATUS_TEXT_LENGTH - head - 1;
+    let mut elided: String = text.chars().take(head).collect();
+    elided.push('…');
+    elided.extend(text.chars().skip(length - tail));
+    elided
+}
+
+/// The title of a tab in the tab strip, shortened to fit.
+fn tab_title(webview: &WebView) -> String {
+    let title = webview.display_title();
+    if title.chars().count() <= MAX_TAB_TITLE_LENGTH {
+        return title;
     }
+    let mut title: String = title.chars().take(MAX_TAB_TITLE_LENGTH - 1).collect();
+    title.push('…');
+    title
 }
@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Whether to request the mobile or the desktop site of each origin, remembered across restarts.
+//!
+//! Tabs showing a mobile site lay out their page at the width of a typical phone,
+//! [`MOBILE_VIEWPORT_WIDTH`], centered in the window, so that responsive sites switch to their
+//! phone layout. By default, sites are mobile if the user agent is that of Android or iOS.
+//!
+//! TODO: Also send a mobile user agent and report the device pixel ratio of a phone to mobile
+//! sites. Servo only takes the user agent at startup, for all webviews, and the device pixel
+//! ratio is that of the window, so for now only the viewport width changes.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use log::warn;
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+
+/// The width of the viewport of mobile sites, in logical pixels, that of a typical phone.
+pub const MOBILE_VIEWPORT_WIDTH: f32 = 412.0;
+
+/// The origins whose site is not the default one, kept in `mobile_sites.json` in the config dir.
+#[derive(Default)]
+pub struct MobileSites {
+    path: Option<PathBuf>,
+    mobile_by_default: bool,
+    sites: HashMap<String, bool>,
+    dirty: bool,
+}
+
+impl MobileSites {
+    /// Read the saved choices. Private windows give every origin the default site at first.
+    pub fn load(mobile_by_default: bool, private: bool) -> Self {
+        let path = config_dir::get()
+            .filter(|_| !private)
+            .map(|dir| dir.join("mobile_sites.json"));
+        let sites = path
+            .as_deref()
+            .and_then(config_dir::read_json)
+            .unwrap_or_default();
+        Self {
+            path,
+            mobile_by_default,
+            sites,
+            dirty: false,
+        }
+    }
+
+    /// Whether to request the mobile site for pages with the same origin as `url`.
+    pub fn get(&self, url: &ServoUrl) -> bool {
+        Self::key(url)
+            .and_then(|key| self.sites.get(&key).copied())
+            .unwrap_or(self.mobile_by_default)
+    }
+
+    /// Remember whether to request the mobile site for pages with the same origin as `url`.
+    pub fn set(&mut self, url: &ServoUrl, mobile: bool) {
+        let Some(key) = Self::key(url) else {
+            return;
+        };
+        if mobile == self.mobile_by_default {
+            self.dirty |= self.sites.remove(&key).is_some();
+        } else if self.sites.insert(key, mobile) != Some(mobile) {
+            self.dirty = true;
+        }
+    }
+
+    /// Whether the site for the origin of `url` was chosen, rather than being the default one.
+    pub fn is_chosen(&self, url: &ServoUrl) -> bool {
+        Self::key(url).is_some_and(|key| self.sites.contains_key(&key))
+    }
+
+    /// Forget the site chosen for the origin of `url`, so that it gets the default one again.
+    pub fn reset(&mut self, url: &ServoUrl) {
+        if let Some(key) = Self::key(url) {
+            self.dirty |= self.sites.remove(&key).is_some();
+        }
+    }
+
+    /// Write the choices to disk, if they have changed since they were loaded.
+    pub fn save(&mut self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        if !self.dirty {
+            return;
+        }
+        let result = config_dir::write_json(path, &self.sites);
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(error) => warn!("Failed to save mobile sites to {}: {error}", path.display()),
+        }
+    }
+
+    /// Only tuple origins are remembered, like for page zoom.
+    fn key(url: &ServoUrl) -> Option<String> {
+        let origin = url.origin();
+        origin.is_tuple().then(|| origin.ascii_serialization())
+    }
+}
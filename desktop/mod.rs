@@ -5,15 +5,53 @@
 //! Contains files specific to the servoshell app for Desktop systems.
 
 pub(crate) mod app;
+mod bookmarks;
 pub(crate) mod cli;
+mod config_dir;
+mod content_scripts;
+mod control;
+pub(crate) mod crash_reporter;
+mod crashes;
+mod curl;
+mod device_emulation;
+mod downloads;
 mod egui_glue;
 mod embedder;
 pub(crate) mod events_loop;
+mod favicon;
+mod fetch;
+#[cfg(target_os = "linux")]
+mod font_dirs;
+mod fonts;
+mod frame_stats;
 pub mod geometry;
 mod headed_window;
 mod headless_window;
+mod history;
 mod keyutils;
+mod kinetic_scroll;
+mod memory;
 mod minibrowser;
+mod mobile_sites;
+mod new_tab;
+mod permissions;
+mod power_save;
+mod reader;
+mod response_headers;
+mod save_page;
+mod screenshot;
+mod search_engines;
+mod session;
+mod shortcuts;
+mod single_instance;
+#[cfg(target_os = "macos")]
+mod swipe;
+mod tabs;
 mod tracing;
+mod url_list;
+mod user_scripts;
+mod view_source;
 mod webview;
+mod window_geometry;
 mod window_trait;
+mod zoom;
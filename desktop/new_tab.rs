@@ -0,0 +1,149 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The page opened in new tabs, listing the recent history and the bookmarks bar.
+//!
+//! Servo can't be taught new URL schemes, so like the pages of [view source](super::view_source),
+//! the `new-tab:` page is HTML of our own, written to a file from the history and bookmarks
+//! every time it is opened, so nothing is fetched over the network. The file is `new-tab.html`
+//! in the config dir, so that tabs restored from the last session still find it. Private
+//! windows write theirs to a private temporary directory instead, so that they don't overwrite
+//! the page of other windows, and it never lists any history, since they don't record any.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use log::warn;
+use servo::servo_url::ServoUrl;
+
+use super::bookmarks::Bookmark;
+use super::config_dir;
+use super::history::HistoryEntry;
+use super::view_source::escape;
+
+/// What the location bar shows for the new tab page, and what can be typed there to open it.
+pub const NEW_TAB_LOCATION: &str = "new-tab:";
+
+/// The most recently visited pages listed on the new tab page.
+pub const MAX_RECENT_PAGES: usize = 12;
+
+/// Where the new tab page of this window is written.
+pub struct NewTabPage {
+    path: Option<PathBuf>,
+    /// The temporary directory of a private window, removed when dropped.
+    temp_dir: Option<PathBuf>,
+}
+
+impl NewTabPage {
+    pub fn new(private: bool) -> Self {
+        if !private {
+            let path = config_dir::get().map(|dir| dir.join("new-tab.html"));
+            return Self {
+                path,
+                temp_dir: None,
+            };
+        }
+        match tempfile::Builder::new()
+            .prefix("servoshell-new-tab-")
+            .tempdir()
+        {
+            Ok(dir) => {
+                let dir = dir.into_path();
+                Self {
+                    path: Some(dir.join("new-tab.html")),
+                    temp_dir: Some(dir),
+                }
+            },
+            Err(error) => {
+                warn!("Failed to create a directory for the new tab page: {error}");
+                Self {
+                    path: None,
+                    temp_dir: None,
+                }
+            },
+        }
+    }
+
+    /// Whether `url` is the new tab page.
+    pub fn is(&self, url: &ServoUrl) -> bool {
+        self.path
+            .as_ref()
+            .is_some_and(|path| url.to_file_path().is_ok_and(|file| file == *path))
+    }
+
+    /// Write the new tab page with these recently visited pages and bookmarks, returning its
+    /// URL.
+    pub fn write(&self, recent: &[HistoryEntry], bookmarks: &[&Bookmark]) -> Option<ServoUrl> {
+        let path = self.path.as_ref()?;
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(path, render(recent, bookmarks)));
+        if let Err(error) = result {
+            warn!("Failed to write {}: {error}", path.display());
+            return None;
+        }
+        ServoUrl::from_file_path(path).ok()
+    }
+}
+
+impl Drop for NewTabPage {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.temp_dir {
+            if let Err(error) = fs::remove_dir_all(dir) {
+                warn!("Failed to remove {}: {error}", dir.display());
+            }
+        }
+    }
+}
+
+fn render(recent: &[HistoryEntry], bookmarks: &[&Bookmark]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n\
+         <meta charset=utf-8>\n\
+         <title>New tab</title>\n\
+         <style>\n\
+         body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; }\n\
+         h2 { font-size: 1em; color: #666; }\n\
+         ul { padding: 0; list-style: none; }\n\
+         li { margin: 0.25em 0; overflow: hidden; white-space: nowrap; text-overflow: ellipsis; }\n\
+         a { text-decoration: none; }\n\
+         .url { margin-left: 1ch; color: #999; font-size: 0.8em; }\n\
+         </style>\n",
+    );
+    let recent = recent
+        .iter()
+        .map(|entry| (entry.url.as_str(), entry.title.as_deref()));
+    push_section(&mut html, "Recently visited", recent);
+    let bookmarks = bookmarks.iter().filter_map(|bookmark| {
+        let url = bookmark.url.as_deref()?;
+        Some((url, Some(bookmark.title.as_str())))
+    });
+    push_section(&mut html, "Bookmarks", bookmarks);
+    html
+}
+
+/// A list of links to pages, as their URL and title, unless there are none.
+fn push_section<'a>(
+    html: &mut String,
+    heading: &str,
+    pages: impl Iterator<Item = (&'a str, Option<&'a str>)>,
+) {
+    let mut pages = pages.peekable();
+    if pages.peek().is_none() {
+        return;
+    }
+    let _ = writeln!(html, "<h2>{heading}</h2>\n<ul>");
+    for (url, title) in pages {
+        let title = title.filter(|title| !title.is_empty()).unwrap_or(url);
+        let _ = writeln!(
+            html,
+            "<li><a href=\"{url}\">{title}</a><span class=url>{url}</span></li>",
+            url = escape(url),
+            title = escape(title),
+        );
+    }
+    html.push_str("</ul>\n");
+}
@@ -0,0 +1,136 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The answers given to permission prompts, remembered per origin across restarts, and the
+//! origins allowed to open popups or to autoplay media.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use log::warn;
+use servo::embedder_traits::{PermissionName, PermissionRequest};
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+
+/// Whether each origin was granted each feature it asked for, kept in `permissions.json` in the
+/// config dir.
+///
+/// Servo doesn't say which frame a request came from, so decisions are made for the origin of
+/// the page in the tab, and apply to its iframes too.
+#[derive(Default)]
+pub struct Permissions {
+    path: Option<PathBuf>,
+    /// Deny everything without asking, for `--deny-all-permissions`.
+    deny_all: bool,
+    /// For each origin, the features it was granted (true) or denied (false).
+    decisions: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+impl Permissions {
+    /// Read the saved decisions. Private windows ask for every feature again.
+    pub fn load(deny_all: bool, private: bool) -> Self {
+        let path = config_dir::get()
+            .filter(|_| !private)
+            .map(|dir| dir.join("permissions.json"));
+        let decisions = path
+            .as_deref()
+            .and_then(config_dir::read_json)
+            .unwrap_or_default();
+        Self {
+            path,
+            deny_all,
+            decisions,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+
+    /// The decision for `feature` in pages with the same origin as `url`, or None if the user
+    /// should be asked.
+    pub fn get(&self, url: &ServoUrl, feature: &str) -> Option<PermissionRequest> {
+        if self.deny_all {
+            return Some(PermissionRequest::Denied);
+        }
+        let granted = *self.decisions.get(&Self::key(url)?)?.get(feature)?;
+        Some(if granted {
+            PermissionRequest::Granted
+        } else {
+            PermissionRequest::Denied
+        })
+    }
+
+    /// Remember whether `feature` was granted to pages with the same origin as `url`.
+    pub fn set(&mut self, url: &ServoUrl, feature: &str, granted: bool) {
+        let Some(key) = Self::key(url) else {
+            return;
+        };
+        self.decisions
+            .entry(key)
+            .or_default()
+            .insert(feature.to_owned(), granted);
+    }
+
+    /// Forget the decision for `feature` in `origin`, so that it is asked again.
+    pub fn revoke(&mut self, origin: &str, feature: &str) {
+        let Some(features) = self.decisions.get_mut(origin) else {
+            return;
+        };
+        features.remove(feature);
+        if features.is_empty() {
+            self.decisions.remove(origin);
+        }
+    }
+
+    /// Every decision, as the serialized origin, the feature, and whether it was granted.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, bool)> {
+        self.decisions.iter().flat_map(|(origin, features)| {
+            features
+                .iter()
+                .map(move |(feature, granted)| (&**origin, &**feature, *granted))
+        })
+    }
+
+    /// The decisions for pages with the same origin as `url`, as the feature and whether it was
+    /// granted.
+    pub fn for_origin(&self, url: &ServoUrl) -> impl Iterator<Item = (&str, bool)> {
+        Self::key(url)
+            .and_then(|key| self.decisions.get(&key))
+            .into_iter()
+            .flatten()
+            .map(|(feature, granted)| (&**feature, *granted))
+    }
+
+    /// Write the decisions to disk.
+    pub fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let result = config_dir::write_json(path, &self.decisions);
+        if let Err(error) = result {
+            warn!("Failed to save permissions to {}: {error}", path.display());
+        }
+    }
+
+    /// Only tuple origins are remembered, since opaque origins have no serialization to tell
+    /// them apart.
+    fn key(url: &ServoUrl) -> Option<String> {
+        let origin = url.origin();
+        origin.is_tuple().then(|| origin.ascii_serialization())
+    }
+}
+
+/// The name under which origins are allowed to open popups without a click.
+pub const POPUPS_FEATURE: &str = "Popups";
+
+/// The name under which origins are allowed to play media without a click, with `--autoplay
+/// block`.
+pub const AUTOPLAY_FEATURE: &str = "Autoplay";
+
+/// The name a permission is remembered and listed by, like `Geolocation`.
+pub fn feature_name(name: &PermissionName) -> String {
+    format!("{name:?}")
+}
@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Saving power, with `--power-save` or the toggle in the toolbar, and on battery power where
+//! that can be told.
+//!
+//! While saving power, Servo is told to throttle background tabs, which runs their animation
+//! frames and timers rarely, and media playing in a tab is paused once it goes to the
+//! background. While the window is unfocused, it is also composited at most once per
+//! [`UNFOCUSED_FRAME_INTERVAL`]. A tab is unthrottled as soon as it is activated again, so the
+//! active tab always runs as usual.
+//!
+//! TODO: Tell whether the system is on battery power on other platforms than Linux, where it is
+//! read from sysfs. Pause the media of every background tab: media session actions only reach
+//! the most recently active media session, so media is only paused in a background tab if no
+//! other tab is playing any.
+
+use std::time::{Duration, Instant};
+
+/// How often to composite while the window is unfocused and saving power.
+pub const UNFOCUSED_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often to check whether the system has switched to or from battery power.
+const BATTERY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct PowerSave {
+    /// Whether power saving was turned on or off, with `--power-save` or the toolbar, rather
+    /// than following the battery.
+    chosen: Option<bool>,
+    on_battery: bool,
+    last_checked: Instant,
+}
+
+impl PowerSave {
+    pub fn new(power_save: bool) -> Self {
+        Self {
+            chosen: power_save.then_some(true),
+            on_battery: on_battery(),
+            last_checked: Instant::now(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.chosen.unwrap_or(self.on_battery)
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.chosen = Some(enabled);
+    }
+
+    /// Check again whether the system is on battery power, if it is time to. Returns true iff
+    /// that has turned power saving on or off.
+    pub fn check_battery(&mut self) -> bool {
+        if self.last_checked.elapsed() < BATTERY_CHECK_INTERVAL {
+            return false;
+        }
+        self.last_checked = Instant::now();
+        let was_enabled = self.enabled();
+        self.on_battery = on_battery();
+        self.enabled() != was_enabled
+    }
+}
+
+/// Whether a battery of the system is discharging, rather than one of a device like a mouse.
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read =
+            |name: &str| std::fs::read_to_string(entry.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Battery" &&
+            read("scope").trim() != "Device" &&
+            read("status").trim() == "Discharging"
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_battery() -> bool {
+    false
+}
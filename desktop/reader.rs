@@ -0,0 +1,124 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Reader mode, which shows the article of a page without the rest of it, in a tab of its own.
+//!
+//! Servo doesn't let the embedder look at the DOM of a page, so like [view
+//! source](super::view_source), the page is fetched again on a background thread (without its
+//! cookies). The reader page, `resources/reader.html`, is written to a temporary file with the
+//! fetched HTML in it, and a script there finds the article, with a small take on the
+//! Readability algorithm of Firefox, and shows it with controls for the text size and width.
+//! Leaving reader mode closes its tab and goes back to the original one, which was never
+//! unloaded.
+//!
+//! TODO: Only offer reader mode for pages that look like articles, and read the page as it was
+//! loaded instead of fetching it again, which both need Servo to let the embedder look at the
+//! DOM. For now, it is offered for every page, which is then fetched without its cookies.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::{fs, thread};
+
+use log::warn;
+use servo::base::id::TopLevelBrowsingContextId as WebViewId;
+use servo::embedder_traits::EventLoopWaker;
+use servo::servo_url::ServoUrl;
+
+use super::view_source::fetch_source;
+
+const READER_PAGE: &str = include_str!("../resources/reader.html");
+
+pub struct Reader {
+    /// A private temporary directory for the pages, created by the first one.
+    dir: Option<PathBuf>,
+    /// How many reader pages have been written, to name the next one.
+    count: usize,
+    sender: Sender<Result<(ServoUrl, WebViewId), String>>,
+    receiver: Receiver<Result<(ServoUrl, WebViewId), String>>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+}
+
+impl Reader {
+    pub fn new(event_loop_waker: Box<dyn EventLoopWaker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            dir: None,
+            count: 0,
+            sender,
+            receiver,
+            event_loop_waker,
+        }
+    }
+
+    /// Start fetching `url`, shown in the tab `webview_id`. Once it is ready,
+    /// [`Reader::take_loaded`] returns the URL of its reader page, along with `webview_id`.
+    pub fn open(&mut self, url: ServoUrl, webview_id: WebViewId) {
+        let dir: &PathBuf = match self.dir {
+            Some(ref dir) => dir,
+            None => match tempfile::Builder::new()
+                .prefix("servoshell-reader-")
+                .tempdir()
+            {
+                Ok(dir) => self.dir.insert(dir.into_path()),
+                Err(error) => {
+                    warn!("Failed to create a directory for reader pages: {error}");
+                    return;
+                },
+            },
+        };
+        let path = dir.join(format!("{:04}.html", self.count));
+        self.count += 1;
+        let sender = self.sender.clone();
+        let event_loop_waker = self.event_loop_waker.clone();
+        let result = thread::Builder::new()
+            .name("Reader".to_owned())
+            .spawn(move || {
+                let result = fetch_source(&url).and_then(|source| {
+                    let html = reader_page(&url, &String::from_utf8_lossy(&source))?;
+                    fs::write(&path, html).map_err(|error| error.to_string())?;
+                    ServoUrl::from_file_path(&path).map_err(|()| "Invalid path".to_owned())
+                });
+                let result = result
+                    .map(|reader_url| (reader_url, webview_id))
+                    .map_err(|error| format!("{url}: {error}"));
+                let _ = sender.send(result);
+                event_loop_waker.wake();
+            });
+        if let Err(error) = result {
+            warn!("Failed to spawn reader thread: {error}");
+        }
+    }
+
+    /// The URLs of the reader pages that are ready to be opened, and the tabs they are for.
+    pub fn take_loaded(&mut self) -> Vec<(ServoUrl, WebViewId)> {
+        self.receiver
+            .try_iter()
+            .filter_map(|result| {
+                result
+                    .map_err(|error| warn!("Failed to open reader mode for {error}"))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.dir {
+            if let Err(error) = fs::remove_dir_all(dir) {
+                warn!("Failed to remove {}: {error}", dir.display());
+            }
+        }
+    }
+}
+
+/// The reader page for the page at `url`, whose HTML is `source`.
+fn reader_page(url: &ServoUrl, source: &str) -> Result<String, String> {
+    let page = serde_json::json!({ "url": url.as_str(), "html": source });
+    // The JSON goes in a script element, which must not be closed by the page it holds.
+    let page = serde_json::to_string(&page)
+        .map_err(|error| error.to_string())?
+        .replace('<', "\\u003c");
+    Ok(READER_PAGE.replace("${page}", &page))
+}
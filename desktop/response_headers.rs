@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The response headers of the page in the active tab, for the response headers window.
+//!
+//! Servo doesn't tell the embedder about the requests it makes, so like [view
+//! source](super::view_source), the page is fetched again on a background thread (without its
+//! cookies), following its redirects one at a time to list each of them. The server may answer
+//! that fetch differently from the one that Servo made.
+//!
+//! TODO: Show the headers that Servo received, and the TLS version and cipher it negotiated,
+//! which need Servo to tell the embedder about its requests. ureq doesn't report the TLS
+//! connection it made either.
+
+use std::fmt::Write as _;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use servo::embedder_traits::EventLoopWaker;
+use servo::servo_url::ServoUrl;
+
+use super::fetch;
+
+/// Redirects after this many are not followed, like the limit of browsers.
+const MAX_REDIRECTS: usize = 20;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+type FetchResult = (ServoUrl, Result<Vec<Response>, String>);
+
+/// A response to fetching a page, either a redirect or the final one.
+pub struct Response {
+    pub url: ServoUrl,
+    pub status: u16,
+    /// Like `HTTP/1.1 200 OK`.
+    pub status_line: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Response {
+    fn from_ureq(url: ServoUrl, response: &ureq::Response) -> Self {
+        let mut headers = vec![];
+        for name in response.headers_names() {
+            for value in response.all(&name) {
+                headers.push((name.clone(), value.to_owned()));
+            }
+        }
+        Self {
+            url,
+            status: response.status(),
+            status_line: format!(
+                "{} {} {}",
+                response.http_version(),
+                response.status(),
+                response.status_text()
+            ),
+            headers,
+        }
+    }
+
+    /// Where this response redirects to, if it is a redirect.
+    pub fn location(&self) -> Option<&str> {
+        if !(300..400).contains(&self.status) {
+            return None;
+        }
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+            .map(|(_, value)| &**value)
+    }
+
+    /// The status line and headers, one per line as they are sent.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.status_line);
+        for (name, value) in &self.headers {
+            let _ = writeln!(text, "{name}: {value}");
+        }
+        text
+    }
+}
+
+pub struct ResponseHeaders {
+    /// The page whose headers are shown, and its responses once they are fetched, the last of
+    /// which is the one that wasn't a redirect.
+    current: Option<(ServoUrl, Option<Result<Vec<Response>, String>>)>,
+    sender: Sender<FetchResult>,
+    receiver: Receiver<FetchResult>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+}
+
+impl ResponseHeaders {
+    pub fn new(event_loop_waker: Box<dyn EventLoopWaker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            current: None,
+            sender,
+            receiver,
+            event_loop_waker,
+        }
+    }
+
+    /// The page whose headers are shown, if any.
+    pub fn url(&self) -> Option<&ServoUrl> {
+        self.current.as_ref().map(|(url, _)| url)
+    }
+
+    /// The responses of the page whose headers are shown, unless they are still being fetched.
+    pub fn responses(&self) -> Option<&Result<Vec<Response>, String>> {
+        self.current.as_ref()?.1.as_ref()
+    }
+
+    /// Start fetching the headers of `url`, unless they are the ones shown already.
+    pub fn inspect(&mut self, url: &ServoUrl) {
+        if self.url() == Some(url) {
+            return;
+        }
+        if !matches!(url.scheme(), "http" | "https") {
+            let error = "Only pages loaded over HTTP have response headers".to_owned();
+            self.current = Some((url.clone(), Some(Err(error))));
+            return;
+        }
+        self.current = Some((url.clone(), None));
+
+        let url = url.clone();
+        let sender = self.sender.clone();
+        let event_loop_waker = self.event_loop_waker.clone();
+        let result = thread::Builder::new()
+            .name("ResponseHeaders".to_owned())
+            .spawn(move || {
+                let result = fetch_responses(&url);
+                let _ = sender.send((url, result));
+                event_loop_waker.wake();
+            });
+        if let Err(error) = result {
+            warn!("Failed to spawn response headers thread: {error}");
+        }
+    }
+
+    /// Stop showing any headers, so that they are fetched again on the next call to
+    /// [`ResponseHeaders::inspect`].
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    /// Take the results of the fetches that have finished, returning true iff the headers shown
+    /// have changed. Results for pages that are no longer shown are dropped.
+    pub fn take_fetched(&mut self) -> bool {
+        let mut changed = false;
+        for (url, result) in self.receiver.try_iter() {
+            match self.current {
+                Some((ref current, ref mut responses)) if *current == url => {
+                    *responses = Some(result);
+                    changed = true;
+                },
+                _ => {},
+            }
+        }
+        changed
+    }
+}
+
+/// Fetch `url`, and whatever it redirects to, returning every response on the way.
+fn fetch_responses(url: &ServoUrl) -> Result<Vec<Response>, String> {
+    let agent = fetch::agent_builder()?
+        .redirects(0)
+        .timeout(FETCH_TIMEOUT)
+        .build();
+    let mut responses = vec![];
+    let mut url = url.clone();
+    loop {
+        let response = match agent.get(url.as_str()).call() {
+            // Show the headers of error pages too.
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(format!("{url}: {error}")),
+        };
+        let response = Response::from_ureq(url.clone(), &response);
+        let next = response.location().map(|location| url.join(location));
+        responses.push(response);
+        match next {
+            None => return Ok(responses),
+            Some(Err(error)) => return Err(format!("Invalid redirect from {url}: {error}")),
+            Some(Ok(_)) if responses.len() > MAX_REDIRECTS => {
+                return Err(format!("More than {MAX_REDIRECTS} redirects"))
+            },
+            Some(Ok(next)) => url = next,
+        }
+    }
+}
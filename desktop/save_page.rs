@@ -0,0 +1,183 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Saving the page, for Ctrl+S.
+//!
+//! Servo doesn't give the embedder the DOM of a document, nor the bytes it received, so like
+//! [view source](super::view_source), the page is fetched again outside of Servo (see
+//! [`super::fetch`]), and saved as it came from the server. This is not the page as it is shown:
+//! what scripts changed is lost, and since the fetch has none of the cookies of the page, a page
+//! that needs a login is saved as the server shows it to a logged out user, likely as its login
+//! page. The save dialog says so.
+//!
+//! Saving the complete page also saves the images, scripts, stylesheets and icons it refers to
+//! in a `_files` directory next to it, and points the page at those copies. Resources in
+//! `data:` URLs and inline styles are part of the page already, so they are left alone, as are
+//! the resources that stylesheets refer to.
+//!
+//! TODO: Save the document as it is, serialized from its DOM, once Servo lets the embedder do
+//! that.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use log::{info, warn};
+use servo::servo_url::ServoUrl;
+
+use super::view_source::fetch_source;
+
+/// The tags whose `src` or `href` are saved with the complete page.
+const RESOURCE_TAGS: &[&str] = &["img", "script", "link", "source", "audio", "video", "input"];
+
+/// Save the page at `url` to `path` on a background thread, with its resources if `complete`.
+pub fn save_page(url: ServoUrl, path: PathBuf, complete: bool) {
+    let result = thread::Builder::new()
+        .name("SavePage".to_owned())
+        .spawn(move || match save(&url, &path, complete) {
+            Ok(()) => info!("Saved {url} to {}", path.display()),
+            Err(error) => warn!("Failed to save {url} to {}: {error}", path.display()),
+        });
+    if let Err(error) = result {
+        warn!("Failed to spawn save page thread: {error}");
+    }
+}
+
+fn save(url: &ServoUrl, path: &Path, complete: bool) -> Result<(), String> {
+    let source = fetch_source(url)?;
+    if !complete {
+        return fs::write(path, source).map_err(|error| error.to_string());
+    }
+    let html = String::from_utf8_lossy(&source);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "page".to_owned());
+    let files_name = format!("{stem}_files");
+    let files_dir = path.with_file_name(&files_name);
+    let html = save_resources(url, &html, &files_dir, &files_name)?;
+    fs::write(path, html).map_err(|error| error.to_string())
+}
+
+/// Save the resources that `html` refers to in `files_dir`, returning `html` with those
+/// references pointing at the copies, as `files_name/…`.
+fn save_resources(
+    page_url: &ServoUrl,
+    html: &str,
+    files_dir: &Path,
+    files_name: &str,
+) -> Result<String, String> {
+    let mut saved: HashMap<ServoUrl, Option<String>> = HashMap::new();
+    let mut output = String::with_capacity(html.len());
+    let mut copied_up_to = 0;
+    for tag in tags(html) {
+        let Some(value) = resource_attribute(&html[tag.clone()]) else {
+            continue;
+        };
+        let value = tag.start + value.start..tag.start + value.end;
+        let Ok(url) = page_url.join(&html[value.clone()].replace("&amp;", "&")) else {
+            continue;
+        };
+        if url.scheme() == "data" {
+            continue;
+        }
+        // Number the files, to keep their names unique.
+        let index = saved.len();
+        let local = saved.entry(url.clone()).or_insert_with(|| {
+            let file_name = format!("{index}-{}", file_name(&url));
+            let result = fetch_source(&url).and_then(|bytes| {
+                fs::create_dir_all(files_dir).map_err(|error| error.to_string())?;
+                fs::write(files_dir.join(&file_name), bytes).map_err(|error| error.to_string())
+            });
+            match result {
+                Ok(()) => Some(format!("{files_name}/{file_name}").replace(' ', "%20")),
+                Err(error) => {
+                    warn!("Not saving {url} with the page: {error}");
+                    None
+                },
+            }
+        });
+        if let Some(local) = local {
+            output.push_str(&html[copied_up_to..value.start]);
+            output.push_str(local);
+            copied_up_to = value.end;
+        }
+    }
+    output.push_str(&html[copied_up_to..]);
+    Ok(output)
+}
+
+/// The last segment of the path of `url`, with only characters that are safe in file names.
+fn file_name(url: &ServoUrl) -> String {
+    let name: String = url
+        .path()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    if name.is_empty() {
+        "resource".to_owned()
+    } else {
+        name
+    }
+}
+
+/// The ranges of the contents of the tags in `html`, between their `<` and `>`, skipping
+/// comments.
+fn tags(html: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut position = 0;
+    std::iter::from_fn(move || loop {
+        let start = position + html[position..].find('<')? + 1;
+        if html[start..].starts_with("!--") {
+            position = start + html[start..].find("-->")? + 3;
+            continue;
+        }
+        let end = start + html[start..].find('>')?;
+        position = end + 1;
+        return Some(start..end);
+    })
+}
+
+/// The range of the value of the `src` or `href` of a tag that refers to a resource of the
+/// page, if it is quoted. Only stylesheets and icons are saved of `<link>`s.
+fn resource_attribute(tag: &str) -> Option<Range<usize>> {
+    let lowercase = tag.to_ascii_lowercase();
+    let name = lowercase.split(|c: char| c.is_whitespace()).next()?;
+    if !RESOURCE_TAGS.contains(&name) {
+        return None;
+    }
+    if name == "link" && !lowercase.contains("stylesheet") && !lowercase.contains("icon") {
+        return None;
+    }
+    ["src", "href"]
+        .iter()
+        .find_map(|attribute| quoted_attribute(&lowercase, attribute))
+}
+
+/// The range of the value of the attribute `name` in the lowercase contents of a tag, if it is
+/// quoted.
+fn quoted_attribute(tag: &str, name: &str) -> Option<Range<usize>> {
+    let mut search_from = 0;
+    while let Some(index) = tag[search_from..].find(name) {
+        let start = search_from + index;
+        search_from = start + name.len();
+        let after_name = tag[search_from..].trim_start();
+        if !tag[..start].ends_with(char::is_whitespace) || !after_name.starts_with('=') {
+            continue;
+        }
+        let value = after_name[1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value_start = tag.len() - value.len() + 1;
+        let value_end = value_start + tag[value_start..].find(quote)?;
+        return Some(value_start..value_end);
+    }
+    None
+}
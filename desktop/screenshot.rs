@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Reading back the rendered frame and saving it as a PNG file.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gleam::gl;
+use image::RgbaImage;
+use servo::webrender_api::units::DeviceIntRect;
+use servo::webrender_traits::RenderingContext;
+use surfman::GLApi;
+
+/// Read back `rect` of the framebuffer `fbo` and save it to `path` as a PNG.
+///
+/// `rect` is in GL coordinates, with its origin at the bottom left. If `fbo` is None, the
+/// framebuffer of the rendering context's surface is read instead. This must be called
+/// after compositing but before presenting, while the frame is still in the back buffer.
+pub fn save_png(
+    rendering_context: &RenderingContext,
+    fbo: Option<gl::GLuint>,
+    rect: DeviceIntRect,
+    path: &Path,
+) -> Result<(), String> {
+    let gl = match rendering_context.connection().gl_api() {
+        GLApi::GL => unsafe { gl::GlFns::load_with(|s| rendering_context.get_proc_address(s)) },
+        GLApi::GLES => unsafe { gl::GlesFns::load_with(|s| rendering_context.get_proc_address(s)) },
+    };
+    rendering_context
+        .make_gl_context_current()
+        .map_err(|error| format!("Failed to make GL context current: {error:?}"))?;
+
+    let fbo = match fbo {
+        Some(fbo) => fbo,
+        None => rendering_context
+            .context_surface_info()
+            .map_err(|error| format!("Failed to get surface info: {error:?}"))?
+            .map(|info| info.framebuffer_object)
+            .ok_or("No surface bound to the GL context")?,
+    };
+
+    let width = rect.width();
+    let height = rect.height();
+    if width <= 0 || height <= 0 {
+        return Err(format!("Nothing to read back in {rect:?}"));
+    }
+
+    // The surface may well be BGRA internally, but RGBA with unsigned bytes is the one
+    // format that every GL and GLES implementation must support for glReadPixels, and
+    // the driver converts for us, so the channels are never swapped.
+    gl.bind_framebuffer(gl::READ_FRAMEBUFFER, fbo);
+    gl.pixel_store_i(gl::PACK_ALIGNMENT, 1);
+    let mut pixels = gl.read_pixels(
+        rect.min.x,
+        rect.min.y,
+        width,
+        height,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+    );
+    let error = gl.get_error();
+    if error != gl::NO_ERROR {
+        return Err(format!("glReadPixels failed with error {error:#x}"));
+    }
+
+    // GL rows start at the bottom of the image, PNG rows start at the top.
+    let stride = width as usize * 4;
+    for row in 0..height as usize / 2 {
+        let (top, bottom) = pixels.split_at_mut((height as usize - row - 1) * stride);
+        top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+    }
+
+    // WebRender renders with premultiplied alpha, but PNG expects straight alpha.
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha != 0 && alpha != 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+
+    let image = RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or("Read back fewer pixels than expected")?;
+    image
+        .save(path)
+        .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+}
+
+/// A path in the current directory for a screenshot taken now, e.g. `screenshot-1723795200.png`.
+pub fn default_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    PathBuf::from(format!("screenshot-{timestamp}.png"))
+}
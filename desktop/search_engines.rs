@@ -0,0 +1,74 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Search engines, for `--search-engine` and for keyword searches like `wiki cats`.
+//!
+//! The default search engine is the `shell.searchpage` pref. Servo's prefs can't hold new keys,
+//! so the keywords are kept in `search_engines.json` in the config dir, as a map from keyword to
+//! URL template, like `{ "mdn": "https://developer.mozilla.org/search?q=%s" }`. They are added
+//! to the keywords of the built-in engines, replacing any that are the same.
+
+use std::collections::HashMap;
+
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+use crate::parser::search_url;
+
+/// The built-in search engines, as their name, their keyword, and the template of their search
+/// URLs, where `%s` is the query.
+pub const SEARCH_ENGINES: &[(&str, &str, &str)] = &[
+    ("duckduckgo", "ddg", "https://duckduckgo.com/html/?q=%s"),
+    (
+        "startpage",
+        "sp",
+        "https://www.startpage.com/do/search?q=%s",
+    ),
+    ("google", "g", "https://www.google.com/search?q=%s"),
+    ("bing", "b", "https://www.bing.com/search?q=%s"),
+    (
+        "wikipedia",
+        "wiki",
+        "https://en.wikipedia.org/w/index.php?search=%s",
+    ),
+];
+
+/// The URL template of a search engine given by the name of a built-in engine, or as a
+/// template itself.
+pub fn search_engine_template(name_or_template: &str) -> Option<String> {
+    SEARCH_ENGINES
+        .iter()
+        .find(|(name, ..)| name.eq_ignore_ascii_case(name_or_template))
+        .map(|(.., template)| template.to_string())
+        .or_else(|| {
+            let is_template = name_or_template.contains("%s") &&
+                ServoUrl::parse(&name_or_template.replace("%s", "")).is_ok();
+            is_template.then(|| name_or_template.to_owned())
+        })
+}
+
+pub struct KeywordSearches {
+    templates: HashMap<String, String>,
+}
+
+impl KeywordSearches {
+    /// Read the keywords of the built-in engines, and those in the config dir.
+    pub fn load() -> Self {
+        let mut templates: HashMap<String, String> = SEARCH_ENGINES
+            .iter()
+            .map(|(_, keyword, template)| (keyword.to_string(), template.to_string()))
+            .collect();
+        let user_templates: Option<HashMap<String, String>> = config_dir::get()
+            .and_then(|dir| config_dir::read_json(&dir.join("search_engines.json")));
+        templates.extend(user_templates.unwrap_or_default());
+        Self { templates }
+    }
+
+    /// The search URL for input like `wiki cats`, if its first word is a keyword.
+    pub fn search(&self, input: &str) -> Option<ServoUrl> {
+        let (keyword, query) = input.trim().split_once(char::is_whitespace)?;
+        let template = self.templates.get(keyword)?;
+        search_url(template, query.trim())
+    }
+}
@@ -0,0 +1,210 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Saving the open tabs on shutdown, and restoring them on the next launch.
+
+use std::path::PathBuf;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+use super::tabs::TabManager;
+
+/// Bump this whenever the format changes. Session files with another version are ignored.
+const SESSION_VERSION: u32 = 1;
+
+/// The tabs that were open when the app last shut down cleanly.
+///
+/// Scroll positions are not saved, because Servo does not report them to the embedder.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Session {
+    version: u32,
+    /// The URL of each tab, in tab strip order.
+    urls: Vec<String>,
+    /// The index in `urls` of the active tab.
+    active: usize,
+}
+
+impl Session {
+    pub fn from_tabs(tabs: &TabManager) -> Self {
+        let mut urls = vec![];
+        let mut active = 0;
+        for webview in tabs.iter() {
+            let Some(url) = webview.pending_url.as_ref().or(webview.url.as_ref()) else {
+                continue;
+            };
+            if Some(webview.id) == tabs.active_id() {
+                active = urls.len();
+            }
+            urls.push(url.to_string());
+        }
+        Self {
+            version: SESSION_VERSION,
+            urls,
+            active,
+        }
+    }
+
+    /// The URLs of the tabs to restore, skipping any that fail to parse, and the index of the
+    /// active one.
+    pub fn tabs(&self) -> (Vec<ServoUrl>, usize) {
+        let mut active = 0;
+        let mut urls = vec![];
+        for (index, url) in self.urls.iter().enumerate() {
+            match ServoUrl::parse(url) {
+                Ok(url) => {
+                    if index <= self.active {
+                        active = urls.len();
+                    }
+                    urls.push(url);
+                },
+                Err(error) => warn!("Ignoring invalid URL {url:?} in session ({error})"),
+            }
+        }
+        (urls, active)
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    config_dir::get().map(|dir| dir.join("session.json"))
+}
+
+/// Read the saved session, if there is one with at least one valid tab.
+/// A session file from another version is ignored.
+pub fn load() -> Option<Session> {
+    let path = session_path()?;
+    let session: Session = config_dir::read_json(&path)?;
+    if session.version != SESSION_VERSION {
+        warn!(
+            "Ignoring session file {}: version {} is not {SESSION_VERSION}",
+            path.display(),
+            session.version
+        );
+        return None;
+    }
+    Some(session).filter(|session| !session.tabs().0.is_empty())
+}
+
+/// Save the given session, to be restored on the next launch.
+pub fn save(session: &Session) {
+    let Some(path) = session_path() else {
+        warn!("No config dir to save the session to");
+        return;
+    };
+    match config_dir::write_json(&path, session) {
+        Ok(()) => info!("Saved session to {}", path.display()),
+        Err(error) => warn!("Failed to save session to {}: {error}", path.display()),
+    }
+}
+
+#[cfg(test)]
+fn test_session(urls: &[&str], active: usize) -> Session {
+    Session {
+        version: SESSION_VERSION,
+        urls: urls.iter().map(|url| url.to_string()).collect(),
+        active,
+    }
+}
+
+#[cfg(test)]
+fn test_urls(tabs: (Vec<ServoUrl>, usize)) -> (Vec<String>, usize) {
+    let (urls, active) = tabs;
+    (urls.iter().map(ServoUrl::to_string).collect(), active)
+}
+
+#[test]
+fn test_session_from_tabs_round_trip() {
+    use servo::base::id::{PipelineNamespace, TopLevelBrowsingContextId, TEST_NAMESPACE};
+    use servo::webrender_api::units::DeviceRect;
+
+    use super::webview::WebView;
+
+    PipelineNamespace::install(TEST_NAMESPACE);
+    let mut tabs = TabManager::default();
+    let urls = [
+        Some("https://a.example/"),
+        Some("https://b.example/"),
+        None,
+        Some("https://c.example/?q=1#top"),
+    ];
+    let mut ids = vec![];
+    for url in urls {
+        let id = TopLevelBrowsingContextId::new();
+        let mut webview = WebView::new(id, DeviceRect::zero());
+        webview.url = url.map(|url| ServoUrl::parse(url).unwrap());
+        tabs.opened(webview);
+        ids.push(id);
+    }
+    // A discarded tab is saved with the URL it loads once activated, not its blank page.
+    let discarded = tabs.get_mut(ids[1]).unwrap();
+    discarded.pending_url = discarded.url.take();
+    discarded.url = Some(ServoUrl::parse("about:blank").unwrap());
+    assert!(tabs.activated(ids[3]));
+
+    let session = Session::from_tabs(&tabs);
+    let json = serde_json::to_string(&session).unwrap();
+    let restored: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.version, SESSION_VERSION);
+    // The tab without a URL is skipped, and the active tab is still the last one.
+    assert_eq!(
+        test_urls(restored.tabs()),
+        (
+            vec![
+                "https://a.example/".to_owned(),
+                "https://b.example/".to_owned(),
+                "https://c.example/?q=1#top".to_owned(),
+            ],
+            2
+        )
+    );
+}
+
+#[test]
+fn test_session_file_format() {
+    let json =
+        r#"{"version": 1, "urls": ["https://a.example/", "https://b.example/"], "active": 1}"#;
+    let session: Session = serde_json::from_str(json).unwrap();
+    assert_eq!(session.version, SESSION_VERSION);
+    assert_eq!(
+        test_urls(session.tabs()),
+        (
+            vec![
+                "https://a.example/".to_owned(),
+                "https://b.example/".to_owned()
+            ],
+            1
+        )
+    );
+    assert!(serde_json::from_str::<Session>(r#"{"urls": []}"#).is_err());
+}
+
+#[test]
+fn test_session_with_invalid_urls() {
+    let a = "https://a.example/";
+    let c = "https://c.example/";
+    // The active tab keeps its place among the valid ones.
+    let session = test_session(&[a, "not a url", c], 2);
+    assert_eq!(
+        test_urls(session.tabs()),
+        (vec![a.to_owned(), c.to_owned()], 1)
+    );
+    // An invalid active tab gives way to the tab to its left, or the first one.
+    let session = test_session(&[a, "not a url", c], 1);
+    assert_eq!(
+        test_urls(session.tabs()),
+        (vec![a.to_owned(), c.to_owned()], 0)
+    );
+    let session = test_session(&["not a url", c], 0);
+    assert_eq!(test_urls(session.tabs()), (vec![c.to_owned()], 0));
+    // An active index past the end is the last tab.
+    let session = test_session(&[a, c], 5);
+    assert_eq!(
+        test_urls(session.tabs()),
+        (vec![a.to_owned(), c.to_owned()], 1)
+    );
+    let session = test_session(&["not a url"], 0);
+    assert_eq!(test_urls(session.tabs()), (vec![], 0));
+}
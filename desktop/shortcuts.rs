@@ -0,0 +1,433 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The keyboard shortcuts of the app, and their overrides in `shortcuts.json` in the config dir.
+//!
+//! `shortcuts.json` maps the names of actions to a key chord, or to a list of them, like
+//! `{ "new-tab": "CmdOrCtrl+Shift+T", "reload": ["CmdOrCtrl+R", "F5"] }`. A chord is any number
+//! of modifiers followed by a key, separated by `+`. The modifiers are `Ctrl`, `Alt`, `Shift` and
+//! `Meta` (or `Cmd`), and `CmdOrCtrl` and `CmdOrAlt`, which are Cmd on macOS and Ctrl or Alt
+//! elsewhere. The key is a character, like `R` or `+`, or the name of a key as in
+//! [`KeyboardEvent.key`](https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_key_values),
+//! like `F5`, `Tab` or `ArrowLeft`. Names are case insensitive, except for the names of keys.
+//! Chords of symbols, like `CmdOrCtrl++`, match whether Shift is held or not, since the keyboard
+//! layout may need it to type them, unless they have `Shift` themselves.
+//!
+//! An entry with an unknown action or an invalid chord is ignored, and so is one that binds a
+//! chord of another action, since only one of them would run. Those actions keep their default
+//! chords.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use keyboard_types::{Key, KeyboardEvent, Modifiers};
+use log::warn;
+use serde::Deserialize;
+
+use super::config_dir;
+use super::keyutils::{CMD_OR_ALT, CMD_OR_CONTROL};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    Reload,
+    HardReload,
+    OpenLocation,
+    Quit,
+    NewTab,
+    Home,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    PrivateWindow,
+    ClearHistory,
+    Screenshot,
+    Bookmark,
+    SavePage,
+    ViewSource,
+    BookmarksBar,
+    SamplingProfiler,
+    CaptureWebRender,
+    RenderTargetDebug,
+    TextureCacheDebug,
+    Fullscreen,
+    FrameStats,
+    WebRenderProfiler,
+    Forward,
+    Back,
+    ExitFullscreen,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    MuteAll,
+}
+
+impl Action {
+    /// Whether the page gets the keys of this action first, and can prevent it from running.
+    pub fn is_handled_after_page(self) -> bool {
+        matches!(self, Self::ZoomIn | Self::ZoomOut | Self::ResetZoom)
+    }
+}
+
+/// The actions, with their name in `shortcuts.json` and their default chords.
+const DEFAULT_KEYMAP: &[(Action, &str, &[&str])] = &[
+    (Action::Reload, "reload", &["CmdOrCtrl+R", "F5"]),
+    (
+        Action::HardReload,
+        "hard-reload",
+        &["CmdOrCtrl+Shift+R", "CmdOrCtrl+F5"],
+    ),
+    // Only without the minibrowser, which has a location field of its own.
+    (Action::OpenLocation, "open-location", &["CmdOrCtrl+L"]),
+    (Action::Quit, "quit", &["CmdOrCtrl+Q"]),
+    (Action::NewTab, "new-tab", &["CmdOrCtrl+T"]),
+    (Action::Home, "home", &["Alt+Home"]),
+    (Action::CloseTab, "close-tab", &["CmdOrCtrl+W"]),
+    (Action::NextTab, "next-tab", &["Ctrl+Tab"]),
+    (Action::PrevTab, "prev-tab", &["Ctrl+Shift+Tab"]),
+    (
+        Action::PrivateWindow,
+        "private-window",
+        &["CmdOrCtrl+Shift+N"],
+    ),
+    (
+        Action::ClearHistory,
+        "clear-history",
+        &["CmdOrCtrl+Shift+Delete"],
+    ),
+    (Action::Screenshot, "screenshot", &["CmdOrCtrl+Shift+S"]),
+    (Action::Bookmark, "bookmark", &["CmdOrCtrl+D"]),
+    (Action::SavePage, "save-page", &["CmdOrCtrl+S"]),
+    (Action::ViewSource, "view-source", &["CmdOrCtrl+U"]),
+    (
+        Action::BookmarksBar,
+        "bookmarks-bar",
+        &["CmdOrCtrl+Shift+B"],
+    ),
+    (
+        Action::SamplingProfiler,
+        "sampling-profiler",
+        &["CmdOrCtrl+P"],
+    ),
+    (Action::CaptureWebRender, "capture-webrender", &["Ctrl+F9"]),
+    (
+        Action::RenderTargetDebug,
+        "render-target-debug",
+        &["Ctrl+F10"],
+    ),
+    (
+        Action::TextureCacheDebug,
+        "texture-cache-debug",
+        &["Ctrl+F11"],
+    ),
+    (Action::Fullscreen, "fullscreen", &["F11"]),
+    (Action::FrameStats, "frame-stats", &["F12"]),
+    (
+        Action::WebRenderProfiler,
+        "webrender-profiler",
+        &["Ctrl+F12"],
+    ),
+    (
+        Action::Forward,
+        "forward",
+        &["CmdOrAlt+ArrowRight", "BrowserForward"],
+    ),
+    (Action::Back, "back", &["CmdOrAlt+ArrowLeft", "BrowserBack"]),
+    (Action::ExitFullscreen, "exit-fullscreen", &["Escape"]),
+    (Action::ZoomIn, "zoom-in", &["CmdOrCtrl+=", "CmdOrCtrl++"]),
+    (Action::ZoomOut, "zoom-out", &["CmdOrCtrl+-"]),
+    (Action::ResetZoom, "reset-zoom", &["CmdOrCtrl+0"]),
+    (Action::MuteAll, "mute-all", &["CmdOrCtrl+Shift+M"]),
+];
+
+/// The modifiers that chords can have. Other modifiers, like Caps Lock, are ignored.
+const CHORD_MODIFIERS: Modifiers = Modifiers::CONTROL
+    .union(Modifiers::ALT)
+    .union(Modifiers::SHIFT)
+    .union(Modifiers::META);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Chord {
+    modifiers: Modifiers,
+    /// The key, with characters in upper case, since they match either case.
+    key: Key,
+}
+
+impl Chord {
+    fn matches(&self, event: &KeyboardEvent) -> bool {
+        let mut modifiers = event.modifiers & CHORD_MODIFIERS;
+        if self.is_symbol() && !self.modifiers.contains(Modifiers::SHIFT) {
+            modifiers.remove(Modifiers::SHIFT);
+        }
+        if modifiers != self.modifiers {
+            return false;
+        }
+        match (&self.key, &event.key) {
+            (Key::Character(key), Key::Character(pressed)) => key.eq_ignore_ascii_case(pressed),
+            (key, pressed) => key == pressed,
+        }
+    }
+
+    /// Whether the key is a character that is neither a letter nor a digit, like `+`.
+    fn is_symbol(&self) -> bool {
+        matches!(self.key, Key::Character(ref key) if !key.chars().any(char::is_alphanumeric))
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let (modifier_names, key_name) = match chord.strip_suffix("++") {
+            Some(modifier_names) => (modifier_names, "+"),
+            None if chord == "+" => ("", "+"),
+            None => chord.rsplit_once('+').unwrap_or(("", chord)),
+        };
+        if key_name.is_empty() {
+            return Err(format!("{chord:?} has no key"));
+        }
+        let mut modifiers = Modifiers::empty();
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "alt" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                "meta" | "cmd" => Modifiers::META,
+                "cmdorctrl" => CMD_OR_CONTROL,
+                "cmdoralt" => CMD_OR_ALT,
+                _ => return Err(format!("{name:?} is not a modifier")),
+            };
+        }
+        let key = match key_name.parse() {
+            Ok(Key::Character(character)) => Key::Character(character.to_ascii_uppercase()),
+            Ok(key) => key,
+            Err(_) => return Err(format!("{key_name:?} is not a key")),
+        };
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (Modifiers::CONTROL, "Ctrl"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::SHIFT, "Shift"),
+            (Modifiers::META, "Meta"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(formatter, "{name}+")?;
+            }
+        }
+        write!(formatter, "{}", self.key)
+    }
+}
+
+/// A chord or a list of them, as given for an action in `shortcuts.json`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChordNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub struct Shortcuts {
+    /// The chords of each action, in the order of [`DEFAULT_KEYMAP`].
+    chords: Vec<Vec<Chord>>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            chords: (0..DEFAULT_KEYMAP.len()).map(default_chords).collect(),
+        }
+    }
+}
+
+/// The default chords of the action at `index` in [`DEFAULT_KEYMAP`].
+fn default_chords(index: usize) -> Vec<Chord> {
+    let (_, _, chords) = DEFAULT_KEYMAP[index];
+    chords
+        .iter()
+        .map(|chord| chord.parse().expect("Default chords are valid"))
+        .collect()
+}
+
+impl Shortcuts {
+    /// Read the default shortcuts, overridden by those in the config dir.
+    pub fn load() -> Self {
+        let mut shortcuts = Self::default();
+        let Some(path) = config_dir::get().map(|dir| dir.join("shortcuts.json")) else {
+            return shortcuts;
+        };
+        let overrides: Option<BTreeMap<String, ChordNames>> = config_dir::read_json(&path);
+        let mut overridden = vec![];
+        for (name, chord_names) in overrides.unwrap_or_default() {
+            let Some(index) = DEFAULT_KEYMAP
+                .iter()
+                .position(|(_, action_name, _)| action_name.eq_ignore_ascii_case(&name))
+            else {
+                warn!(
+                    "Ignoring {name:?} in {}: there is no such action",
+                    path.display()
+                );
+                continue;
+            };
+            let chord_names = match chord_names {
+                ChordNames::One(chord_name) => vec![chord_name],
+                ChordNames::Many(chord_names) => chord_names,
+            };
+            match chord_names.iter().map(|chord| chord.parse()).collect() {
+                Ok(chords) => {
+                    shortcuts.chords[index] = chords;
+                    overridden.push((index, name));
+                },
+                Err(error) => warn!("Ignoring {name:?} in {}: {error}", path.display()),
+            }
+        }
+        for (index, name) in overridden {
+            if let Some((chord, other)) = shortcuts.conflict(index) {
+                warn!(
+                    "Ignoring {name:?} in {}: {chord} is a shortcut for {other:?}",
+                    path.display()
+                );
+                shortcuts.chords[index] = default_chords(index);
+            }
+        }
+        shortcuts
+    }
+
+    /// A chord of the action at `index` that is also a chord of another action, and the name of
+    /// that action.
+    fn conflict(&self, index: usize) -> Option<(&Chord, &'static str)> {
+        self.chords[index].iter().find_map(|chord| {
+            let other = (0..self.chords.len())
+                .find(|&other| other != index && self.chords[other].contains(chord))?;
+            let (_, other_name, _) = DEFAULT_KEYMAP[other];
+            Some((chord, other_name))
+        })
+    }
+
+    /// The action that the key of `event` is a shortcut for, if any, whether it is pressed or
+    /// released.
+    pub fn action(&self, event: &KeyboardEvent) -> Option<Action> {
+        let index = self
+            .chords
+            .iter()
+            .position(|chords| chords.iter().any(|chord| chord.matches(event)))?;
+        let (action, _, _) = DEFAULT_KEYMAP[index];
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+fn test_key_event(modifiers: Modifiers, key: &str) -> KeyboardEvent {
+    KeyboardEvent {
+        modifiers,
+        key: key.parse().unwrap(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+fn test_index(name: &str) -> usize {
+    DEFAULT_KEYMAP
+        .iter()
+        .position(|(_, action_name, _)| *action_name == name)
+        .unwrap()
+}
+
+#[test]
+fn test_chord_with_modifiers() {
+    let chord: Chord = "CmdOrCtrl+Shift+T".parse().unwrap();
+    assert_eq!(chord.modifiers, CMD_OR_CONTROL | Modifiers::SHIFT);
+    assert_eq!(chord.key, Key::Character("T".to_owned()));
+    assert_eq!(chord, "cmdorctrl+shift+t".parse().unwrap());
+
+    assert!(chord.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "T")));
+    assert!(chord.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "t")));
+    // Other modifiers, like Caps Lock, are ignored.
+    assert!(chord.matches(&test_key_event(
+        CMD_OR_CONTROL | Modifiers::SHIFT | Modifiers::CAPS_LOCK,
+        "T"
+    )));
+    assert!(!chord.matches(&test_key_event(CMD_OR_CONTROL, "T")));
+    assert!(!chord.matches(&test_key_event(
+        CMD_OR_CONTROL | Modifiers::SHIFT | Modifiers::ALT,
+        "T"
+    )));
+    assert!(!chord.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "N")));
+}
+
+#[test]
+fn test_chord_of_plus_and_equals() {
+    let plus: Chord = "CmdOrCtrl++".parse().unwrap();
+    assert_eq!(plus.modifiers, CMD_OR_CONTROL);
+    assert_eq!(plus.key, Key::Character("+".to_owned()));
+    let equals: Chord = "CmdOrCtrl+=".parse().unwrap();
+    assert_eq!(equals.modifiers, CMD_OR_CONTROL);
+    assert_eq!(equals.key, Key::Character("=".to_owned()));
+    let bare_plus: Chord = "+".parse().unwrap();
+    assert_eq!(bare_plus.modifiers, Modifiers::empty());
+    assert_eq!(bare_plus.key, Key::Character("+".to_owned()));
+
+    // On a US keyboard, + is Shift+=, so it must match with Shift held too.
+    assert!(plus.matches(&test_key_event(CMD_OR_CONTROL, "+")));
+    assert!(plus.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "+")));
+    assert!(!plus.matches(&test_key_event(CMD_OR_CONTROL, "=")));
+    assert!(equals.matches(&test_key_event(CMD_OR_CONTROL, "=")));
+
+    // Chords of letters still need Shift to be the same, and symbols that say Shift need it.
+    let letter: Chord = "CmdOrCtrl+R".parse().unwrap();
+    assert!(!letter.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "R")));
+    let shift_plus: Chord = "CmdOrCtrl+Shift++".parse().unwrap();
+    assert!(!shift_plus.matches(&test_key_event(CMD_OR_CONTROL, "+")));
+    assert!(shift_plus.matches(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "+")));
+
+    let shortcuts = Shortcuts::default();
+    assert_eq!(
+        shortcuts.action(&test_key_event(CMD_OR_CONTROL | Modifiers::SHIFT, "+")),
+        Some(Action::ZoomIn)
+    );
+    assert_eq!(
+        shortcuts.action(&test_key_event(CMD_OR_CONTROL, "=")),
+        Some(Action::ZoomIn)
+    );
+}
+
+#[test]
+fn test_invalid_chords() {
+    assert!("Hyper+X".parse::<Chord>().is_err());
+    assert!("CmdOrCtrl+NotAKey".parse::<Chord>().is_err());
+    assert!("".parse::<Chord>().is_err());
+    assert!("Ctrl+".parse::<Chord>().is_err());
+}
+
+#[test]
+fn test_chord_display() {
+    let chord: Chord = "shift+ctrl+ArrowLeft".parse().unwrap();
+    assert_eq!(chord.to_string(), "Ctrl+Shift+ArrowLeft");
+    // Unlike the names of modifiers, those of keys are case sensitive.
+    assert!("Ctrl+arrowleft".parse::<Chord>().is_err());
+}
+
+#[test]
+fn test_duplicate_bindings() {
+    let mut shortcuts = Shortcuts::default();
+    for index in 0..DEFAULT_KEYMAP.len() {
+        assert!(shortcuts.conflict(index).is_none());
+    }
+
+    let new_tab = test_index("new-tab");
+    shortcuts.chords[new_tab] = vec!["F1".parse().unwrap(), "CmdOrCtrl+R".parse().unwrap()];
+    let (chord, other) = shortcuts.conflict(new_tab).unwrap();
+    assert_eq!(*chord, "CmdOrCtrl+R".parse().unwrap());
+    assert_eq!(other, "reload");
+    assert_eq!(
+        shortcuts
+            .conflict(test_index("reload"))
+            .map(|(_, other)| other),
+        Some("new-tab")
+    );
+}
@@ -0,0 +1,253 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Running a single instance of the app per config dir, for `--single-instance`.
+//!
+//! The running instance keeps `instance.lock` in the config dir, which is `~/.config/servo` on
+//! Linux, `~/Library/Application Support/Servo` on macOS and `%APPDATA%\Servo` on Windows unless
+//! `--config-dir` or `--profile-dir` says otherwise. The lock file holds the address of its
+//! [control server](super::control), which is `control.sock` in the config dir on Unix, and a
+//! port of localhost elsewhere, unless `--control-socket` or `--control-port` is given.
+//!
+//! Launching the app again finds the lock file, sends its URLs to the running instance with
+//! `open_tab` commands, and exits. If nothing listens on its control address, or its process
+//! is gone, it must have crashed without removing the lock file, so the lock is taken over
+//! instead. Any other failure to reach it is an error, rather than a second instance.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{process, thread};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+use super::control::{token_path, ControlAddress, PROTOCOL_VERSION};
+
+/// How long to keep trying to reach the running instance, which may still be starting up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What is kept in the lock file.
+#[derive(Deserialize, Serialize)]
+struct LockFile {
+    pid: u32,
+    control_socket: Option<PathBuf>,
+    control_port: Option<u16>,
+}
+
+/// The lock of the running instance, which is removed when dropped.
+pub struct SingleInstance {
+    path: PathBuf,
+}
+
+/// The outcome of [`SingleInstance::acquire`].
+pub enum Acquired {
+    /// This is the only instance, and should listen for commands on the control address.
+    Lock(SingleInstance),
+    /// Another instance is running, and was sent the URLs.
+    Forwarded,
+}
+
+impl SingleInstance {
+    /// Take the lock for this config dir, or send `urls` to the instance that holds it. The
+    /// control address to listen on, if not given, is set to the default one.
+    pub fn acquire(
+        control_address: &mut Option<ControlAddress>,
+        urls: &[ServoUrl],
+    ) -> io::Result<Acquired> {
+        let dir = config_dir::get()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "There is no config dir"))?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("instance.lock");
+        // Try again once after removing a stale lock file.
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // Dropping the lock removes the file, should anything below fail.
+                    let instance = Self { path: path.clone() };
+                    let address = match control_address.take() {
+                        Some(address) => address,
+                        None => default_control_address(&dir)?,
+                    };
+                    let lock = LockFile {
+                        pid: process::id(),
+                        control_socket: match address {
+                            #[cfg(unix)]
+                            ControlAddress::Unix(ref path) => Some(path.clone()),
+                            ControlAddress::Tcp(_) => None,
+                        },
+                        control_port: match address {
+                            ControlAddress::Tcp(port) => Some(port),
+                            #[cfg(unix)]
+                            ControlAddress::Unix(_) => None,
+                        },
+                    };
+                    *control_address = Some(address);
+                    file.write_all(&serde_json::to_vec(&lock)?)?;
+                    return Ok(Acquired::Lock(instance));
+                },
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                    let running: Option<LockFile> = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|json| serde_json::from_str(&json).ok());
+                    let Some(running) = running else {
+                        info!("Taking over {}, which is corrupt", path.display());
+                        fs::remove_file(&path)?;
+                        continue;
+                    };
+                    match forward(&running, urls) {
+                        Ok(()) => return Ok(Acquired::Forwarded),
+                        // Nothing listens on the control address, or the instance is gone.
+                        Err(error) if is_connect_error(&error) || !is_running(running.pid) => {
+                            info!("Taking over {}: {error}", path.display())
+                        },
+                        Err(error) => {
+                            return Err(io::Error::new(
+                                error.kind(),
+                                format!(
+                                    "Failed to reach the running instance, process {}: {error}",
+                                    running.pid
+                                ),
+                            ))
+                        },
+                    }
+                    fs::remove_file(&path)?;
+                },
+                Err(error) => return Err(error),
+            }
+        }
+        Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} keeps being recreated", path.display()),
+        ))
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            warn!("Failed to remove {}: {error}", self.path.display());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn default_control_address(config_dir: &Path) -> io::Result<ControlAddress> {
+    Ok(ControlAddress::Unix(config_dir.join("control.sock")))
+}
+
+/// A port of localhost that is free for now.
+#[cfg(not(unix))]
+fn default_control_address(_config_dir: &Path) -> io::Result<ControlAddress> {
+    let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+        .local_addr()?
+        .port();
+    Ok(ControlAddress::Tcp(port))
+}
+
+/// Open a tab for each of `urls` in the running instance, or its new tab page if there are none.
+fn forward(running: &LockFile, urls: &[ServoUrl]) -> io::Result<()> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        let result = match (&running.control_socket, running.control_port) {
+            #[cfg(unix)]
+            (Some(path), _) => std::os::unix::net::UnixStream::connect(path).and_then(|stream| {
+                send_urls(BufReader::new(stream.try_clone()?), stream, None, urls)
+            }),
+            (_, Some(port)) => TcpStream::connect((Ipv4Addr::LOCALHOST, port)).and_then(|stream| {
+                let token = token_path(port)
+                    .map(fs::read_to_string)
+                    .transpose()?
+                    .map(|token| token.trim().to_owned());
+                send_urls(BufReader::new(stream.try_clone()?), stream, token, urls)
+            }),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "The lock file has no control address",
+            )),
+        };
+        match result {
+            Err(error) if is_connect_error(&error) && Instant::now() < deadline => {
+                thread::sleep(CONNECT_RETRY_INTERVAL);
+            },
+            result => return result,
+        }
+    }
+}
+
+fn is_connect_error(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionRefused | ErrorKind::NotFound
+    )
+}
+
+/// Whether the process with the given ID, such as the one that wrote a lock file, still exists.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists, which it does if it isn't ours to signal.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return false;
+    }
+    let mut exit_code = 0;
+    let ok = unsafe { GetExitCodeProcess(process, &mut exit_code) };
+    unsafe { CloseHandle(process) };
+    ok != 0 && exit_code == STILL_ACTIVE as u32
+}
+
+/// Assume the process exists where there is no way to tell, so the lock is only taken over if
+/// nothing listens on its control address.
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+fn send_urls(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    token: Option<String>,
+    urls: &[ServoUrl],
+) -> io::Result<()> {
+    let mut commands = vec![json!({ "cmd": "hello", "version": PROTOCOL_VERSION, "token": token })];
+    if urls.is_empty() {
+        commands.push(json!({ "cmd": "open_tab" }));
+    }
+    commands.extend(
+        urls.iter()
+            .map(|url| json!({ "cmd": "open_tab", "url": url.as_str() })),
+    );
+    for command in commands {
+        writeln!(writer, "{command}")?;
+        writer.flush()?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let reply: Value = serde_json::from_str(&line)?;
+        if reply["ok"] != json!(true) {
+            let error = reply["error"].as_str().unwrap_or("No reply").to_owned();
+            return Err(io::Error::other(error));
+        }
+    }
+    Ok(())
+}
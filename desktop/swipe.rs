@@ -0,0 +1,87 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Two-finger swipes on macOS trackpads, which go back or forward in history, like in Safari.
+//!
+//! winit doesn't report the swipe gestures of AppKit, but it does tell when the fingers of a
+//! trackpad scroll touch the trackpad and when they leave it. A scroll that starts out mostly
+//! horizontal is followed as a swipe, while it still scrolls the page, and the minibrowser shows
+//! how far it has gone. Swiping right goes back and swiping left goes forward, but only if the
+//! fingers went past [`SWIPE_DISTANCE`] by the time they leave the trackpad.
+//!
+//! TODO: Only swipe when the page can't scroll any further sideways, as Safari does. Servo
+//! doesn't tell the embedder whether a scroll was consumed, so for now scrolling wide content far
+//! enough sideways navigates too.
+
+use servo::script_traits::TraversalDirection;
+use winit::event::TouchPhase;
+
+/// How far the fingers must go sideways to navigate, in logical pixels.
+const SWIPE_DISTANCE: f64 = 160.0;
+
+/// How far the fingers go before telling a swipe from a vertical scroll, in logical pixels.
+const SLOP: f64 = 8.0;
+
+/// A trackpad scroll, from the fingers touching the trackpad until they leave it.
+struct Gesture {
+    dx: f64,
+    dy: f64,
+    /// Whether it is a swipe, once the fingers went far enough to tell.
+    horizontal: Option<bool>,
+}
+
+#[derive(Default)]
+pub struct Swipe {
+    gesture: Option<Gesture>,
+}
+
+impl Swipe {
+    /// Follow a trackpad scroll by `dx` and `dy`, returning where to go if it ends a swipe.
+    pub fn on_scroll(&mut self, phase: TouchPhase, dx: f64, dy: f64) -> Option<TraversalDirection> {
+        match phase {
+            TouchPhase::Started => {
+                self.gesture = Some(Gesture {
+                    dx,
+                    dy,
+                    horizontal: None,
+                });
+            },
+            // Momentum after the fingers left the trackpad comes as moves without a start, so
+            // there is no gesture to follow.
+            TouchPhase::Moved => {
+                let gesture = self.gesture.as_mut()?;
+                gesture.dx += dx;
+                gesture.dy += dy;
+                if gesture.horizontal.is_none() && gesture.dx.hypot(gesture.dy) >= SLOP {
+                    let horizontal = gesture.dx.abs() > 2.0 * gesture.dy.abs();
+                    if !horizontal {
+                        self.gesture = None;
+                        return None;
+                    }
+                    gesture.horizontal = Some(horizontal);
+                }
+            },
+            TouchPhase::Ended => {
+                let gesture = self.gesture.take()?;
+                if gesture.horizontal == Some(true) && gesture.dx.abs() >= SWIPE_DISTANCE {
+                    return Some(if gesture.dx > 0.0 {
+                        TraversalDirection::Back(1)
+                    } else {
+                        TraversalDirection::Forward(1)
+                    });
+                }
+            },
+            TouchPhase::Cancelled => self.gesture = None,
+        }
+        None
+    }
+
+    /// How far the swipe has gone towards navigating, from -1 to go forward to 1 to go back, if
+    /// there is one.
+    pub fn progress(&self) -> Option<f32> {
+        let gesture = self.gesture.as_ref()?;
+        (gesture.horizontal == Some(true))
+            .then(|| (gesture.dx / SWIPE_DISTANCE).clamp(-1.0, 1.0) as f32)
+    }
+}
@@ -0,0 +1,415 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The tabs of a window: its webviews in tab strip order, and which one is active.
+//!
+//! Background tabs can be discarded, after `--discard-after` or from the context menu of the tab
+//! strip. A discarded tab keeps its title, URL and favicon, but its page is replaced with a blank
+//! one, so that Servo can free the page, and it loads again once the tab is activated, like the
+//! tabs of a restored session. Tabs that are playing media are never discarded automatically.
+//!
+//! TODO: Keep the scroll position of discarded tabs, which Servo doesn't tell the embedder, and
+//! exempt pinned tabs once tabs can be pinned.
+//!
+//! Tabs can be moved along the tab strip by dragging them. TODO: Detach a tab into a window of
+//! its own by dragging it out of the tab strip, and drop it onto the tab strip of another window,
+//! once there can be more than one window.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use servo::base::id::TopLevelBrowsingContextId as WebViewId;
+use servo::compositing::windowing::EmbedderEvent;
+use servo::servo_url::ServoUrl;
+
+use super::session::Session;
+use super::webview::WebView;
+
+/// Keeps track of the tabs of a window.
+///
+/// Servo tells us when webviews are opened, closed and focused, so the methods that change the
+/// tabs come in two kinds. [`TabManager::new_tab`], [`TabManager::close_tab`],
+/// [`TabManager::activate`], [`TabManager::next_tab`] and [`TabManager::prev_tab`] return the
+/// [`EmbedderEvent`] to send to Servo, and the tabs are only updated once Servo replies with an
+/// [`servo::embedder_traits::EmbedderMsg`], in [`TabManager::opened`], [`TabManager::closed`]
+/// and [`TabManager::activated`].
+#[derive(Default)]
+pub struct TabManager {
+    /// The tabs, in the order they appear in the tab strip.
+    tabs: Vec<WebView>,
+
+    /// The index in `tabs` of the active tab, the only one painted to the window.
+    /// Background tabs keep running, but they are hidden, unless they are discarded.
+    active: Option<usize>,
+
+    /// Restored tabs that Servo has not opened yet, and the URL to load once each is activated.
+    pending_urls: HashMap<WebViewId, ServoUrl>,
+
+    /// Tabs opened in the background that Servo has not opened yet.
+    background: HashSet<WebViewId>,
+}
+
+fn blank_url() -> ServoUrl {
+    ServoUrl::parse("about:blank").unwrap()
+}
+
+impl TabManager {
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// The tabs, in tab strip order.
+    pub fn iter(&self) -> impl Iterator<Item = &WebView> {
+        self.tabs.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut WebView> {
+        self.tabs.iter_mut()
+    }
+
+    pub fn get(&self, webview_id: WebViewId) -> Option<&WebView> {
+        self.tabs.iter().find(|webview| webview.id == webview_id)
+    }
+
+    pub fn get_mut(&mut self, webview_id: WebViewId) -> Option<&mut WebView> {
+        self.tabs
+            .iter_mut()
+            .find(|webview| webview.id == webview_id)
+    }
+
+    pub fn active(&self) -> Option<&WebView> {
+        self.active.map(|index| &self.tabs[index])
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut WebView> {
+        self.active.map(|index| &mut self.tabs[index])
+    }
+
+    pub fn active_id(&self) -> Option<WebViewId> {
+        self.active().map(|webview| webview.id)
+    }
+
+    fn index_of(&self, webview_id: WebViewId) -> Option<usize> {
+        self.tabs
+            .iter()
+            .position(|webview| webview.id == webview_id)
+    }
+
+    /// Open a new tab loading `url`. It becomes the active tab once Servo has opened it.
+    pub fn new_tab(&self, url: ServoUrl) -> EmbedderEvent {
+        EmbedderEvent::NewWebView(url, WebViewId::new())
+    }
+
+    /// Open a new tab loading `url`, behind the active tab.
+    pub fn new_background_tab(&mut self, url: ServoUrl) -> EmbedderEvent {
+        let webview_id = WebViewId::new();
+        self.background.insert(webview_id);
+        EmbedderEvent::NewWebView(url, webview_id)
+    }
+
+    /// Close the given tab.
+    pub fn close_tab(&self, webview_id: WebViewId) -> Option<EmbedderEvent> {
+        self.get(webview_id)
+            .map(|webview| EmbedderEvent::CloseWebView(webview.id))
+    }
+
+    /// Make the given tab the active one.
+    pub fn activate(&self, webview_id: WebViewId) -> Option<EmbedderEvent> {
+        self.get(webview_id)
+            .map(|webview| EmbedderEvent::FocusWebView(webview.id))
+    }
+
+    /// Activate the tab to the right of the active one, wrapping around at the end.
+    pub fn next_tab(&self) -> Option<EmbedderEvent> {
+        let index = self.active?;
+        let next = &self.tabs[(index + 1) % self.tabs.len()];
+        self.activate(next.id)
+    }
+
+    /// Activate the tab to the left of the active one, wrapping around at the start.
+    pub fn prev_tab(&self) -> Option<EmbedderEvent> {
+        let index = self.active?;
+        let prev = &self.tabs[(index + self.tabs.len() - 1) % self.tabs.len()];
+        self.activate(prev.id)
+    }
+
+    /// Open the tabs of a saved session, then a tab for each of `urls`, the first of which
+    /// becomes the active tab if there are any. `active_id` is the id to use for the active tab.
+    pub fn restore(
+        &mut self,
+        session: &Session,
+        urls: Vec<ServoUrl>,
+        active_id: WebViewId,
+    ) -> Vec<EmbedderEvent> {
+        let (mut session_urls, mut active) = session.tabs();
+        if !urls.is_empty() {
+            active = session_urls.len();
+        }
+        session_urls.extend(urls);
+        self.open(session_urls, active, active_id)
+    }
+
+    /// Open a tab for each of `urls`, the one at index `active` becoming the active tab.
+    /// `active_id` is the id to use for the active tab.
+    ///
+    /// Only the active tab loads now. The other tabs start out blank, and load their URL once
+    /// they are activated.
+    pub fn open(
+        &mut self,
+        urls: Vec<ServoUrl>,
+        active: usize,
+        active_id: WebViewId,
+    ) -> Vec<EmbedderEvent> {
+        urls.into_iter()
+            .enumerate()
+            .map(|(index, url)| {
+                if index == active {
+                    return EmbedderEvent::NewWebView(url, active_id);
+                }
+                let webview_id = WebViewId::new();
+                self.pending_urls.insert(webview_id, url);
+                EmbedderEvent::NewWebView(blank_url(), webview_id)
+            })
+            .collect()
+    }
+
+    /// Discard the given background tab, until it is activated again.
+    pub fn discard(&mut self, webview_id: WebViewId) -> Option<EmbedderEvent> {
+        let index = self.index_of(webview_id)?;
+        if Some(index) == self.active {
+            return None;
+        }
+        let webview = &mut self.tabs[index];
+        if webview.is_discarded() {
+            return None;
+        }
+        webview.pending_url = Some(webview.url.clone()?);
+        Some(EmbedderEvent::LoadUrl(webview_id, blank_url()))
+    }
+
+    /// Discard the background tabs that have not been active for `discard_after`, except those
+    /// playing media. Also returns when the next tab is due to be discarded, if any.
+    pub fn discard_idle(
+        &mut self,
+        discard_after: Duration,
+    ) -> (Vec<EmbedderEvent>, Option<Instant>) {
+        let now = Instant::now();
+        let mut idle = vec![];
+        let mut next_deadline: Option<Instant> = None;
+        for webview in &self.tabs {
+            let Some(inactive_since) = webview.inactive_since else {
+                continue;
+            };
+            if webview.is_discarded() || webview.media_playing {
+                continue;
+            }
+            let deadline = inactive_since + discard_after;
+            if deadline <= now {
+                idle.push(webview.id);
+            } else {
+                next_deadline = Some(next_deadline.map_or(deadline, |next| next.min(deadline)));
+            }
+        }
+        let events = idle
+            .into_iter()
+            .filter_map(|webview_id| self.discard(webview_id))
+            .collect();
+        (events, next_deadline)
+    }
+
+    /// Move the given tab to just before the tab at `index`, or to the end if there is none.
+    pub fn move_tab(&mut self, webview_id: WebViewId, index: usize) {
+        let Some(from) = self.index_of(webview_id) else {
+            return;
+        };
+        let active_id = self.active_id();
+        let webview = self.tabs.remove(from);
+        let to = if index > from { index - 1 } else { index };
+        self.tabs.insert(to.min(self.tabs.len()), webview);
+        self.active = active_id.and_then(|active_id| self.index_of(active_id));
+    }
+
+    /// Servo has opened a webview, add a tab for it at the end of the tab strip.
+    /// Returns true if it is a restored or background tab that should stay in the background
+    /// until activated.
+    pub fn opened(&mut self, mut webview: WebView) -> bool {
+        webview.pending_url = self.pending_urls.remove(&webview.id);
+        webview.inactive_since = Some(Instant::now());
+        let background = webview.pending_url.is_some() || self.background.remove(&webview.id);
+        self.tabs.push(webview);
+        background
+    }
+
+    /// Servo has closed a webview, remove its tab.
+    /// If it was the active tab, returns the tab that should be activated instead, if any.
+    pub fn closed(&mut self, webview_id: WebViewId) -> Option<WebViewId> {
+        let index = self.index_of(webview_id)?;
+        self.tabs.remove(index);
+        match self.active {
+            Some(active) if active == index => {
+                self.active = None;
+                // Prefer the tab that took its place, or else the one to its left.
+                self.tabs
+                    .get(index)
+                    .or_else(|| self.tabs.last())
+                    .map(|webview| webview.id)
+            },
+            Some(active) if active > index => {
+                self.active = Some(active - 1);
+                None
+            },
+            _ => None,
+        }
+    }
+
+    /// Servo has focused a webview, make its tab the active one.
+    /// Returns false if there is no tab for that webview.
+    pub fn activated(&mut self, webview_id: WebViewId) -> bool {
+        let Some(index) = self.index_of(webview_id) else {
+            return false;
+        };
+        if let Some(previous) = self.active.filter(|&previous| previous != index) {
+            self.tabs[previous].inactive_since = Some(Instant::now());
+        }
+        self.tabs[index].inactive_since = None;
+        self.active = Some(index);
+        true
+    }
+}
+
+/// A tab manager with `count` tabs that Servo has opened, the first of which is active.
+#[cfg(test)]
+fn test_tabs(count: usize) -> (TabManager, Vec<WebViewId>) {
+    use servo::base::id::{PipelineNamespace, TEST_NAMESPACE};
+    use servo::webrender_api::units::DeviceRect;
+
+    PipelineNamespace::install(TEST_NAMESPACE);
+    let mut tabs = TabManager::default();
+    let ids: Vec<_> = (0..count).map(|_| WebViewId::new()).collect();
+    for (index, id) in ids.iter().enumerate() {
+        let mut webview = WebView::new(*id, DeviceRect::zero());
+        webview.url = Some(ServoUrl::parse(&format!("https://example.com/{index}")).unwrap());
+        assert!(!tabs.opened(webview));
+    }
+    if let Some(first) = ids.first() {
+        assert!(tabs.activated(*first));
+    }
+    (tabs, ids)
+}
+
+#[cfg(test)]
+fn test_order(tabs: &TabManager) -> Vec<WebViewId> {
+    tabs.iter().map(|webview| webview.id).collect()
+}
+
+#[cfg(test)]
+fn test_focused(event: Option<EmbedderEvent>) -> Option<WebViewId> {
+    match event? {
+        EmbedderEvent::FocusWebView(webview_id) => Some(webview_id),
+        event => panic!("Expected FocusWebView, got {event:?}"),
+    }
+}
+
+#[test]
+fn test_next_and_prev_tab_wrap_around() {
+    let (mut tabs, ids) = test_tabs(3);
+    assert_eq!(test_order(&tabs), ids);
+    assert_eq!(tabs.active_id(), Some(ids[0]));
+    assert_eq!(test_focused(tabs.next_tab()), Some(ids[1]));
+    assert_eq!(test_focused(tabs.prev_tab()), Some(ids[2]));
+
+    assert!(tabs.activated(ids[2]));
+    assert_eq!(test_focused(tabs.next_tab()), Some(ids[0]));
+    assert_eq!(test_focused(tabs.prev_tab()), Some(ids[1]));
+    // The tab that was active is inactive from then on.
+    assert!(tabs.get(ids[0]).unwrap().inactive_since.is_some());
+    assert!(tabs.active().unwrap().inactive_since.is_none());
+
+    let tabs = TabManager::default();
+    assert!(tabs.next_tab().is_none());
+    assert!(tabs.prev_tab().is_none());
+}
+
+#[test]
+fn test_move_tab() {
+    let (mut tabs, ids) = test_tabs(4);
+    // Past the end moves to the end.
+    tabs.move_tab(ids[0], 10);
+    assert_eq!(test_order(&tabs), [ids[1], ids[2], ids[3], ids[0]]);
+    // Before a tab to its right, which ends up next to it.
+    tabs.move_tab(ids[1], 3);
+    assert_eq!(test_order(&tabs), [ids[2], ids[3], ids[1], ids[0]]);
+    // Before a tab to its left.
+    tabs.move_tab(ids[0], 1);
+    assert_eq!(test_order(&tabs), [ids[2], ids[0], ids[3], ids[1]]);
+    // Onto itself, or just after itself, which changes nothing.
+    tabs.move_tab(ids[1], 3);
+    tabs.move_tab(ids[1], 4);
+    assert_eq!(test_order(&tabs), [ids[2], ids[0], ids[3], ids[1]]);
+    // The active tab stays active wherever it moves to.
+    assert_eq!(tabs.active_id(), Some(ids[0]));
+    assert_eq!(test_focused(tabs.next_tab()), Some(ids[3]));
+}
+
+#[test]
+fn test_closed_tabs() {
+    let (mut tabs, ids) = test_tabs(4);
+    assert!(tabs.activated(ids[1]));
+    // Closing a tab to the left of the active one keeps the active one.
+    assert_eq!(tabs.closed(ids[0]), None);
+    assert_eq!(tabs.active_id(), Some(ids[1]));
+    // Closing the active tab activates the one that takes its place.
+    assert_eq!(tabs.closed(ids[1]), Some(ids[2]));
+    assert_eq!(tabs.active_id(), None);
+    assert!(tabs.activated(ids[3]));
+    // Or the one to its left, at the end.
+    assert_eq!(tabs.closed(ids[3]), Some(ids[2]));
+    assert!(tabs.activated(ids[2]));
+    assert_eq!(tabs.closed(ids[2]), None);
+    assert!(tabs.is_empty());
+    assert_eq!(tabs.closed(ids[2]), None);
+}
+
+#[test]
+fn test_open_and_discard_tabs() {
+    use servo::webrender_api::units::DeviceRect;
+
+    let (mut tabs, ids) = test_tabs(1);
+    let urls: Vec<_> = (0..3)
+        .map(|index| ServoUrl::parse(&format!("https://example.org/{index}")).unwrap())
+        .collect();
+    let active_id = WebViewId::new();
+    let events = tabs.open(urls.clone(), 1, active_id);
+    let opened: Vec<_> = events
+        .into_iter()
+        .map(|event| match event {
+            EmbedderEvent::NewWebView(url, webview_id) => (url, webview_id),
+            event => panic!("Expected NewWebView, got {event:?}"),
+        })
+        .collect();
+    assert_eq!(opened[0].0, blank_url());
+    assert_eq!(opened[1], (urls[1].clone(), active_id));
+    assert_eq!(opened[2].0, blank_url());
+    // Only the active one loads, the others load their URL once activated.
+    for (index, (_, webview_id)) in opened.iter().enumerate() {
+        let background = tabs.opened(WebView::new(*webview_id, DeviceRect::zero()));
+        assert_eq!(background, index != 1);
+        let pending_url = tabs.get(*webview_id).unwrap().pending_url.clone();
+        assert_eq!(pending_url, (index != 1).then(|| urls[index].clone()));
+    }
+
+    // The active tab can't be discarded, nor a tab that is already.
+    assert!(tabs.discard(ids[0]).is_none());
+    assert!(tabs.activated(active_id));
+    match tabs.discard(ids[0]) {
+        Some(EmbedderEvent::LoadUrl(webview_id, url)) => {
+            assert_eq!(webview_id, ids[0]);
+            assert_eq!(url, blank_url());
+        },
+        event => panic!("Expected LoadUrl, got {event:?}"),
+    }
+    assert!(tabs.get(ids[0]).unwrap().is_discarded());
+    assert!(tabs.discard(ids[0]).is_none());
+    assert!(tabs.discard(opened[0].1).is_none());
+}
@@ -0,0 +1,294 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Batch mode: `--url-list` loads each URL of a file in turn, headless and in the same tab, and
+//! reports how each load went, for link checking and the like.
+//!
+//! Each URL has `--load-timeout` to load. A URL fails if it times out or makes the content
+//! process panic, and the next one is loaded anyway. The report is JSON, or CSV if its path
+//! ends with `.csv`, and goes to stdout without `--url-list-report`. The app exits with a
+//! non-zero code if any URL failed.
+//!
+//! TODO: Report the HTTP status of each page and the errors logged to its console, which Servo
+//! doesn't tell the embedder, so pages that load as HTTP errors count as loaded for now. Load
+//! several URLs at once in their own tabs with `--concurrency`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use servo::servo_url::ServoUrl;
+
+use super::webview::ServoEventResponse;
+
+#[derive(Serialize)]
+struct Outcome {
+    url: String,
+    loaded: bool,
+    /// From the start of the load to its end, in milliseconds, if it loaded.
+    load_time_ms: Option<u128>,
+    error: Option<String>,
+}
+
+pub struct UrlList {
+    /// The URLs still to load, in reverse order.
+    urls: Vec<ServoUrl>,
+    /// The URL being loaded, when its load started if it has, and when it times out.
+    current: Option<(ServoUrl, Option<Instant>, Instant)>,
+    timeout: Duration,
+    outcomes: Vec<Outcome>,
+    report_path: Option<PathBuf>,
+}
+
+impl UrlList {
+    /// Load `urls` one after the other, the first of which the app opens itself.
+    pub fn new(mut urls: Vec<ServoUrl>, timeout: Duration, report_path: Option<PathBuf>) -> Self {
+        urls.reverse();
+        let current = urls.pop().map(|url| (url, None, Instant::now() + timeout));
+        Self {
+            urls,
+            current,
+            timeout,
+            outcomes: vec![],
+            report_path,
+        }
+    }
+
+    /// When the URL being loaded times out, if one is.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.current.as_ref().map(|(_, _, deadline)| *deadline)
+    }
+
+    /// Record how the load of the current URL went, for the given response from Servo. Returns
+    /// the next URL to load once it is over, or None if it isn't or if there are none left.
+    pub fn update(&mut self, response: &ServoEventResponse) -> Option<ServoUrl> {
+        let (url, started, deadline) = self.current.as_mut()?;
+        if response.load_started && started.is_none() {
+            *started = Some(Instant::now());
+        }
+        let result = if let Some(ref reason) = response.panic {
+            Err(format!("Panic: {reason}"))
+        } else if let (true, Some(started)) = (response.load_complete, *started) {
+            Ok(started.elapsed())
+        } else if Instant::now() >= *deadline {
+            Err("Timed out".to_owned())
+        } else {
+            return None;
+        };
+        self.outcomes.push(Outcome {
+            url: url.to_string(),
+            loaded: result.is_ok(),
+            load_time_ms: result.as_ref().ok().map(Duration::as_millis),
+            error: result.err(),
+        });
+        self.current = self
+            .urls
+            .pop()
+            .map(|url| (url, None, Instant::now() + self.timeout));
+        self.current.as_ref().map(|(url, ..)| url.clone())
+    }
+
+    /// Whether every URL has been loaded or has failed.
+    pub fn is_done(&self) -> bool {
+        self.current.is_none()
+    }
+
+    /// Write the report, returning whether every URL loaded.
+    pub fn write_report(&self) -> Result<bool, String> {
+        let is_csv = self
+            .report_path
+            .as_ref()
+            .and_then(|path| path.extension())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let report = if is_csv {
+            let mut csv = "url,loaded,load_time_ms,error\n".to_owned();
+            for outcome in &self.outcomes {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    csv_field(&outcome.url),
+                    outcome.loaded,
+                    outcome
+                        .load_time_ms
+                        .map_or_else(String::new, |ms| ms.to_string()),
+                    csv_field(outcome.error.as_deref().unwrap_or_default()),
+                ));
+            }
+            csv
+        } else {
+            serde_json::to_string_pretty(&self.outcomes).map_err(|error| error.to_string())? + "\n"
+        };
+        match self.report_path {
+            Some(ref path) => fs::write(path, report)
+                .map_err(|error| format!("Failed to write {}: {error}", path.display()))?,
+            None => print!("{report}"),
+        }
+        Ok(self.outcomes.iter().all(|outcome| outcome.loaded))
+    }
+}
+
+/// The URLs of a list file, one per line, without blank lines and comments starting with `#`.
+pub fn lines(list: &str) -> impl Iterator<Item = &str> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Quote a CSV field if it needs to be.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+#[cfg(test)]
+fn test_url_list(urls: &[&str], timeout: Duration, report_path: Option<PathBuf>) -> UrlList {
+    let urls = urls
+        .iter()
+        .map(|url| ServoUrl::parse(url).unwrap())
+        .collect();
+    UrlList::new(urls, timeout, report_path)
+}
+
+#[test]
+fn test_lines() {
+    let list = concat!(
+        "# Pages to check\n",
+        "\n",
+        "https://example.com/\n",
+        "  example.org  \r\n",
+        "\t# Not this one\n",
+        "file:///tmp/a b",
+    );
+    assert_eq!(
+        lines(list).collect::<Vec<_>>(),
+        ["https://example.com/", "example.org", "file:///tmp/a b"]
+    );
+    assert_eq!(lines("\n# Nothing\n  \n").count(), 0);
+}
+
+#[test]
+fn test_loads_in_order() {
+    let mut list = test_url_list(
+        &["https://a.example/", "https://b.example/"],
+        Duration::from_secs(60),
+        None,
+    );
+    assert!(!list.is_done());
+    assert!(list.deadline().is_some());
+
+    // A load that completes without having started is from the previous page.
+    let complete = ServoEventResponse {
+        load_complete: true,
+        ..Default::default()
+    };
+    assert_eq!(list.update(&complete), None);
+    assert_eq!(list.update(&ServoEventResponse::default()), None);
+
+    let started = ServoEventResponse {
+        load_started: true,
+        ..Default::default()
+    };
+    assert_eq!(list.update(&started), None);
+    assert_eq!(
+        list.update(&complete),
+        Some(ServoUrl::parse("https://b.example/").unwrap())
+    );
+    let panic = ServoEventResponse {
+        panic: Some("oops".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(list.update(&panic), None);
+    assert!(list.is_done());
+    assert!(list.deadline().is_none());
+    assert_eq!(list.update(&complete), None);
+
+    assert_eq!(list.outcomes.len(), 2);
+    assert!(list.outcomes[0].loaded);
+    assert!(list.outcomes[0].load_time_ms.is_some());
+    assert!(list.outcomes[0].error.is_none());
+    assert!(!list.outcomes[1].loaded);
+    assert!(list.outcomes[1].load_time_ms.is_none());
+    assert_eq!(list.outcomes[1].error.as_deref(), Some("Panic: oops"));
+}
+
+#[test]
+fn test_timeout() {
+    let mut list = test_url_list(
+        &["https://a.example/", "https://b.example/"],
+        Duration::ZERO,
+        None,
+    );
+    assert_eq!(
+        list.update(&ServoEventResponse::default()),
+        Some(ServoUrl::parse("https://b.example/").unwrap())
+    );
+    assert_eq!(list.outcomes[0].error.as_deref(), Some("Timed out"));
+    assert_eq!(list.update(&ServoEventResponse::default()), None);
+    assert!(list.is_done());
+}
+
+#[test]
+fn test_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    let panic = ServoEventResponse {
+        panic: Some("at \"a, b\"".to_owned()),
+        ..Default::default()
+    };
+
+    let json_path = dir.path().join("report.json");
+    let mut list = test_url_list(
+        &["https://a.example/?q=1,2"],
+        Duration::from_secs(60),
+        Some(json_path.clone()),
+    );
+    list.update(&panic);
+    assert_eq!(list.write_report(), Ok(false));
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{
+            "url": "https://a.example/?q=1,2",
+            "loaded": false,
+            "load_time_ms": null,
+            "error": "Panic: at \"a, b\"",
+        }])
+    );
+
+    let csv_path = dir.path().join("report.CSV");
+    let mut list = test_url_list(
+        &["https://a.example/?q=1,2", "https://b.example/"],
+        Duration::from_secs(60),
+        Some(csv_path.clone()),
+    );
+    list.update(&panic);
+    list.update(&ServoEventResponse {
+        load_started: true,
+        load_complete: true,
+        ..Default::default()
+    });
+    assert_eq!(list.write_report(), Ok(false));
+    let csv = fs::read_to_string(&csv_path).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "url,loaded,load_time_ms,error");
+    assert_eq!(
+        lines[1],
+        r#""https://a.example/?q=1,2",false,,"Panic: at ""a, b""""#
+    );
+    assert!(lines[2].starts_with("https://b.example/,true,"));
+    assert!(lines[2].ends_with(','));
+}
+
+#[test]
+fn test_csv_field() {
+    assert_eq!(csv_field(""), "");
+    assert_eq!(csv_field("plain text"), "plain text");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+}
@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Scripts from `--user-script`, `--scripts-manifest` and the control server, injected into
+//! pages.
+//!
+//! Servo runs every script in its `--userscripts` directory in every document, including those
+//! of iframes, so each script is written to a directory of our own, wrapped so that it:
+//!
+//! - runs once the document has been parsed (like the "document end" of browser extensions),
+//!   rather than as soon as the head is inserted, which is when Servo runs it, unless a
+//!   [manifest](super::content_scripts) says otherwise;
+//! - only runs in documents whose URL matches one of its patterns, if it has any;
+//! - doesn't run at all in documents whose origin was excluded with `--user-script-exclude`.
+//!   Each frame is checked against its own origin, so a script still runs in an iframe of
+//!   another origin embedded in an excluded page, and vice versa.
+//!
+//! Servo reads the directory again for every document, so scripts added at runtime apply from
+//! the next navigation on. User stylesheets are handled by Servo's own `--user-stylesheet`,
+//! which applies to every document without exceptions.
+
+use std::path::PathBuf;
+use std::{fs, io};
+
+use log::warn;
+use servo::config::opts;
+
+use super::content_scripts::{ContentScript, MatchPattern, RunAt};
+
+pub struct UserScripts {
+    dir: PathBuf,
+    /// The serialized origins that scripts don't run in, like `https://example.com`.
+    excluded_origins: Vec<String>,
+    count: usize,
+}
+
+impl UserScripts {
+    /// Create the directory for the scripts, and tell Servo to run the scripts in it. It gets
+    /// a random name and is only accessible to the user, so that nobody else can plant scripts
+    /// in it.
+    pub fn new(excluded_origins: Vec<String>) -> io::Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("servoshell-user-scripts-")
+            .tempdir()?
+            .into_path();
+        let mut user_scripts_opts = opts::get().clone();
+        user_scripts_opts.userscripts = Some(dir.to_string_lossy().into_owned());
+        opts::set_options(user_scripts_opts);
+        Ok(Self {
+            dir,
+            excluded_origins,
+            count: 0,
+        })
+    }
+
+    /// Inject `source` into every document loaded from now on.
+    pub fn add(&mut self, source: &str) -> io::Result<()> {
+        self.add_matching(source, None, RunAt::DocumentEnd)
+    }
+
+    /// Inject the scripts and stylesheets of `script` into the documents loaded from now on
+    /// that match its patterns.
+    pub fn add_content_script(&mut self, script: &ContentScript) -> io::Result<()> {
+        for css in &script.css {
+            let source = format!(
+                "let style = document.createElement('style');\n\
+                 style.textContent = {css};\n\
+                 (document.head || document.documentElement).append(style);\n",
+                css = serde_json::to_string(css)?,
+            );
+            self.add_matching(
+                &source,
+                Some(script.matches.as_slice()),
+                RunAt::DocumentStart,
+            )?;
+        }
+        for source in &script.js {
+            self.add_matching(source, Some(script.matches.as_slice()), script.run_at)?;
+        }
+        Ok(())
+    }
+
+    fn add_matching(
+        &mut self,
+        source: &str,
+        matches: Option<&[MatchPattern]>,
+        run_at: RunAt,
+    ) -> io::Result<()> {
+        let matches = match matches {
+            // Like browser extensions, ignore the fragment.
+            Some(matches) => {
+                let regexes: Vec<_> = matches.iter().map(|pattern| &pattern.regex).collect();
+                format!(
+                    "let url = location.href.split('#')[0];\n\
+                     if (!{}.some(regex => new RegExp(regex).test(url))) return;\n",
+                    serde_json::to_string(&regexes)?
+                )
+            },
+            None => String::new(),
+        };
+        let run = match run_at {
+            RunAt::DocumentStart => "run();\n",
+            RunAt::DocumentEnd => {
+                "if (document.readyState == 'loading') {\n\
+                 document.addEventListener('DOMContentLoaded', run, { once: true });\n\
+                 } else {\n\
+                 run();\n\
+                 }\n"
+            },
+            RunAt::DocumentIdle => {
+                "if (document.readyState == 'complete') {\n\
+                 run();\n\
+                 } else {\n\
+                 window.addEventListener('load', run, { once: true });\n\
+                 }\n"
+            },
+        };
+        let wrapped = format!(
+            "(function() {{\n\
+             if ({excluded}.includes(location.origin)) return;\n\
+             {matches}\
+             let run = () => (0, eval)({source});\n\
+             {run}\
+             }})();\n",
+            excluded = serde_json::to_string(&self.excluded_origins)?,
+            source = serde_json::to_string(source)?,
+        );
+        // Servo runs the scripts in the order of their file names.
+        let path = self.dir.join(format!("{:04}.js", self.count));
+        fs::write(path, wrapped)?;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+impl Drop for UserScripts {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.dir) {
+            warn!("Failed to remove {}: {error}", self.dir.display());
+        }
+    }
+}
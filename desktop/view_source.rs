@@ -0,0 +1,292 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Showing the source of a page, for Ctrl+U and `view-source:` URLs.
+//!
+//! Servo can't be taught new URL schemes, and doesn't give the embedder the bytes it received,
+//! so the source is fetched again on a background thread: `file:` pages are read again, and
+//! `http:` and `https:` pages are requested again outside of Servo (see [`super::fetch`]). Reader
+//! mode, saving pages and the response headers window get their pages the same way. The source
+//! is highlighted into an HTML page of our own, which is written to a temporary file for a new
+//! tab to load. Fetching and highlighting happen off the event loop, so that big pages don't
+//! freeze the UI, though Servo still has to lay out all of it.
+//!
+//! TODO: Show the source as Servo received it, rather than what the server sends to a GET
+//! without the cookies and cache of the original request. This needs Servo's network layer to
+//! give the embedder the body of the response for a document, which it has no API for.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use servo::embedder_traits::EventLoopWaker;
+use servo::servo_url::ServoUrl;
+
+use super::fetch;
+
+/// The prefix of the URLs that the location bar shows the source of.
+pub const VIEW_SOURCE_PREFIX: &str = "view-source:";
+
+/// Sources bigger than this are cut off.
+const MAX_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct ViewSource {
+    /// A private temporary directory for the pages, created by the first one.
+    dir: Option<PathBuf>,
+    /// How many sources have been written, to name the next one.
+    count: usize,
+    sender: Sender<Result<ServoUrl, String>>,
+    receiver: Receiver<Result<ServoUrl, String>>,
+    event_loop_waker: Box<dyn EventLoopWaker>,
+}
+
+impl ViewSource {
+    pub fn new(event_loop_waker: Box<dyn EventLoopWaker>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            dir: None,
+            count: 0,
+            sender,
+            receiver,
+            event_loop_waker,
+        }
+    }
+
+    /// Whether the source of `url` can be fetched again.
+    pub fn can_open(url: &ServoUrl) -> bool {
+        matches!(url.scheme(), "http" | "https" | "file")
+    }
+
+    /// Start reading the source of `url`. Once it is ready, [`ViewSource::take_loaded`] returns
+    /// the URL of the page showing it.
+    pub fn open(&mut self, url: ServoUrl) {
+        if !Self::can_open(&url) {
+            warn!("Can't show the source of {url}, only of http:, https: and file: URLs");
+            return;
+        }
+        let dir: &PathBuf = match self.dir {
+            Some(ref dir) => dir,
+            None => match tempfile::Builder::new()
+                .prefix("servoshell-view-source-")
+                .tempdir()
+            {
+                Ok(dir) => self.dir.insert(dir.into_path()),
+                Err(error) => {
+                    warn!("Failed to create a directory for the source: {error}");
+                    return;
+                },
+            },
+        };
+        let path = dir.join(format!("{:04}.html", self.count));
+        self.count += 1;
+        let sender = self.sender.clone();
+        let event_loop_waker = self.event_loop_waker.clone();
+        let result = thread::Builder::new()
+            .name("ViewSource".to_owned())
+            .spawn(move || {
+                let result = fetch_source(&url).and_then(|source| {
+                    let html = highlight(&url, &String::from_utf8_lossy(&source));
+                    fs::write(&path, html).map_err(|error| error.to_string())?;
+                    ServoUrl::from_file_path(&path).map_err(|()| "Invalid path".to_owned())
+                });
+                let _ = sender.send(result.map_err(|error| format!("{url}: {error}")));
+                event_loop_waker.wake();
+            });
+        if let Err(error) = result {
+            warn!("Failed to spawn view source thread: {error}");
+        }
+    }
+
+    /// The URLs of the source pages that are ready to be opened.
+    pub fn take_loaded(&mut self) -> Vec<ServoUrl> {
+        self.receiver
+            .try_iter()
+            .filter_map(|result| {
+                result
+                    .map_err(|error| warn!("Failed to view source of {error}"))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+impl Drop for ViewSource {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.dir {
+            if let Err(error) = fs::remove_dir_all(dir) {
+                warn!("Failed to remove {}: {error}", dir.display());
+            }
+        }
+    }
+}
+
+/// Fetch `url` again, as it comes from the server or the file system.
+pub fn fetch_source(url: &ServoUrl) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    match url.scheme() {
+        "http" | "https" => fetch::agent_builder()?
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .get(url.as_str())
+            .call()
+            // Show the source of error pages too.
+            .or_else(|error| match error {
+                ureq::Error::Status(_, response) => Ok(response),
+                error => Err(error.to_string()),
+            })?
+            .into_reader()
+            .take(MAX_SOURCE_BYTES)
+            .read_to_end(&mut bytes),
+        "file" => {
+            let path = url.to_file_path().map_err(|()| "Invalid path".to_owned())?;
+            fs::File::open(path)
+                .and_then(|file| file.take(MAX_SOURCE_BYTES).read_to_end(&mut bytes))
+        },
+        scheme => return Err(format!("Unsupported scheme {scheme}")),
+    }
+    .map_err(|error| error.to_string())?;
+    Ok(bytes)
+}
+
+/// The page showing `source`, with line numbers, and the tags, attributes and comments in
+/// colors. A checkbox at the top wraps long lines, without needing any script.
+fn highlight(url: &ServoUrl, source: &str) -> String {
+    let mut lines = Lines::default();
+    let mut rest = source;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(rest.len(), |index| index + 7);
+            lines.push(Some("comment"), &rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |index| index + 1);
+            push_tag(&mut lines, &rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            lines.push(None, &rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    format!(
+        "<!DOCTYPE html>\n\
+         <meta charset=utf-8>\n\
+         <title>{VIEW_SOURCE_PREFIX}{title}</title>\n\
+         <style>\n\
+         body {{ margin: 0; font-family: monospace; }}\n\
+         label {{ display: block; padding: 4px 8px; background: #eee; font-family: sans-serif; }}\n\
+         pre {{ margin: 0; padding: 4px 8px; white-space: pre; }}\n\
+         #wrap:checked + label + pre {{ white-space: pre-wrap; }}\n\
+         .line-number {{ display: inline-block; width: 6ch; color: #999; text-align: right; \
+         margin-right: 2ch; }}\n\
+         .tag {{ color: #881280; }}\n\
+         .attribute {{ color: #994500; }}\n\
+         .value {{ color: #1a1aa6; }}\n\
+         .comment {{ color: #236e25; }}\n\
+         </style>\n\
+         <input type=checkbox id=wrap><label for=wrap>Wrap lines</label>\n\
+         <pre>{}</pre>\n",
+        lines.finish(),
+        title = escape(url.as_str()),
+    )
+}
+
+/// Highlight a tag, like `<a href="…">`, as its name, and the names and values of its
+/// attributes.
+fn push_tag(lines: &mut Lines, tag: &str) {
+    let name_end = tag
+        .find(|c: char| c.is_whitespace() || c == '>')
+        .unwrap_or(tag.len());
+    lines.push(Some("tag"), &tag[..name_end]);
+    let mut rest = &tag[name_end..];
+    while !rest.is_empty() {
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let end = rest[1..].find(quote).map_or(rest.len(), |index| index + 2);
+            lines.push(Some("value"), &rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with(|c: char| c.is_whitespace() || c == '=') {
+            let end = rest
+                .find(|c: char| !c.is_whitespace() && c != '=')
+                .unwrap_or(rest.len());
+            lines.push(None, &rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with('>') || rest.starts_with("/>") {
+            lines.push(Some("tag"), rest);
+            break;
+        } else if lines.last_was_equals {
+            // An unquoted value.
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .unwrap_or(rest.len());
+            lines.push(Some("value"), &rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+                .map_or(rest.len(), |(index, _)| index);
+            lines.push(Some("attribute"), &rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+}
+
+/// The highlighted source, numbered line by line.
+#[derive(Default)]
+struct Lines {
+    html: String,
+    count: usize,
+    /// Whether the last text pushed ended with `=`, as before an attribute value.
+    last_was_equals: bool,
+}
+
+impl Lines {
+    /// Add `text` in the given class, closing and reopening its span around line breaks, so
+    /// that every line starts with its number.
+    fn push(&mut self, class: Option<&str>, text: &str) {
+        if self.count == 0 {
+            self.start_line();
+        }
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.html.push('\n');
+                self.start_line();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match class {
+                Some(class) => {
+                    let _ = write!(self.html, "<span class={class}>{}</span>", escape(line));
+                },
+                None => self.html.push_str(&escape(line)),
+            }
+        }
+        self.last_was_equals = text.trim_end().ends_with('=');
+    }
+
+    fn start_line(&mut self) {
+        self.count += 1;
+        let _ = write!(self.html, "<span class=line-number>{}</span>", self.count);
+    }
+
+    fn finish(self) -> String {
+        self.html
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -3,60 +3,159 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use std::vec::Drain;
-use std::{env, thread};
+use std::{env, process, thread};
 
 use arboard::Clipboard;
 use euclid::{Point2D, Vector2D};
 use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Repeat, Replay, Ticks};
 use gilrs::{EventType, Gilrs};
-use keyboard_types::{Key, KeyboardEvent, Modifiers, ShortcutMatcher};
+use image::RgbaImage;
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers, ShortcutMatcher};
 use log::{debug, error, info, trace, warn};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult};
 use servo::base::id::TopLevelBrowsingContextId as WebViewId;
-use servo::compositing::windowing::{EmbedderEvent, WebRenderDebugOption};
+use servo::compositing::windowing::{EmbedderEvent, MouseWindowEvent, WebRenderDebugOption};
 use servo::embedder_traits::{
-    CompositorEventVariant, ContextMenuResult, DualRumbleEffectParams, EmbedderMsg, FilterPattern,
-    GamepadHapticEffectType, PermissionPrompt, PermissionRequest, PromptDefinition, PromptOrigin,
+    CompositorEventVariant, ContextMenuResult, DualRumbleEffectParams, EmbedderMsg, EventLoopWaker,
+    FilterPattern, GamepadHapticEffectType, MediaSessionActionType, MediaSessionEvent,
+    MediaSessionPlaybackState, PermissionPrompt, PermissionRequest, PromptDefinition, PromptOrigin,
     PromptResult,
 };
 use servo::ipc_channel::ipc::IpcSender;
 use servo::script_traits::{
     GamepadEvent, GamepadIndex, GamepadInputBounds, GamepadSupportedHapticEffects,
-    GamepadUpdateType, TouchEventType, TraversalDirection,
+    GamepadUpdateType, MouseButton, TouchEventType, TraversalDirection,
 };
-use servo::servo_config::opts;
+use servo::servo_config::{opts, pref, set_pref};
 use servo::servo_url::ServoUrl;
 use servo::webrender_api::units::DeviceRect;
 use servo::webrender_api::ScrollLocation;
 use tinyfiledialogs::{self, MessageBoxIcon, OkCancel, YesNo};
 
-use super::keyutils::{CMD_OR_ALT, CMD_OR_CONTROL};
+use super::bookmarks::{BookmarkId, Bookmarks};
+use super::config_dir;
+use super::crash_reporter;
+use super::crashes::{log_crash, CrashPages};
+use super::curl;
+use super::device_emulation::Emulation;
+use super::downloads::Downloads;
+use super::favicon::{Favicons, FAVICON_SIZE};
+use super::frame_stats::FrameStats;
+use super::history::{History, HistoryEntry};
+use super::keyutils::CMD_OR_CONTROL;
+use super::mobile_sites::MobileSites;
+use super::new_tab::{NewTabPage, MAX_RECENT_PAGES, NEW_TAB_LOCATION};
+use super::permissions::{feature_name, Permissions, AUTOPLAY_FEATURE, POPUPS_FEATURE};
+use super::power_save::PowerSave;
+use super::reader::Reader;
+use super::response_headers::{Response, ResponseHeaders};
+use super::save_page::save_page;
+use super::search_engines::KeywordSearches;
+use super::shortcuts::{Action, Shortcuts};
+use super::tabs::TabManager;
+use super::view_source::ViewSource;
 use super::window_trait::{WindowPortsMethods, LINE_HEIGHT};
+use super::zoom::{
+    is_default_zoom, zoom_in_level, zoom_out_level, PageZooms, MAX_PAGE_ZOOM, MIN_PAGE_ZOOM,
+};
 use crate::desktop::tracing::{trace_embedder_event, trace_embedder_msg};
-use crate::parser::location_bar_input_to_url;
+use crate::parser::{fix_up_url, get_default_url, location_bar_input_to_url};
+
+/// Titles of the window longer than this many characters are cut off, since window managers
+/// don't all cope with them.
+const MAX_WINDOW_TITLE_CHARS: usize = 120;
+
+/// Pages can open popups for this long after they were clicked, tapped or typed into.
+const USER_ACTIVATION_DURATION: Duration = Duration::from_secs(1);
+
+/// The title that Servo's network error page has until its script explains the failure, which
+/// must match the `<title>` of resources/neterror.html.
+const NET_ERROR_PAGE_TITLE: &str = "Error loading page";
 
 pub struct WebViewManager<Window: WindowPortsMethods + ?Sized> {
-    current_url: Option<ServoUrl>,
-    current_url_string: Option<String>,
     status_text: Option<String>,
 
-    /// List of top-level browsing contexts.
-    /// Modified by EmbedderMsg::WebViewOpened and EmbedderMsg::WebViewClosed,
-    /// and we exit if it ever becomes empty.
-    webviews: HashMap<WebViewId, WebView>,
+    /// List of top-level browsing contexts, one per tab.
+    /// Modified by EmbedderMsg::WebViewOpened, EmbedderMsg::WebViewClosed and
+    /// EmbedderMsg::WebViewFocused, and we exit if it ever becomes empty.
+    tabs: TabManager,
+
+    /// The zoom factor of each origin, applied to tabs as they navigate.
+    page_zooms: PageZooms,
+    /// Whether the zoom factor of the active tab has changed since the last
+    /// [`WebViewManager::handle_servo_events`], needing a toolbar update.
+    page_zoom_changed: bool,
+    /// Whether each origin gets its mobile or desktop site, applied to tabs as they navigate.
+    mobile_sites: MobileSites,
+
+    /// Whether this is a private window, that doesn't write anything about its browsing to disk.
+    private: bool,
+
+    /// The browsing history, or None if it isn't being recorded.
+    history: Option<History>,
+
+    /// The favicon of each origin, or None if there is no minibrowser to show them or if this is
+    /// a private window.
+    favicons: Option<Favicons>,
+
+    bookmarks: Bookmarks,
+    bookmarks_bar_visible: bool,
+
+    permissions: Permissions,
+    /// Whether media that pages play without being clicked is paused, see
+    /// [`WebViewManager::allow_autoplay`].
+    block_autoplay: bool,
+
+    view_source: ViewSource,
+    reader: Reader,
+    /// The tabs in reader mode, and the tab of the page each is showing the article of.
+    reader_tabs: HashMap<WebViewId, WebViewId>,
+
+    response_headers: ResponseHeaders,
+    /// Whether the response headers window is open, so the headers of the active tab are
+    /// fetched whenever it has loaded another page.
+    inspecting_response_headers: bool,
+
+    downloads: Downloads,
+
+    keyword_searches: KeywordSearches,
+
+    shortcuts: Shortcuts,
+
+    /// The page opened in new tabs, from `--new-tab-page`, or None for the new tab page.
+    new_tab_url: Option<ServoUrl>,
 
-    /// The order in which the webviews were created.
-    creation_order: Vec<WebViewId>,
+    frame_stats: FrameStats,
 
-    /// The webview that is currently focused.
-    /// Modified by EmbedderMsg::WebViewFocused and EmbedderMsg::WebViewBlurred.
-    focused_webview_id: Option<WebViewId>,
+    /// How long background tabs are left alone before they are discarded, from
+    /// `--discard-after`, or None to never discard them automatically.
+    discard_after: Option<Duration>,
 
-    title: Option<String>,
+    /// Whether links Ctrl-clicked open in the foreground, and in the background with Shift,
+    /// from `--links-in-foreground`, rather than the other way around.
+    links_in_foreground: bool,
+
+    power_save: PowerSave,
+
+    /// The tab whose page last reported on its media session. Servo sends media session actions
+    /// to the last media session it heard from, so they go to this tab.
+    media_session_tab: Option<WebViewId>,
+
+    /// The pages shown in place of pages that crashed.
+    crash_pages: CrashPages,
+
+    /// Where the new tab page is written, in the config dir unless this is a private window.
+    new_tab_page: NewTabPage,
+
+    /// The context menu that Servo asked to show, until an item is chosen or it is dismissed.
+    context_menu: Option<ContextMenu>,
 
     window: Rc<Window>,
     event_queue: Vec<EmbedderEvent>,
@@ -64,17 +163,148 @@ pub struct WebViewManager<Window: WindowPortsMethods + ?Sized> {
     gamepad: Option<Gilrs>,
     haptic_effects: HashMap<usize, HapticEffect>,
     shutdown_requested: bool,
-    load_status: LoadStatus,
+    screenshot_requested: bool,
 }
 
 #[derive(Debug)]
 pub struct WebView {
+    pub id: WebViewId,
     pub rect: DeviceRect,
+    pub title: Option<String>,
+    pub url: Option<ServoUrl>,
+    pub favicon_url: Option<ServoUrl>,
+    pub load_status: LoadStatus,
+    /// For a restored or discarded tab that has not been activated since, the URL it will load
+    /// once it is.
+    pub pending_url: Option<ServoUrl>,
+    /// When this tab stopped being the active one, or None while it is active.
+    pub inactive_since: Option<Instant>,
+    /// Whether the page is playing media, according to its media session.
+    pub media_playing: bool,
+    /// Whether the page played media without being clicked, which was paused with `--autoplay
+    /// block`.
+    pub autoplay_blocked: bool,
+    /// Whether the user played the blocked media of the page, letting it play until the tab
+    /// navigates.
+    autoplay_allowed: bool,
+    /// Whether the user muted this tab, see `WebViewManager::set_muted`.
+    pub muted: bool,
+    /// Whether the media of the page was paused because the tab was muted, to play it again
+    /// once the tab is unmuted.
+    paused_for_mute: bool,
+    /// Whether Servo was told to throttle this tab, to save power in the background.
+    pub throttled: bool,
+    pub page_zoom: f32,
+    /// Whether the page is laid out like on a phone, see [`super::mobile_sites`].
+    pub mobile: bool,
+    /// The URLs of the session history entries, oldest first, as reported by Servo.
+    pub session_history: Vec<ServoUrl>,
+    /// The index of the current entry in `session_history`.
+    pub session_history_index: usize,
+    /// The URL being navigated to, until it has loaded.
+    pub navigation_url: Option<ServoUrl>,
+    /// The URL of the page that last crashed in this tab, which reloading the crash page loads.
+    pub crashed_url: Option<ServoUrl>,
+    /// How many popups the page tried to open without being clicked, which were blocked.
+    pub blocked_popups: usize,
+    /// Whether the page being loaded, or last loaded, is Servo's network error page.
+    pub load_failed: bool,
+}
+
+impl WebView {
+    pub fn new(id: WebViewId, rect: DeviceRect) -> Self {
+        Self {
+            id,
+            rect,
+            title: None,
+            url: None,
+            favicon_url: None,
+            load_status: LoadStatus::LoadComplete,
+            pending_url: None,
+            inactive_since: None,
+            media_playing: false,
+            autoplay_blocked: false,
+            autoplay_allowed: false,
+            muted: false,
+            paused_for_mute: false,
+            throttled: false,
+            page_zoom: 1.0,
+            mobile: false,
+            session_history: vec![],
+            session_history_index: 0,
+            navigation_url: None,
+            crashed_url: None,
+            blocked_popups: 0,
+            load_failed: false,
+        }
+    }
+
+    /// Whether the page of this tab was unloaded, or never loaded, to be loaded once the tab is
+    /// activated.
+    pub fn is_discarded(&self) -> bool {
+        self.pending_url.is_some()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.session_history_index > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.session_history_index + 1 < self.session_history.len()
+    }
+
+    /// The title to show for this webview, falling back to its URL if it has no title.
+    pub fn display_title(&self) -> String {
+        match (&self.title, self.pending_url.as_ref().or(self.url.as_ref())) {
+            (Some(title), _) if !title.is_empty() => title.clone(),
+            (_, Some(url)) => url.to_string(),
+            _ => String::from("Untitled"),
+        }
+    }
+}
+
+/// A context menu for the page, with the items that the page added to it, if any.
+///
+/// Servo doesn't say what is under the pointer, so the minibrowser can only add actions for the
+/// whole page, not for links, images or editable fields.
+pub struct ContextMenu {
+    pub title: Option<String>,
+    pub items: Vec<String>,
+    sender: IpcSender<ContextMenuResult>,
+}
+
+/// The options of [`WebViewManager::new`], from the command line.
+pub struct WebViewManagerOptions {
+    /// Whether this is a private window, that doesn't write anything about its browsing to disk.
+    pub private: bool,
+    /// Deny every permission without asking, for `--deny-all-permissions`.
+    pub deny_all_permissions: bool,
+    /// Pause media that pages play without being clicked, for `--autoplay block`.
+    pub block_autoplay: bool,
+    /// The page that new tabs open, instead of the new tab page.
+    pub new_tab_url: Option<ServoUrl>,
+    pub frame_stats: FrameStats,
+    /// How long background tabs left alone are kept loaded, if they are ever discarded.
+    pub discard_after: Option<Duration>,
+    /// Whether links Ctrl-clicked (Cmd-clicked on macOS) open in the foreground, rather than
+    /// with Shift too.
+    pub links_in_foreground: bool,
+    /// Whether sites get their mobile site unless the user chose otherwise.
+    pub mobile_by_default: bool,
+    /// Whether to save power as on battery power, even when plugged in.
+    pub power_save: bool,
 }
 
+#[derive(Default)]
 pub struct ServoEventResponse {
     pub need_present: bool,
     pub need_update: bool,
+    /// A page started loading.
+    pub load_started: bool,
+    /// A page finished loading.
+    pub load_complete: bool,
+    /// The reason for a panic in the content process, if any.
+    pub panic: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -93,15 +323,61 @@ impl<Window> WebViewManager<Window>
 where
     Window: WindowPortsMethods + ?Sized,
 {
-    pub fn new(window: Rc<Window>) -> WebViewManager<Window> {
+    pub fn new(
+        window: Rc<Window>,
+        options: WebViewManagerOptions,
+        event_loop_waker: Box<dyn EventLoopWaker>,
+    ) -> WebViewManager<Window> {
+        let WebViewManagerOptions {
+            private,
+            deny_all_permissions,
+            block_autoplay,
+            new_tab_url,
+            frame_stats,
+            discard_after,
+            links_in_foreground,
+            mobile_by_default,
+            power_save,
+        } = options;
         WebViewManager {
-            title: None,
-            current_url: None,
-            current_url_string: None,
             status_text: None,
-            webviews: HashMap::default(),
-            creation_order: vec![],
-            focused_webview_id: None,
+            tabs: TabManager::default(),
+            page_zooms: PageZooms::load(private),
+            page_zoom_changed: false,
+            mobile_sites: MobileSites::load(mobile_by_default, private),
+            private,
+            // Like private windows, headless runs don't record history, since they are usually
+            // automated.
+            history: if private || opts::get().headless {
+                None
+            } else {
+                History::open()
+            },
+            favicons: (opts::get().minibrowser && !opts::get().headless && !private).then(|| {
+                let cache_dir = config_dir::get().map(|dir| dir.join("favicons"));
+                Favicons::new(cache_dir, event_loop_waker.clone())
+            }),
+            bookmarks: Bookmarks::load(private),
+            bookmarks_bar_visible: false,
+            permissions: Permissions::load(deny_all_permissions, private),
+            block_autoplay,
+            view_source: ViewSource::new(event_loop_waker.clone()),
+            reader: Reader::new(event_loop_waker.clone()),
+            reader_tabs: HashMap::new(),
+            response_headers: ResponseHeaders::new(event_loop_waker.clone()),
+            inspecting_response_headers: false,
+            downloads: Downloads::new(event_loop_waker),
+            context_menu: None,
+            keyword_searches: KeywordSearches::load(),
+            shortcuts: Shortcuts::load(),
+            new_tab_url,
+            frame_stats,
+            discard_after,
+            links_in_foreground,
+            power_save: PowerSave::new(power_save),
+            media_session_tab: None,
+            crash_pages: CrashPages::default(),
+            new_tab_page: NewTabPage::new(private),
             window,
             clipboard: match Clipboard::new() {
                 Ok(c) => Some(c),
@@ -121,32 +397,930 @@ where
 
             event_queue: Vec::new(),
             shutdown_requested: false,
-            load_status: LoadStatus::LoadComplete,
+            screenshot_requested: false,
         }
     }
 
     pub fn webview_id(&self) -> Option<WebViewId> {
-        self.focused_webview_id
+        self.tabs.active_id()
+    }
+
+    pub fn get(&self, webview_id: WebViewId) -> Option<&WebView> {
+        self.tabs.get(webview_id)
     }
 
     pub fn get_mut(&mut self, webview_id: WebViewId) -> Option<&mut WebView> {
-        self.webviews.get_mut(&webview_id)
+        self.tabs.get_mut(webview_id)
     }
 
+    pub fn tabs(&self) -> &TabManager {
+        &self.tabs
+    }
+
+    pub fn tabs_mut(&mut self) -> &mut TabManager {
+        &mut self.tabs
+    }
+
+    /// Discard the given background tab, as the "sleep" item of its context menu does.
+    pub fn discard_tab(&mut self, webview_id: WebViewId) {
+        self.event_queue.extend(self.tabs.discard(webview_id));
+    }
+
+    /// Discard the background tabs that have been left alone for `--discard-after`, returning
+    /// when the next one is due to be, if any.
+    pub fn discard_idle_tabs(&mut self) -> Option<Instant> {
+        let (events, next_deadline) = self.tabs.discard_idle(self.discard_after?);
+        self.event_queue.extend(events);
+        next_deadline
+    }
+
+    pub fn power_saving(&self) -> bool {
+        self.power_save.enabled()
+    }
+
+    /// Turn power saving on or off from the toolbar, until the app exits.
+    pub fn set_power_saving(&mut self, enabled: bool) {
+        self.power_save.set_enabled(enabled);
+        self.throttle_background_tabs();
+    }
+
+    /// Whether network responses are cached, unless `--incognito-network` or the toolbar turned
+    /// that off.
+    pub fn http_cache_enabled(&self) -> bool {
+        !pref!(network.http_cache.disabled)
+    }
+
+    /// Turn the caching of network responses on or off, for the requests made from then on.
+    pub fn set_http_cache_enabled(&mut self, enabled: bool) {
+        set_pref!(network.http_cache.disabled, !enabled);
+    }
+
+    /// Throttle or unthrottle the background tabs if power saving has been turned on or off by
+    /// plugging or unplugging the system.
+    pub fn check_power_save(&mut self) {
+        if self.power_save.check_battery() {
+            self.throttle_background_tabs();
+        }
+    }
+
+    /// Mute the tab if it isn't muted, or else unmute it.
+    pub fn toggle_mute(&mut self, webview_id: WebViewId) {
+        if let Some(webview) = self.tabs.get(webview_id) {
+            self.set_muted(webview_id, !webview.muted);
+        }
+    }
+
+    /// Mute every tab, or unmute them all if they all are muted already.
+    pub fn toggle_mute_all(&mut self) {
+        let muted = self.tabs.iter().any(|webview| !webview.muted);
+        let ids: Vec<_> = self.tabs.iter().map(|webview| webview.id).collect();
+        for id in ids {
+            self.set_muted(id, muted);
+        }
+    }
+
+    /// Mute or unmute a tab.
+    ///
+    /// Servo can't mute the media of a webview, so the media of a muted tab is paused instead,
+    /// and played again when it is unmuted. Media session actions only reach the tab that last
+    /// reported on its media session, so a muted tab that is playing in the background is paused
+    /// the next time its page reports playing, rather than right away.
+    fn set_muted(&mut self, webview_id: WebViewId, muted: bool) {
+        let media_session = self.media_session_tab == Some(webview_id);
+        let Some(webview) = self.tabs.get_mut(webview_id) else {
+            return;
+        };
+        if webview.muted == muted {
+            return;
+        }
+        webview.muted = muted;
+        if !media_session {
+            return;
+        }
+        if muted && webview.media_playing {
+            webview.paused_for_mute = true;
+            self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                MediaSessionActionType::Pause,
+            ));
+        } else if !muted && std::mem::take(&mut webview.paused_for_mute) {
+            self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                MediaSessionActionType::Play,
+            ));
+        }
+    }
+
+    /// Throttle the background tabs while saving power, pausing their media, and unthrottle the
+    /// others.
+    fn throttle_background_tabs(&mut self) {
+        let power_saving = self.power_save.enabled();
+        let active_id = self.tabs.active_id();
+        let playing = self
+            .tabs
+            .iter()
+            .filter(|webview| webview.media_playing)
+            .count();
+        for webview in self.tabs.iter_mut() {
+            let throttled = power_saving && Some(webview.id) != active_id;
+            if webview.throttled == throttled {
+                continue;
+            }
+            webview.throttled = throttled;
+            self.event_queue
+                .push(EmbedderEvent::SetWebViewThrottled(webview.id, throttled));
+            // Media session actions go to the most recently active media session, which can
+            // only be known to be this tab's if no other is playing.
+            if throttled && webview.media_playing && playing == 1 {
+                self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                    MediaSessionActionType::Pause,
+                ));
+            }
+        }
+    }
+
+    /// The webview of the active tab, which is the one receiving input.
     pub fn focused_webview_id(&self) -> Option<WebViewId> {
-        self.focused_webview_id
+        self.tabs.active_id()
+    }
+
+    pub fn current_url(&self) -> Option<&ServoUrl> {
+        self.tabs.active().and_then(|webview| webview.url.as_ref())
     }
 
+    /// The location of the active tab, as shown in the location bar.
     pub fn current_url_string(&self) -> Option<&str> {
-        self.current_url_string.as_deref()
+        let webview = self.tabs.active()?;
+        let url = webview.url.as_ref()?;
+        if self.new_tab_page.is(url) {
+            return Some(NEW_TAB_LOCATION);
+        }
+        match webview.crashed_url {
+            Some(ref crashed_url) if self.crash_pages.contains(url) => Some(crashed_url.as_str()),
+            _ => Some(url.as_str()),
+        }
+    }
+
+    /// Whether the page made the window fullscreen, hiding the tab strip and the toolbar.
+    pub fn content_fullscreen(&self) -> bool {
+        self.window.get_fullscreen()
+    }
+
+    /// How far a trackpad swipe has gone towards going back (1) or forward (-1), while there is
+    /// one and the active tab can go that way.
+    pub fn swipe_progress(&self) -> Option<f32> {
+        let webview = self.tabs.active()?;
+        self.window.swipe_progress().filter(|&progress| {
+            if progress > 0.0 {
+                webview.can_go_back()
+            } else {
+                webview.can_go_forward()
+            }
+        })
     }
 
     pub fn load_status(&self) -> LoadStatus {
-        self.load_status
+        self.tabs
+            .active()
+            .map_or(LoadStatus::LoadComplete, |webview| webview.load_status)
+    }
+
+    /// The device being emulated, if any.
+    pub fn emulation(&self) -> Option<Emulation> {
+        self.window.emulation()
+    }
+
+    /// Start or stop emulating a device, for all tabs.
+    pub fn set_emulation(&mut self, emulation: Option<Emulation>) {
+        self.window.set_emulation(emulation);
+    }
+
+    /// The device pixel ratio forced with `--device-pixel-ratio` or since, if any.
+    pub fn device_pixel_ratio_override(&self) -> Option<f32> {
+        self.window
+            .device_pixel_ratio_override()
+            .map(|ratio| ratio.get())
+    }
+
+    /// The device pixel ratio of the screen, which is used unless it is overridden.
+    pub fn device_hidpi_factor(&self) -> f32 {
+        self.window.device_hidpi_factor().get()
+    }
+
+    /// Force the device pixel ratio for all tabs, or stop if None. Pages are repainted at the
+    /// new ratio without being reloaded, but a device being emulated still takes precedence.
+    pub fn set_device_pixel_ratio_override(&mut self, ratio: Option<f32>) {
+        self.window.set_device_pixel_ratio_override(ratio);
+    }
+
+    /// The zoom factor of the active tab.
+    pub fn page_zoom(&self) -> f32 {
+        self.tabs.active().map_or(1.0, |webview| webview.page_zoom)
+    }
+
+    /// Set the zoom factor of the active tab, and remember it for the origin of its page.
+    pub fn set_page_zoom(&mut self, factor: f32) {
+        let Some(webview) = self.tabs.active_mut() else {
+            return;
+        };
+        webview.page_zoom = if is_default_zoom(factor) {
+            1.0
+        } else {
+            factor.clamp(MIN_PAGE_ZOOM, MAX_PAGE_ZOOM)
+        };
+        if let Some(ref url) = webview.url {
+            self.page_zooms.set(url, webview.page_zoom);
+        }
+        self.apply_page_zoom();
+    }
+
+    /// The zoom factor remembered for pages with the same origin as `url`.
+    pub fn site_zoom(&self, url: &ServoUrl) -> f32 {
+        self.page_zooms.get(url)
+    }
+
+    /// Zoom the pages with the same origin as `url` back to 100%, in every tab.
+    pub fn reset_site_zoom(&mut self, url: &ServoUrl) {
+        self.page_zooms.set(url, 1.0);
+        let origin = url.origin();
+        for webview in self.tabs.iter_mut() {
+            if webview
+                .url
+                .as_ref()
+                .is_some_and(|url| url.origin() == origin)
+            {
+                webview.page_zoom = 1.0;
+            }
+        }
+        self.apply_page_zoom();
+    }
+
+    /// Whether pages with the same origin as `url` get their mobile site, and whether that was
+    /// chosen rather than being the default.
+    pub fn site_mobile(&self, url: &ServoUrl) -> (bool, bool) {
+        (self.mobile_sites.get(url), self.mobile_sites.is_chosen(url))
+    }
+
+    /// Go back to the default site for pages with the same origin as `url`, reloading the tabs
+    /// that showed the other one.
+    pub fn reset_mobile_site(&mut self, url: &ServoUrl) {
+        self.mobile_sites.reset(url);
+        self.mobile_sites.save();
+        let origin = url.origin();
+        let mobile = self.mobile_sites.get(url);
+        for webview in self.tabs.iter_mut() {
+            if webview.mobile != mobile &&
+                webview
+                    .url
+                    .as_ref()
+                    .is_some_and(|url| url.origin() == origin)
+            {
+                webview.mobile = mobile;
+                self.event_queue.push(EmbedderEvent::Reload(webview.id));
+            }
+        }
+    }
+
+    /// Switch the given tab between the mobile and the desktop site, remember that for the origin
+    /// of its page, and reload it.
+    pub fn toggle_mobile_site(&mut self, webview_id: WebViewId) {
+        let Some(webview) = self.tabs.get_mut(webview_id) else {
+            return;
+        };
+        webview.mobile = !webview.mobile;
+        if let Some(ref url) = webview.url {
+            self.mobile_sites.set(url, webview.mobile);
+            self.mobile_sites.save();
+        }
+        self.event_queue.push(EmbedderEvent::Reload(webview_id));
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.set_page_zoom(zoom_in_level(self.page_zoom()));
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.set_page_zoom(zoom_out_level(self.page_zoom()));
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.set_page_zoom(1.0);
+    }
+
+    /// Reload the active tab. A hard reload also fetches the page and all of its subresources
+    /// from the network again, rather than from the HTTP cache. Servo can't bypass the cache for
+    /// a single load, so a hard reload clears all of it, for every site, once the user agrees.
+    ///
+    /// There is no way to stop a load in progress: Servo has no API for it yet, so the reload
+    /// button doesn't turn into a stop button while loading, and Escape doesn't stop the load.
+    pub fn reload(&mut self, hard: bool) {
+        let Some(id) = self.tabs.active_id() else {
+            return;
+        };
+        if hard {
+            let answer = MessageDialog::new()
+                .set_title("Reload without the cache")
+                .set_description(
+                    "Reloading without the cache clears all of the cache, for every site, not \
+                     only for this page. Clear the cache and reload?",
+                )
+                .set_buttons(MessageButtons::OkCancel)
+                .show();
+            if answer != MessageDialogResult::Ok {
+                return;
+            }
+            self.event_queue.push(EmbedderEvent::ClearCache);
+        }
+        // Reloading the crash page loads the page that crashed.
+        if let Some(webview) = self.tabs.active() {
+            if let (Some(url), Some(crashed_url)) = (&webview.url, &webview.crashed_url) {
+                if self.crash_pages.contains(url) {
+                    self.event_queue
+                        .push(EmbedderEvent::LoadUrl(id, crashed_url.clone()));
+                    return;
+                }
+            }
+        }
+        self.event_queue.push(EmbedderEvent::Reload(id));
+    }
+
+    /// Interpret input from the location bar, as a keyword search if it starts with the keyword
+    /// of a search engine and is not a URL.
+    pub fn location_to_url(&self, input: &str) -> Option<ServoUrl> {
+        if fix_up_url(input).is_none() {
+            if let Some(url) = self.keyword_searches.search(input) {
+                return Some(url);
+            }
+        }
+        location_bar_input_to_url(input)
+    }
+
+    /// The URL of the page to open in new tabs.
+    pub fn new_tab_url(&self) -> ServoUrl {
+        self.new_tab_url
+            .clone()
+            .or_else(|| self.new_tab_page_url())
+            .unwrap_or_else(home_url)
+    }
+
+    /// Write the new tab page, from the history and the bookmarks bar, returning its URL.
+    pub fn new_tab_page_url(&self) -> Option<ServoUrl> {
+        let recent = self
+            .history
+            .as_ref()
+            .map_or(vec![], |history| history.recent(MAX_RECENT_PAGES));
+        let bookmarks: Vec<_> = self
+            .bookmarks
+            .list(None)
+            .filter(|bookmark| !bookmark.is_folder())
+            .collect();
+        self.new_tab_page.write(&recent, &bookmarks)
+    }
+
+    /// Navigate the active tab to the home page.
+    pub fn go_home(&mut self) {
+        if let Some(id) = self.tabs.active_id() {
+            self.event_queue
+                .push(EmbedderEvent::LoadUrl(id, home_url()));
+        }
+    }
+
+    pub fn context_menu(&self) -> Option<&ContextMenu> {
+        self.context_menu.as_ref()
+    }
+
+    /// Tell the page which of its context menu items was chosen, if any.
+    pub fn close_context_menu(&mut self, result: ContextMenuResult) {
+        if let Some(context_menu) = self.context_menu.take() {
+            if let Err(error) = context_menu.sender.send(result) {
+                warn!("Failed to send ShowContextMenu response: {error}");
+            }
+        }
     }
 
+    pub fn copy_current_url(&mut self) {
+        let Some(url) = self.current_url_string().map(str::to_owned) else {
+            return;
+        };
+        if let Some(ref mut clipboard) = self.clipboard {
+            if let Err(e) = clipboard.set_text(url) {
+                warn!("Error setting clipboard contents ({})", e);
+            }
+        }
+    }
+
+    /// Copy a `curl` command that requests the page of the active tab, if it was loaded over HTTP.
+    pub fn copy_as_curl(&mut self) {
+        let Some(url) = self
+            .current_url()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+        else {
+            return;
+        };
+        let command = curl::command(url);
+        if let Some(ref mut clipboard) = self.clipboard {
+            if let Err(e) = clipboard.set_text(command) {
+                warn!("Error setting clipboard contents ({})", e);
+            }
+        }
+    }
+
+    /// Open a new tab with the source of `url`, once it has been fetched.
+    pub fn view_source(&mut self, url: ServoUrl) {
+        self.view_source.open(url);
+    }
+
+    /// Whether reader mode can be entered in the active tab (false) or left (true), or None if
+    /// neither, like for pages that can't be fetched again.
+    pub fn reader_mode(&self) -> Option<bool> {
+        let webview = self.tabs.active()?;
+        if self.reader_tabs.contains_key(&webview.id) {
+            return Some(true);
+        }
+        let url = webview.url.as_ref()?;
+        matches!(url.scheme(), "http" | "https" | "file").then_some(false)
+    }
+
+    /// Enter reader mode in a new tab, once the page of the active tab has been fetched again,
+    /// or leave it, going back to the tab of the original page.
+    pub fn toggle_reader_mode(&mut self) {
+        let Some(webview) = self.tabs.active() else {
+            return;
+        };
+        let webview_id = webview.id;
+        if let Some(&original_id) = self.reader_tabs.get(&webview_id) {
+            self.event_queue.extend(self.tabs.activate(original_id));
+            self.event_queue.extend(self.tabs.close_tab(webview_id));
+        } else if let Some(url) = webview.url.clone() {
+            self.reader.open(url, webview_id);
+        }
+    }
+
+    pub fn response_headers(&self) -> &ResponseHeaders {
+        &self.response_headers
+    }
+
+    pub fn downloads(&self) -> &Downloads {
+        &self.downloads
+    }
+
+    pub fn downloads_mut(&mut self) -> &mut Downloads {
+        &mut self.downloads
+    }
+
+    /// Start or stop fetching the response headers of the page in the active tab, for the
+    /// response headers window.
+    pub fn inspect_response_headers(&mut self, inspect: bool) {
+        self.inspecting_response_headers = inspect;
+        if inspect {
+            self.inspect_active_response_headers();
+        } else {
+            self.response_headers.clear();
+        }
+    }
+
+    /// Copy the status line and headers of the final response shown in the response headers
+    /// window.
+    pub fn copy_response_headers(&mut self) {
+        let Some(Ok(responses)) = self.response_headers.responses() else {
+            return;
+        };
+        let Some(text) = responses.last().map(Response::to_text) else {
+            return;
+        };
+        if let Some(ref mut clipboard) = self.clipboard {
+            if let Err(e) = clipboard.set_text(text) {
+                warn!("Error setting clipboard contents ({})", e);
+            }
+        }
+    }
+
+    /// Fetch the headers of the page in the active tab once it has loaded, if the response
+    /// headers window is open.
+    fn inspect_active_response_headers(&mut self) {
+        if !self.inspecting_response_headers {
+            return;
+        }
+        let Some(webview) = self.tabs.active() else {
+            return;
+        };
+        if let (LoadStatus::LoadComplete, Some(url)) = (webview.load_status, &webview.url) {
+            self.response_headers.inspect(url);
+        }
+    }
+
+    /// Open files dropped onto the window: the first in the given tab, or the active one, and
+    /// any others in new tabs.
+    ///
+    /// TODO: Servo has no API to hand dropped files to an `<input type=file>` under the pointer,
+    /// nor to start a drag of a link out of the page, and winit only reports dropped files, not
+    /// dropped URLs or text.
+    pub fn open_dropped_files(&mut self, webview_id: Option<WebViewId>, paths: Vec<PathBuf>) {
+        let mut target = webview_id.or(self.tabs.active_id());
+        for path in paths {
+            let Ok(url) = ServoUrl::from_file_path(&path) else {
+                warn!("Not opening dropped file {}", path.display());
+                continue;
+            };
+            match target.take() {
+                Some(id) => {
+                    self.event_queue.push(EmbedderEvent::LoadUrl(id, url));
+                    self.event_queue.extend(self.tabs.activate(id));
+                },
+                None => self.event_queue.push(self.tabs.new_tab(url)),
+            }
+        }
+    }
+
+    /// Write the zoom factor of each origin to disk, unless this is a private window.
+    pub fn save_page_zooms(&mut self) {
+        self.page_zooms.save();
+    }
+
+    /// Apply the zoom factor of the active tab to the compositor.
+    ///
+    /// The compositor has a single zoom factor for all webviews, so this has to be done again
+    /// whenever another tab is activated. It also only knows how to zoom relative to the current
+    /// zoom, so reset the zoom first, to avoid accumulating rounding errors.
+    fn apply_page_zoom(&mut self) {
+        let page_zoom = self.page_zoom();
+        self.event_queue.push(EmbedderEvent::ResetZoom);
+        if !is_default_zoom(page_zoom) {
+            self.event_queue.push(EmbedderEvent::Zoom(page_zoom));
+        }
+        self.page_zoom_changed = true;
+    }
+
+    /// The link that Servo last reported as hovered, unless it runs a script.
+    ///
+    /// Servo doesn't say what started a navigation, so clicks that open links in a new tab or
+    /// download them act on this link, and must not reach the page, or it would follow the link
+    /// in the tab it is in as well.
+    fn hovered_link(&self) -> Option<ServoUrl> {
+        let url = ServoUrl::parse(self.status_text.as_ref()?).ok()?;
+        (url.scheme() != "javascript").then_some(url)
+    }
+
+    /// If clicking with `button` and the modifiers held opens links in a new tab, and a link is
+    /// hovered, return the event that opens the link in one. Ctrl-click (Cmd-click on macOS) and
+    /// middle-click open it in the background, or in the foreground with Shift too, the other
+    /// way around with `--links-in-foreground`, and Shift-click opens it in the foreground.
+    fn open_hovered_link_in_new_tab(&mut self, button: MouseButton) -> Option<EmbedderEvent> {
+        let url = self.hovered_link()?;
+        let modifiers = self.window.modifiers_state();
+        let command = if cfg!(target_os = "macos") {
+            modifiers.super_key()
+        } else {
+            modifiers.control_key()
+        };
+        let foreground = match (button, command, modifiers.shift_key()) {
+            (MouseButton::Middle, _, shift) | (MouseButton::Left, true, shift) => {
+                shift != self.links_in_foreground
+            },
+            (MouseButton::Left, false, true) => true,
+            _ => return None,
+        };
+        Some(if foreground {
+            self.tabs.new_tab(url)
+        } else {
+            self.tabs.new_background_tab(url)
+        })
+    }
+
+    /// Zoom the visual viewport back out, after pinching zoomed it in.
+    ///
+    /// Unlike page zoom, pinching trackpads and touchscreens zooms into the page without laying
+    /// it out again, in the compositor, which keeps it between 1 and 10 times. The compositor
+    /// only zooms relative to the current zoom, and it tracks touchscreen pinches itself, so
+    /// zooming by 0 is the only way to reset it: it clamps that back to 1. Like page zoom, it
+    /// applies to all webviews, so it is reset on navigation and when another tab is activated.
+    ///
+    /// TODO: Zoom to fit the block that was double tapped, and bounce back when pinching past
+    /// the limits. Servo doesn't tell the embedder which element is where, and the compositor
+    /// stops at the limits.
+    fn reset_pinch_zoom(&mut self) {
+        self.event_queue.push(EmbedderEvent::PinchZoom(0.0));
+    }
+
+    /// Find up to `limit` pages in the history matching what was typed in the location bar.
+    pub fn search_history(&self, prefix: &str, limit: usize) -> Vec<HistoryEntry> {
+        self.history
+            .as_ref()
+            .map_or(vec![], |history| history.search(prefix, limit))
+    }
+
+    /// The favicon of the origin of `url`, if it has been fetched.
+    pub fn favicon(&self, url: &ServoUrl) -> Option<Arc<RgbaImage>> {
+        self.favicons.as_ref()?.get(url)
+    }
+
+    /// Changes whenever a favicon has been fetched.
+    pub fn favicons_generation(&self) -> u64 {
+        self.favicons.as_ref().map_or(0, Favicons::generation)
+    }
+
+    /// The size to decode favicons at, in device pixels.
+    fn favicon_size(&self) -> u32 {
+        (FAVICON_SIZE * self.window.hidpi_factor().get()).ceil() as u32
+    }
+
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    pub fn bookmarks_bar_visible(&self) -> bool {
+        self.bookmarks_bar_visible
+    }
+
+    /// Ask which folder to bookmark the page of the active tab in, then bookmark it.
+    pub fn bookmark_current_page(&mut self) {
+        let Some(webview) = self.tabs.active() else {
+            return;
+        };
+        let Some(url) = webview.url.as_ref().map(ServoUrl::to_string) else {
+            return;
+        };
+        let title = webview.display_title();
+        let Some(path) = tinyfiledialogs::input_box(
+            "Bookmark this page",
+            "Folder to add the bookmark to, like Reading/Later. Leave empty for the bookmarks bar.",
+            "",
+        ) else {
+            return;
+        };
+        let folder = self.bookmarks.folder_by_path(&path);
+        self.bookmarks.add(&url, &title, folder);
+        self.bookmarks.save();
+    }
+
+    /// Ask which folder to move a bookmark to, then move it.
+    pub fn move_bookmark(&mut self, id: BookmarkId) {
+        let Some(path) = tinyfiledialogs::input_box(
+            "Move bookmark",
+            "Folder to move the bookmark to, like Reading/Later. Leave empty for the bookmarks bar.",
+            "",
+        ) else {
+            return;
+        };
+        let folder = self.bookmarks.folder_by_path(&path);
+        if self.bookmarks.move_to(id, folder) {
+            self.bookmarks.save();
+        } else {
+            warn!("Can't move a bookmark folder into itself");
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, id: BookmarkId) {
+        self.bookmarks.remove(id);
+        self.bookmarks.save();
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    pub fn frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    /// Show or hide the frame timing overlay, logging the timing when hiding it, which is also
+    /// the only way to see it without the minibrowser.
+    fn toggle_frame_stats(&mut self) {
+        if self.frame_stats.overlay_visible() {
+            info!(
+                "Frame stats: {}",
+                self.frame_stats.summary().replace('\n', ", ")
+            );
+        }
+        self.frame_stats.toggle_overlay();
+    }
+
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    /// Let the origin of the active tab open popups without a click from now on, and reload it
+    /// so that it can open the popups that were blocked.
+    pub fn allow_popups(&mut self) {
+        let Some(webview) = self.tabs.active_mut() else {
+            return;
+        };
+        webview.blocked_popups = 0;
+        if let Some(url) = webview.url.clone() {
+            self.permissions.set(&url, POPUPS_FEATURE, true);
+            self.permissions.save();
+        }
+        self.reload(false);
+    }
+
+    /// How many popups were blocked in the page of the active tab.
+    pub fn blocked_popups(&self) -> usize {
+        self.tabs
+            .active()
+            .map_or(0, |webview| webview.blocked_popups)
+    }
+
+    /// Whether the page in `opener` may open a popup, that is a new webview with `window.open()`
+    /// or a link to a new window: only shortly after a click, unless its origin was allowed to.
+    ///
+    /// Servo doesn't say whether a script runs because of a click, nor what the popup would
+    /// load, so this goes by when the page was last clicked, and a blocked popup can't be opened
+    /// later, only by reloading the page. Without the minibrowser, there is nowhere to say that
+    /// a popup was blocked, so they are all allowed.
+    fn allow_popup(&self, opener: Option<WebViewId>) -> bool {
+        if !opts::get().minibrowser || opts::get().headless {
+            return true;
+        }
+        let clicked = self
+            .window
+            .last_user_activation()
+            .is_some_and(|activation| activation.elapsed() < USER_ACTIVATION_DURATION);
+        clicked ||
+            opener
+                .and_then(|id| self.tabs.get(id))
+                .and_then(|webview| webview.url.as_ref())
+                .and_then(|url| self.permissions.get(url, POPUPS_FEATURE))
+                .is_some_and(|decision| matches!(decision, PermissionRequest::Granted))
+    }
+
+    /// Whether the page in `webview_id` may play media it just started: always, unless
+    /// `--autoplay block`, and then only shortly after a click, once the user played its blocked
+    /// media, or if its origin was allowed to.
+    ///
+    /// Like for popups, Servo doesn't say whether media plays because of a click, so this goes
+    /// by when the page was last clicked. Without the minibrowser, there is nowhere to play
+    /// blocked media from, so it is all allowed.
+    fn allow_autoplay(&self, webview_id: WebViewId) -> bool {
+        if !self.block_autoplay || !opts::get().minibrowser || opts::get().headless {
+            return true;
+        }
+        let Some(webview) = self.tabs.get(webview_id) else {
+            return true;
+        };
+        let clicked = self
+            .window
+            .last_user_activation()
+            .is_some_and(|activation| activation.elapsed() < USER_ACTIVATION_DURATION);
+        clicked ||
+            webview.autoplay_allowed ||
+            webview
+                .url
+                .as_ref()
+                .and_then(|url| self.permissions.get(url, AUTOPLAY_FEATURE))
+                .is_some_and(|decision| matches!(decision, PermissionRequest::Granted))
+    }
+
+    /// Whether the page of the active tab had media paused with `--autoplay block`.
+    pub fn autoplay_blocked(&self) -> bool {
+        self.tabs
+            .active()
+            .is_some_and(|webview| webview.autoplay_blocked)
+    }
+
+    /// Play the blocked media of the active tab, and let it play until the tab navigates, or
+    /// from now on if `always`, for every page with the same origin.
+    ///
+    /// Media session actions only reach the tab that last reported on its media session, so
+    /// if another has since, the media can only be played from the page.
+    pub fn play_blocked_media(&mut self, always: bool) {
+        let media_session_tab = self.media_session_tab;
+        let Some(webview) = self.tabs.active_mut() else {
+            return;
+        };
+        webview.autoplay_blocked = false;
+        webview.autoplay_allowed = true;
+        if media_session_tab == Some(webview.id) {
+            self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                MediaSessionActionType::Play,
+            ));
+        }
+        if always {
+            if let Some(url) = webview.url.clone() {
+                self.permissions.set(&url, AUTOPLAY_FEATURE, true);
+                self.permissions.save();
+            }
+        }
+    }
+
+    /// Forget whether `origin` was granted `feature`, so that it is asked again.
+    pub fn revoke_permission(&mut self, origin: &str, feature: &str) {
+        self.permissions.revoke(origin, feature);
+        self.permissions.save();
+    }
+
+    /// Ask for a bookmark file exported by another browser, then import it.
+    pub fn import_bookmarks(&mut self) {
+        let Some(path) = FileDialog::new()
+            .set_title("Import bookmarks")
+            .add_filter("Bookmark files", &["html", "htm"])
+            .pick_file()
+        else {
+            return;
+        };
+        match fs::read_to_string(&path) {
+            Ok(html) => {
+                let count = self.bookmarks.import_html(&html);
+                info!("Imported {count} bookmarks from {}", path.display());
+                self.bookmarks.save();
+            },
+            Err(error) => warn!(
+                "Failed to import bookmarks from {}: {error}",
+                path.display()
+            ),
+        }
+    }
+
+    /// Ask where to save the bookmarks, then export them for other browsers to import.
+    pub fn export_bookmarks(&self) {
+        let Some(path) = FileDialog::new()
+            .set_title("Export bookmarks")
+            .set_file_name("bookmarks.html")
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(error) = fs::write(&path, self.bookmarks.export_html()) {
+            warn!("Failed to export bookmarks to {}: {error}", path.display());
+        }
+    }
+
+    /// Ask where to save the current page, and whether to save its resources too, then save it.
+    pub fn save_current_page(&self) {
+        let Some(url) = self.current_url().cloned() else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .set_title("Save page as")
+            .set_file_name("page.html")
+            .add_filter("Web pages", &["html", "htm"])
+            .save_file()
+        else {
+            return;
+        };
+        let answer = MessageDialog::new()
+            .set_title("Save page as")
+            .set_description(
+                "Also save the images, styles and scripts of the page?\n\nThe page is \
+                 downloaded again without your cookies, so it is saved as it is before scripts \
+                 change it, and as a logged out user would see it.",
+            )
+            .set_buttons(MessageButtons::YesNo)
+            .show();
+        let complete = answer == MessageDialogResult::Yes;
+        save_page(url, path, complete);
+    }
+
+    /// Ask how much of the history to clear, and whether to clear the cache, then clear them.
+    ///
+    /// The cache is cleared all at once, since Servo doesn't say when its entries were stored.
+    /// Cookies and local storage are not cleared, and can't be listed: Servo gives the embedder
+    /// no access to its cookie jar or its storage, so the dialog says so rather than offering
+    /// them.
+    fn prompt_clear_history(&mut self) {
+        let Some(input) = tinyfiledialogs::input_box(
+            "Clear browsing data",
+            "Clear the history of the last how many hours? Leave empty to clear all of it.\n\n\
+             Cookies and site data can't be cleared from here.",
+            "",
+        ) else {
+            return;
+        };
+        let since = match input.trim() {
+            "" => None,
+            hours => match hours.parse::<f64>() {
+                Ok(hours) if hours >= 0.0 => {
+                    Some(SystemTime::now() - Duration::from_secs_f64(hours * 3600.0))
+                },
+                _ => {
+                    warn!("Not clearing history, {input:?} is not a number of hours");
+                    return;
+                },
+            },
+        };
+        if let Some(ref history) = self.history {
+            match since {
+                Some(since) => history.clear_history(since..),
+                None => history.clear_history(..),
+            }
+        }
+        let clear_cache = matches!(
+            tinyfiledialogs::message_box_yes_no(
+                "Clear browsing data",
+                "Clear the cache too? All of it is cleared, whatever the time range.",
+                MessageBoxIcon::Question,
+                YesNo::No,
+            ),
+            YesNo::Yes
+        );
+        if clear_cache {
+            self.event_queue.push(EmbedderEvent::ClearCache);
+        }
+    }
+
+    /// The destination of the hovered link, or else where the active tab is navigating to.
     pub fn status_text(&self) -> Option<String> {
-        self.status_text.clone()
+        self.status_text.clone().or_else(|| {
+            let url = self.tabs.active()?.navigation_url.as_ref()?;
+            Some(format!("Loading {url}"))
+        })
     }
 
     pub fn get_events(&mut self) -> Vec<EmbedderEvent> {
@@ -154,12 +1328,47 @@ where
     }
 
     pub fn handle_window_events(&mut self, events: Vec<EmbedderEvent>) {
+        if let Some(direction) = self.window.take_swipe() {
+            let can_go = |webview: &&WebView| match direction {
+                TraversalDirection::Back(_) => webview.can_go_back(),
+                TraversalDirection::Forward(_) => webview.can_go_forward(),
+            };
+            if let Some(webview) = self.tabs.active().filter(can_go) {
+                let event = EmbedderEvent::Navigation(webview.id, direction);
+                self.event_queue.push(event);
+            }
+        }
         for event in events {
             trace_embedder_event!(event, "{event:?}");
             match event {
                 EmbedderEvent::Keyboard(key_event) => {
                     self.handle_key_from_window(key_event);
                 },
+                EmbedderEvent::MouseWindowEventClass(MouseWindowEvent::Click(button, point)) => {
+                    // Alt-click (Option-click on macOS) downloads the link.
+                    if button == MouseButton::Left && self.window.modifiers_state().alt_key() {
+                        if let Some(url) = self.hovered_link() {
+                            self.downloads.start(url);
+                            continue;
+                        }
+                    }
+                    match self.open_hovered_link_in_new_tab(button) {
+                        Some(event) => self.event_queue.push(event),
+                        // Servo would follow a middle clicked link like a left clicked one.
+                        None if button == MouseButton::Middle => {},
+                        None => self.event_queue.push(EmbedderEvent::MouseWindowEventClass(
+                            MouseWindowEvent::Click(button, point),
+                        )),
+                    }
+                },
+                EmbedderEvent::WindowResize => {
+                    if self.window.take_fullscreen_exited() {
+                        if let Some(id) = self.tabs.active_id() {
+                            self.event_queue.push(EmbedderEvent::ExitFullScreen(id));
+                        }
+                    }
+                    self.event_queue.push(EmbedderEvent::WindowResize);
+                },
                 event => {
                     self.event_queue.push(event);
                 },
@@ -367,17 +1576,33 @@ where
         self.shutdown_requested
     }
 
+    /// Returns true once after the screenshot shortcut was pressed.
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_requested)
+    }
+
     /// Handle key events before sending them to Servo.
     fn handle_key_from_window(&mut self, key_event: KeyboardEvent) {
-        ShortcutMatcher::from_event(key_event.clone())
-            .shortcut(CMD_OR_CONTROL, 'R', || {
-                if let Some(id) = self.focused_webview_id {
-                    self.event_queue.push(EmbedderEvent::Reload(id));
-                }
-            })
-            .shortcut(CMD_OR_CONTROL, 'L', || {
+        // Shortcuts only match presses. Releases go to the page like any other key, so that the
+        // page gets the release of a press that a shortcut passes on, like Escape outside of
+        // fullscreen.
+        match self.shortcuts.action(&key_event) {
+            Some(action)
+                if key_event.state == KeyState::Down && !action.is_handled_after_page() =>
+            {
+                self.run_shortcut(action, key_event)
+            },
+            _ => self.platform_handle_key(key_event),
+        }
+    }
+
+    fn run_shortcut(&mut self, action: Action, key_event: KeyboardEvent) {
+        match action {
+            Action::Reload => self.reload(false),
+            Action::HardReload => self.reload(true),
+            Action::OpenLocation => {
                 if !opts::get().minibrowser {
-                    let url: String = if let Some(ref current_url) = self.current_url {
+                    let url: String = if let Some(current_url) = self.current_url() {
                         current_url.to_string()
                     } else {
                         String::from("")
@@ -385,18 +1610,53 @@ where
                     let title = "URL or search query";
                     let input = tinyfiledialogs::input_box(title, title, &tiny_dialog_escape(&url));
                     if let Some(input) = input {
-                        if let Some(url) = location_bar_input_to_url(&input) {
-                            if let Some(id) = self.focused_webview_id {
+                        if let Some(url) = self.location_to_url(&input) {
+                            if let Some(id) = self.tabs.active_id() {
                                 self.event_queue.push(EmbedderEvent::LoadUrl(id, url));
                             }
                         }
                     }
                 }
-            })
-            .shortcut(CMD_OR_CONTROL, 'Q', || {
+            },
+            Action::Quit => {
                 self.event_queue.push(EmbedderEvent::Quit);
-            })
-            .shortcut(CMD_OR_CONTROL, 'P', || {
+            },
+            Action::NewTab => {
+                let url = self.new_tab_url();
+                self.event_queue.push(self.tabs.new_tab(url));
+            },
+            Action::Home => self.go_home(),
+            Action::CloseTab => {
+                if let Some(event) = self.tabs.active_id().and_then(|id| self.tabs.close_tab(id)) {
+                    self.event_queue.push(event);
+                }
+            },
+            Action::NextTab => {
+                if let Some(event) = self.tabs.next_tab() {
+                    self.event_queue.push(event);
+                }
+            },
+            Action::PrevTab => {
+                if let Some(event) = self.tabs.prev_tab() {
+                    self.event_queue.push(event);
+                }
+            },
+            Action::PrivateWindow => open_private_window(),
+            Action::ClearHistory => self.prompt_clear_history(),
+            Action::Screenshot => {
+                self.screenshot_requested = true;
+            },
+            Action::Bookmark => self.bookmark_current_page(),
+            Action::SavePage => self.save_current_page(),
+            Action::ViewSource => {
+                if let Some(url) = self.current_url().cloned() {
+                    self.view_source(url);
+                }
+            },
+            Action::BookmarksBar => {
+                self.bookmarks_bar_visible = !self.bookmarks_bar_visible;
+            },
+            Action::SamplingProfiler => {
                 let rate = env::var("SAMPLING_RATE")
                     .ok()
                     .and_then(|s| s.parse().ok())
@@ -409,54 +1669,66 @@ where
                     Duration::from_millis(rate),
                     Duration::from_secs(duration),
                 ));
-            })
-            .shortcut(Modifiers::CONTROL, Key::F9, || {
-                self.event_queue.push(EmbedderEvent::CaptureWebRender)
-            })
-            .shortcut(Modifiers::CONTROL, Key::F10, || {
+            },
+            Action::CaptureWebRender => self.event_queue.push(EmbedderEvent::CaptureWebRender),
+            Action::RenderTargetDebug => {
                 self.event_queue.push(EmbedderEvent::ToggleWebRenderDebug(
                     WebRenderDebugOption::RenderTargetDebug,
                 ));
-            })
-            .shortcut(Modifiers::CONTROL, Key::F11, || {
+            },
+            Action::TextureCacheDebug => {
                 self.event_queue.push(EmbedderEvent::ToggleWebRenderDebug(
                     WebRenderDebugOption::TextureCacheDebug,
                 ));
-            })
-            .shortcut(Modifiers::CONTROL, Key::F12, || {
+            },
+            Action::Fullscreen => {
+                let state = !self.window.get_browser_fullscreen();
+                self.window.set_browser_fullscreen(state);
+            },
+            Action::FrameStats => self.toggle_frame_stats(),
+            Action::WebRenderProfiler => {
                 self.event_queue.push(EmbedderEvent::ToggleWebRenderDebug(
                     WebRenderDebugOption::Profiler,
                 ));
-            })
-            .shortcut(CMD_OR_ALT, Key::ArrowRight, || {
-                if let Some(id) = self.focused_webview_id {
-                    let event = EmbedderEvent::Navigation(id, TraversalDirection::Forward(1));
+            },
+            Action::Forward => {
+                if let Some(webview) = self
+                    .tabs
+                    .active()
+                    .filter(|webview| webview.can_go_forward())
+                {
+                    let event =
+                        EmbedderEvent::Navigation(webview.id, TraversalDirection::Forward(1));
                     self.event_queue.push(event);
                 }
-            })
-            .shortcut(CMD_OR_ALT, Key::ArrowLeft, || {
-                if let Some(id) = self.focused_webview_id {
-                    let event = EmbedderEvent::Navigation(id, TraversalDirection::Back(1));
+            },
+            Action::Back => {
+                if let Some(webview) = self.tabs.active().filter(|webview| webview.can_go_back()) {
+                    let event = EmbedderEvent::Navigation(webview.id, TraversalDirection::Back(1));
                     self.event_queue.push(event);
                 }
-            })
-            .shortcut(Modifiers::empty(), Key::Escape, || {
+            },
+            Action::ExitFullscreen => {
                 let state = self.window.get_fullscreen();
                 if state {
-                    if let Some(id) = self.focused_webview_id {
+                    if let Some(id) = self.tabs.active_id() {
                         let event = EmbedderEvent::ExitFullScreen(id);
                         self.event_queue.push(event);
                     }
                 } else {
-                    self.platform_handle_key(key_event.clone());
+                    self.platform_handle_key(key_event);
                 }
-            })
-            .otherwise(|| self.platform_handle_key(key_event));
+            },
+            Action::ZoomIn => self.zoom_in(),
+            Action::ZoomOut => self.zoom_out(),
+            Action::ResetZoom => self.reset_zoom(),
+            Action::MuteAll => self.toggle_mute_all(),
+        }
     }
 
     #[cfg(not(target_os = "windows"))]
     fn platform_handle_key(&mut self, key_event: KeyboardEvent) {
-        if let Some(id) = self.focused_webview_id {
+        if let Some(id) = self.tabs.active_id() {
             if let Some(event) = ShortcutMatcher::from_event(key_event.clone())
                 .shortcut(CMD_OR_CONTROL, '[', || {
                     EmbedderEvent::Navigation(id, TraversalDirection::Back(1))
@@ -476,19 +1748,13 @@ where
 
     /// Handle key events after they have been handled by Servo.
     fn handle_key_from_servo(&mut self, _: Option<WebViewId>, event: KeyboardEvent) {
+        if let Some(action) = self.shortcuts.action(&event) {
+            if action.is_handled_after_page() && event.state == KeyState::Down {
+                self.run_shortcut(action, event);
+            }
+            return;
+        }
         ShortcutMatcher::from_event(event)
-            .shortcut(CMD_OR_CONTROL, '=', || {
-                self.event_queue.push(EmbedderEvent::Zoom(1.1))
-            })
-            .shortcut(CMD_OR_CONTROL, '+', || {
-                self.event_queue.push(EmbedderEvent::Zoom(1.1))
-            })
-            .shortcut(CMD_OR_CONTROL, '-', || {
-                self.event_queue.push(EmbedderEvent::Zoom(1.0 / 1.1))
-            })
-            .shortcut(CMD_OR_CONTROL, '0', || {
-                self.event_queue.push(EmbedderEvent::ResetZoom)
-            })
             .shortcut(Modifiers::empty(), Key::PageDown, || {
                 let scroll_location = ScrollLocation::Delta(Vector2D::new(
                     0.0,
@@ -540,13 +1806,53 @@ where
         self.event_queue.push(event);
     }
 
+    /// Set the window title from the title of the active tab, or its host if it has no title.
+    fn update_window_title(&self) {
+        if let Some(webview) = self.tabs.active() {
+            let url = webview.pending_url.as_ref().or(webview.url.as_ref());
+            let page_title = match (&webview.title, url) {
+                (Some(title), _) if !title.trim().is_empty() => title.trim().to_owned(),
+                (_, Some(url)) => url
+                    .host_str()
+                    .map_or_else(|| url.to_string(), str::to_owned),
+                _ => String::from("Untitled"),
+            };
+            let page_title = match page_title.char_indices().nth(MAX_WINDOW_TITLE_CHARS) {
+                Some((end, _)) => format!("{}…", &page_title[..end]),
+                None => page_title,
+            };
+            let title = if self.private {
+                format!("{page_title} - Servo (Private)")
+            } else {
+                format!("{page_title} - Servo")
+            };
+            self.window.set_title(&title);
+        }
+    }
+
     /// Returns true if the caller needs to manually present a new frame.
     pub fn handle_servo_events(
         &mut self,
         events: Drain<'_, (Option<WebViewId>, EmbedderMsg)>,
     ) -> ServoEventResponse {
-        let mut need_present = self.load_status != LoadStatus::LoadComplete;
-        let mut need_update = false;
+        let mut need_present = self.load_status() != LoadStatus::LoadComplete;
+        let mut need_update = self.favicons.as_mut().is_some_and(Favicons::take_fetched);
+        need_update |= self.response_headers.take_fetched();
+        need_update |= self.downloads.take_progress();
+        for url in self.view_source.take_loaded() {
+            self.event_queue.push(self.tabs.new_tab(url));
+        }
+        for (url, original_id) in self.reader.take_loaded() {
+            let event = self.tabs.new_tab(url);
+            if let EmbedderEvent::NewWebView(_, reader_id) = event {
+                self.reader_tabs.insert(reader_id, original_id);
+            }
+            self.event_queue.push(event);
+        }
+        let mut load_started = false;
+        let mut load_complete = false;
+        let mut panic = None;
+        let favicon_size = self.favicon_size();
         for (webview_id, msg) in events {
             if let Some(webview_id) = webview_id {
                 trace_embedder_msg!(msg, "{webview_id} {msg:?}");
@@ -559,19 +1865,27 @@ where
                     need_update = true;
                 },
                 EmbedderMsg::ChangePageTitle(title) => {
-                    self.title = title;
-
-                    let fallback_title: String = if let Some(ref current_url) = self.current_url {
-                        current_url.to_string()
-                    } else {
-                        String::from("Untitled")
-                    };
-                    let title = match self.title {
-                        Some(ref title) if !title.is_empty() => &**title,
-                        _ => &fallback_title,
-                    };
-                    let title = format!("{} - Servo", title);
-                    self.window.set_title(&title);
+                    // Discarded tabs keep the title of the page they will load again.
+                    let webview = webview_id
+                        .and_then(|id| self.tabs.get_mut(id))
+                        .filter(|webview| !webview.is_discarded());
+                    if let Some(webview) = webview {
+                        // Servo doesn't tell the embedder that a load failed, but the error page
+                        // it shows instead starts with this title.
+                        if webview.load_status != LoadStatus::LoadComplete &&
+                            title.as_deref() == Some(NET_ERROR_PAGE_TITLE)
+                        {
+                            webview.load_failed = true;
+                        }
+                        webview.title = title;
+                        if let (Some(history), Some(url)) = (&self.history, &webview.url) {
+                            history.set_title(url, webview.title.as_deref());
+                        }
+                    }
+                    if webview_id == self.tabs.active_id() {
+                        self.update_window_title();
+                    }
+                    need_update = true;
                 },
                 EmbedderMsg::MoveTo(point) => {
                     self.window.set_position(point);
@@ -679,16 +1993,26 @@ where
                             .push(EmbedderEvent::SendError(webview_id, reason));
                     }
                 },
-                EmbedderMsg::AllowNavigationRequest(pipeline_id, _url) => {
-                    if let Some(_webview_id) = webview_id {
+                EmbedderMsg::AllowNavigationRequest(pipeline_id, url) => {
+                    if let Some(webview_id) = webview_id {
+                        if let Some(webview) = self.tabs.get_mut(webview_id) {
+                            webview.navigation_url = Some(url);
+                            need_update = true;
+                        }
                         self.event_queue
                             .push(EmbedderEvent::AllowNavigationResponse(pipeline_id, true));
                     }
                 },
                 EmbedderMsg::AllowOpeningWebView(response_chan) => {
-                    // Note: would be a place to handle pop-ups config.
-                    // see Step 7 of #the-rules-for-choosing-a-browsing-context-given-a-browsing-context-name
-                    if let Err(e) = response_chan.send(true) {
+                    // See Step 7 of #the-rules-for-choosing-a-browsing-context-given-a-browsing-context-name
+                    let allow = self.allow_popup(webview_id);
+                    if !allow {
+                        if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                            webview.blocked_popups += 1;
+                            need_update = true;
+                        }
+                    }
+                    if let Err(e) = response_chan.send(allow) {
                         warn!("Failed to send AllowOpeningWebView response: {}", e);
                     };
                 },
@@ -701,50 +2025,83 @@ where
                     let mut rect = self.window.get_coordinates().get_viewport().to_f32();
                     rect.min.y += toolbar * scale;
 
-                    self.webviews.insert(new_webview_id, WebView { rect });
-                    self.creation_order.push(new_webview_id);
-                    self.event_queue
-                        .push(EmbedderEvent::FocusWebView(new_webview_id));
+                    let restored = self.tabs.opened(WebView::new(new_webview_id, rect));
                     self.event_queue
                         .push(EmbedderEvent::MoveResizeWebView(new_webview_id, rect));
-                    self.event_queue
-                        .push(EmbedderEvent::RaiseWebViewToTop(new_webview_id, true));
+                    if restored {
+                        self.throttle_background_tabs();
+                    } else {
+                        self.event_queue
+                            .push(EmbedderEvent::FocusWebView(new_webview_id));
+                        self.event_queue
+                            .push(EmbedderEvent::RaiseWebViewToTop(new_webview_id, true));
+                    }
+                    need_update = true;
                 },
                 EmbedderMsg::WebViewClosed(webview_id) => {
-                    self.webviews.retain(|&id, _| id != webview_id);
-                    self.creation_order.retain(|&id| id != webview_id);
-                    self.focused_webview_id = None;
-                    if let Some(&newest_webview_id) = self.creation_order.last() {
-                        self.event_queue
-                            .push(EmbedderEvent::FocusWebView(newest_webview_id));
-                    } else {
+                    let mut next_webview_id = self.tabs.closed(webview_id);
+                    // Leaving reader mode goes back to the original page.
+                    if let Some(original_id) = self.reader_tabs.remove(&webview_id) {
+                        if next_webview_id.is_some() && self.tabs.get(original_id).is_some() {
+                            next_webview_id = Some(original_id);
+                        }
+                    }
+                    if self.tabs.is_empty() {
                         self.event_queue.push(EmbedderEvent::Quit);
+                    } else if let Some(next_webview_id) = next_webview_id {
+                        self.event_queue
+                            .push(EmbedderEvent::FocusWebView(next_webview_id));
                     }
+                    need_update = true;
                 },
                 EmbedderMsg::WebViewFocused(webview_id) => {
-                    self.focused_webview_id = Some(webview_id);
-                    // Show the most recently created webview and hide all others.
-                    // TODO: Stop doing this once we have full multiple webviews support
-                    self.event_queue
-                        .push(EmbedderEvent::ShowWebView(webview_id, true));
+                    if self.tabs.activated(webview_id) {
+                        // Show the active tab and hide all others, so only it gets painted.
+                        self.event_queue
+                            .push(EmbedderEvent::ShowWebView(webview_id, true));
+                        let pending_url = self
+                            .tabs
+                            .get_mut(webview_id)
+                            .and_then(|webview| webview.pending_url.take());
+                        if let Some(url) = pending_url {
+                            self.event_queue
+                                .push(EmbedderEvent::LoadUrl(webview_id, url));
+                        }
+                        self.apply_page_zoom();
+                        self.reset_pinch_zoom();
+                        self.update_window_title();
+                        self.throttle_background_tabs();
+                        need_update = true;
+                    }
                 },
                 EmbedderMsg::WebViewBlurred => {
-                    self.focused_webview_id = None;
+                    // The active tab stays active, so that it keeps getting painted and
+                    // keyboard shortcuts still apply to it.
                 },
                 EmbedderMsg::Keyboard(key_event) => {
                     self.handle_key_from_servo(webview_id, key_event);
                 },
+                // TODO: Servo only exchanges plain text with the clipboard, so there's no HTML
+                // flavor to paste, and it doesn't implement navigator.clipboard yet, which would
+                // need a permission prompt per origin.
                 EmbedderMsg::GetClipboardContents(sender) => {
-                    let contents = self
-                        .clipboard
-                        .as_mut()
-                        .and_then(|clipboard| clipboard.get_text().ok())
-                        .unwrap_or_else(|| {
-                            warn!("Error getting clipboard text. Returning empty string.");
-                            String::new()
+                    // Reading the clipboard can mean waiting for the app that owns it, and what
+                    // is pasted can be big, so don't hold up the event loop.
+                    let result = thread::Builder::new()
+                        .name("Clipboard".to_owned())
+                        .spawn(move || {
+                            let contents = Clipboard::new()
+                                .and_then(|mut clipboard| clipboard.get_text())
+                                .unwrap_or_else(|e| {
+                                    warn!("Error getting clipboard text ({e}). Returning empty string.");
+                                    String::new()
+                                });
+                            if let Err(e) = sender.send(contents) {
+                                warn!("Failed to send clipboard ({})", e);
+                            }
                         });
-                    if let Err(e) = sender.send(contents) {
-                        warn!("Failed to send clipboard ({})", e);
+                    if let Err(e) = result {
+                        warn!("Failed to spawn clipboard thread ({})", e);
                     }
                 },
                 EmbedderMsg::SetClipboardContents(text) => {
@@ -757,33 +2114,108 @@ where
                 EmbedderMsg::SetCursor(cursor) => {
                     self.window.set_cursor(cursor);
                 },
-                EmbedderMsg::NewFavicon(_url) => {
-                    // FIXME: show favicons in the UI somehow
+                EmbedderMsg::NewFavicon(url) => {
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        if let (Some(favicons), Some(page_url)) = (&mut self.favicons, &webview.url)
+                        {
+                            favicons.fetch(page_url, url.clone(), favicon_size);
+                        }
+                        webview.favicon_url = Some(url);
+                        need_update = true;
+                    }
                 },
                 EmbedderMsg::HeadParsed => {
-                    self.load_status = LoadStatus::HeadParsed;
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        webview.load_status = LoadStatus::HeadParsed;
+                    }
                     need_update = true;
                 },
                 EmbedderMsg::HistoryChanged(urls, current) => {
-                    self.current_url = Some(urls[current].clone());
-                    self.current_url_string = Some(urls[current].clone().into_string());
+                    // Servo adds entries for fragment navigations and pushState() itself, and
+                    // reports them here like any other.
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        let page_zoom = self.page_zooms.get(&urls[current]);
+                        webview.mobile = self.mobile_sites.get(&urls[current]);
+                        webview.url = Some(urls[current].clone());
+                        webview.session_history = urls;
+                        webview.session_history_index = current;
+                        if webview.page_zoom != page_zoom {
+                            webview.page_zoom = page_zoom;
+                            if webview_id == self.tabs.active_id() {
+                                self.apply_page_zoom();
+                            }
+                        }
+                    }
+                    // Pages without a title are named after their host.
+                    if webview_id == self.tabs.active_id() {
+                        self.update_window_title();
+                    }
                     need_update = true;
                 },
                 EmbedderMsg::SetFullscreenState(state) => {
                     self.window.set_fullscreen(state);
+                    need_update = true;
                 },
                 EmbedderMsg::LoadStart => {
-                    self.load_status = LoadStatus::LoadStart;
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        webview.load_status = LoadStatus::LoadStart;
+                        webview.favicon_url = None;
+                        webview.media_playing = false;
+                        webview.autoplay_blocked = false;
+                        webview.autoplay_allowed = false;
+                        webview.blocked_popups = 0;
+                        webview.load_failed = false;
+                    }
+                    if webview_id == self.tabs.active_id() {
+                        self.reset_pinch_zoom();
+                        // Fetch the headers again once loaded, even if it was a reload.
+                        self.response_headers.clear();
+                    }
                     need_update = true;
+                    load_started = true;
                 },
                 EmbedderMsg::LoadComplete => {
-                    self.load_status = LoadStatus::LoadComplete;
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        webview.load_status = LoadStatus::LoadComplete;
+                        webview.navigation_url = None;
+                        let url = webview.url.as_ref().filter(|url| {
+                            !self.new_tab_page.is(url) && !self.crash_pages.contains(url)
+                        });
+                        if let (Some(history), Some(url)) = (&self.history, url) {
+                            history.record_visit(url, webview.title.as_deref());
+                        }
+                        // Fall back to /favicon.ico if the page didn't link to a favicon.
+                        if let (Some(favicons), Some(url), None) =
+                            (&mut self.favicons, &webview.url, &webview.favicon_url)
+                        {
+                            favicons.fetch_default(url, favicon_size);
+                        }
+                    }
                     need_update = true;
+                    load_complete = true;
                 },
                 EmbedderMsg::Shutdown => {
                     self.shutdown_requested = true;
                 },
-                EmbedderMsg::Panic(_reason, _backtrace) => {},
+                EmbedderMsg::Panic(reason, _backtrace) => {
+                    if let Some(webview) = webview_id.and_then(|id| self.tabs.get_mut(id)) {
+                        // The load won't complete, so stop showing it as in progress.
+                        webview.load_status = LoadStatus::LoadComplete;
+                        let url = webview.navigation_url.take().or(webview.url.clone());
+                        log_crash(url.as_ref().filter(|_| !self.private), &reason);
+                        // Show our crash page, unless it is the crash page that crashed.
+                        let url = url.filter(|url| !self.crash_pages.contains(url));
+                        if let Some(url) = url {
+                            if let Some(crash_page) = self.crash_pages.write(&url, &reason) {
+                                self.event_queue
+                                    .push(EmbedderEvent::LoadUrl(webview.id, crash_page));
+                                webview.crashed_url = Some(url);
+                            }
+                        }
+                    }
+                    panic = Some(reason);
+                    need_update = true;
+                },
                 EmbedderMsg::GetSelectedBluetoothDevice(devices, sender) => {
                     let selected = platform_get_selected_devices(devices);
                     if let Err(e) = sender.send(selected) {
@@ -808,14 +2240,46 @@ where
                     };
                 },
                 EmbedderMsg::PromptPermission(prompt, sender) => {
-                    let permission_state = prompt_user(prompt);
+                    let feature = match prompt {
+                        PermissionPrompt::Request(ref name) |
+                        PermissionPrompt::Insecure(ref name) => feature_name(name),
+                    };
+                    let url = webview_id
+                        .and_then(|id| self.tabs.get(id))
+                        .and_then(|webview| webview.url.clone());
+                    let remembered = url
+                        .as_ref()
+                        .and_then(|url| self.permissions.get(url, &feature));
+                    let permission_state = match remembered {
+                        Some(permission_state) => permission_state,
+                        None => match (prompt_user(prompt), url) {
+                            (Some(permission_state), Some(url)) => {
+                                let granted =
+                                    matches!(permission_state, PermissionRequest::Granted);
+                                self.permissions.set(&url, &feature, granted);
+                                self.permissions.save();
+                                permission_state
+                            },
+                            (Some(permission_state), None) => permission_state,
+                            // Without a prompt, deny this time, but ask again next time.
+                            (None, _) => PermissionRequest::Denied,
+                        },
+                    };
                     let _ = sender.send(permission_state);
                 },
-                EmbedderMsg::ShowIME(_kind, _text, _multiline, _rect) => {
+                EmbedderMsg::ShowIME(_kind, _text, _multiline, rect) => {
                     debug!("ShowIME received");
+                    // Servo gives the rect of the focused field, relative to its webview, but
+                    // not of the caret within it.
+                    let origin = webview_id
+                        .and_then(|id| self.tabs.get(id))
+                        .map(|webview| webview.rect.min.to_i32().to_vector())
+                        .unwrap_or_default();
+                    self.window.set_ime_area(Some(rect.translate(origin)));
                 },
                 EmbedderMsg::HideIME => {
                     debug!("HideIME received");
+                    self.window.set_ime_area(None);
                 },
                 EmbedderMsg::ReportProfile(bytes) => {
                     let filename = env::var("PROFILE_OUTPUT").unwrap_or("samples.json".to_string());
@@ -824,16 +2288,57 @@ where
                         error!("Failed to store profile: {}", e);
                     }
                 },
-                EmbedderMsg::MediaSessionEvent(_) => {
+                EmbedderMsg::MediaSessionEvent(event) => {
                     debug!("MediaSessionEvent received");
                     // TODO(ferjm): MediaSession support for winit based browsers.
+                    self.media_session_tab = webview_id;
+                    if let (MediaSessionEvent::PlaybackStateChange(state), Some(webview_id)) =
+                        (event, webview_id)
+                    {
+                        let playing = matches!(state, MediaSessionPlaybackState::Playing);
+                        // Blocked media doesn't count as playing, since it is paused right away.
+                        let blocked = playing && !self.allow_autoplay(webview_id);
+                        if let Some(webview) = self.tabs.get_mut(webview_id) {
+                            webview.media_playing = playing && !blocked;
+                            if blocked {
+                                webview.autoplay_blocked = true;
+                                self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                                    MediaSessionActionType::Pause,
+                                ));
+                            } else if webview.media_playing && webview.muted {
+                                // Muted tabs are kept paused, see `set_muted`.
+                                webview.paused_for_mute = true;
+                                self.event_queue.push(EmbedderEvent::MediaSessionAction(
+                                    MediaSessionActionType::Pause,
+                                ));
+                            }
+                            need_update = true;
+                        }
+                    }
                 },
                 EmbedderMsg::OnDevtoolsStarted(port, _token) => match port {
-                    Ok(p) => info!("Devtools Server running on port {}", p),
+                    Ok(p) => {
+                        info!("Devtools Server running on port {}", p);
+                        // Printed even without logging, since it is what `--devtools` is for.
+                        println!(
+                            "DevTools listening on localhost:{p}. To connect, open about:debugging \
+                             in Firefox, add localhost:{p} as a network location and connect to it."
+                        );
+                    },
                     Err(()) => error!("Error running devtools server"),
                 },
-                EmbedderMsg::ShowContextMenu(sender, ..) => {
-                    let _ = sender.send(ContextMenuResult::Ignored);
+                EmbedderMsg::ShowContextMenu(sender, title, items) => {
+                    if opts::get().minibrowser && !opts::get().headless {
+                        self.close_context_menu(ContextMenuResult::Dismissed);
+                        self.context_menu = Some(ContextMenu {
+                            title,
+                            items,
+                            sender,
+                        });
+                        need_update = true;
+                    } else {
+                        let _ = sender.send(ContextMenuResult::Ignored);
+                    }
                 },
                 EmbedderMsg::ReadyToPresent(_webview_ids) => {
                     need_present = true;
@@ -865,17 +2370,42 @@ where
             }
         }
 
+        self.inspect_active_response_headers();
+
+        if need_update && !self.private {
+            crash_reporter::set_active_url(self.current_url_string().unwrap_or_default());
+        }
+
         ServoEventResponse {
             need_present,
-            need_update,
+            need_update: need_update || std::mem::take(&mut self.page_zoom_changed),
+            load_started,
+            load_complete,
+            panic,
         }
     }
 }
 
+/// The home page, from `--home` or the `shell.homepage` pref.
+fn home_url() -> ServoUrl {
+    get_default_url(None, env::current_dir().unwrap_or_default(), |_| false)
+}
+
+/// Open a private window, in a new process so that it gets its own, ephemeral, cookies and
+/// storage.
+fn open_private_window() {
+    let result = env::current_exe()
+        .and_then(|servoshell| process::Command::new(servoshell).arg("--private").spawn());
+    if let Err(error) = result {
+        error!("Failed to open a private window: {error}");
+    }
+}
+
+/// Ask the user whether to grant a permission, or return None if there is no way to ask.
 #[cfg(target_os = "linux")]
-fn prompt_user(prompt: PermissionPrompt) -> PermissionRequest {
+fn prompt_user(prompt: PermissionPrompt) -> Option<PermissionRequest> {
     if opts::get().headless {
-        return PermissionRequest::Denied;
+        return None;
     }
 
     let message = match prompt {
@@ -897,15 +2427,15 @@ fn prompt_user(prompt: PermissionPrompt) -> PermissionRequest {
         MessageBoxIcon::Question,
         YesNo::No,
     ) {
-        YesNo::Yes => PermissionRequest::Granted,
-        YesNo::No => PermissionRequest::Denied,
+        YesNo::Yes => Some(PermissionRequest::Granted),
+        YesNo::No => Some(PermissionRequest::Denied),
     }
 }
 
 #[cfg(not(target_os = "linux"))]
-fn prompt_user(_prompt: PermissionPrompt) -> PermissionRequest {
+fn prompt_user(_prompt: PermissionPrompt) -> Option<PermissionRequest> {
     // TODO popup only supported on linux
-    PermissionRequest::Denied
+    None
 }
 
 #[cfg(target_os = "linux")]
@@ -941,37 +2471,29 @@ fn platform_get_selected_devices(devices: Vec<String>) -> Option<String> {
     None
 }
 
+/// Show the native file chooser for an `<input type=file>`, returning None if it was cancelled.
+///
+/// The dialog is modal, and on macOS it has to be shown from the main thread. The patterns are
+/// the file extensions that Servo derived from the `accept` attribute.
+///
+/// TODO: Servo doesn't tell us about `webkitdirectory`, which would need `pick_folder`.
 fn get_selected_files(patterns: Vec<FilterPattern>, multiple_files: bool) -> Option<Vec<String>> {
-    let picker_name = if multiple_files {
-        "Pick files"
+    let mut dialog = FileDialog::new();
+    if !patterns.is_empty() {
+        let extensions: Vec<&str> = patterns.iter().map(|pattern| pattern.0.as_str()).collect();
+        dialog = dialog.add_filter("Accepted files", &extensions);
+    }
+    let files = if multiple_files {
+        dialog.set_title("Pick files").pick_files()?
     } else {
-        "Pick a file"
+        vec![dialog.set_title("Pick a file").pick_file()?]
     };
-    thread::Builder::new()
-        .name("FilePicker".to_owned())
-        .spawn(move || {
-            let mut filters = vec![];
-            for p in patterns {
-                let s = "*.".to_string() + &p.0;
-                filters.push(tiny_dialog_escape(&s))
-            }
-            let filter_ref = &(filters.iter().map(|s| s.as_str()).collect::<Vec<&str>>()[..]);
-            let filter_opt = if !filters.is_empty() {
-                Some((filter_ref, ""))
-            } else {
-                None
-            };
-
-            if multiple_files {
-                tinyfiledialogs::open_file_dialog_multi(picker_name, "", filter_opt)
-            } else {
-                let file = tinyfiledialogs::open_file_dialog(picker_name, "", filter_opt);
-                file.map(|x| vec![x])
-            }
-        })
-        .unwrap()
-        .join()
-        .expect("Thread spawning failed")
+    Some(
+        files
+            .into_iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+    )
 }
 
 // This is a mitigation for #25498, not a verified solution.
@@ -0,0 +1,84 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Saving the size and position of the window on shutdown, to open it there on the next launch.
+
+use std::path::PathBuf;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use winit::dpi::{LogicalPosition, LogicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::Window;
+
+use super::config_dir;
+
+/// Where the window was, in logical pixels, so that it opens at the same size on screens with
+/// another scale factor.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowGeometry {
+    /// The geometry of `window`, unless it is fullscreen, which is not worth reopening at.
+    pub fn of(window: &Window) -> Option<Self> {
+        if window.fullscreen().is_some() {
+            return None;
+        }
+        let scale_factor = window.scale_factor();
+        let position: LogicalPosition<f64> = window.outer_position().ok()?.to_logical(scale_factor);
+        let size: LogicalSize<f64> = window.inner_size().to_logical(scale_factor);
+        Some(Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+}
+
+/// Whether a window at `position` would show at least its top left corner on one of `monitors`,
+/// so that it can be dragged back into view.
+pub fn is_on_screen(
+    position: LogicalPosition<f64>,
+    mut monitors: impl Iterator<Item = MonitorHandle>,
+) -> bool {
+    // Enough of the title bar to grab.
+    const VISIBLE: f64 = 50.0;
+    monitors.any(|monitor| {
+        let scale_factor = monitor.scale_factor();
+        let origin: LogicalPosition<f64> = monitor.position().to_logical(scale_factor);
+        let size: LogicalSize<f64> = monitor.size().to_logical(scale_factor);
+        position.x + VISIBLE > origin.x &&
+            position.x < origin.x + size.width - VISIBLE &&
+            position.y >= origin.y &&
+            position.y < origin.y + size.height - VISIBLE
+    })
+}
+
+fn geometry_path() -> Option<PathBuf> {
+    config_dir::get().map(|dir| dir.join("window.json"))
+}
+
+/// Read the saved geometry, if there is one.
+pub fn load() -> Option<WindowGeometry> {
+    config_dir::read_json(&geometry_path()?)
+}
+
+/// Save the given geometry, to open the window with on the next launch.
+pub fn save(geometry: &WindowGeometry) {
+    let Some(path) = geometry_path() else {
+        return;
+    };
+    if let Err(error) = config_dir::write_json(&path, geometry) {
+        warn!(
+            "Failed to save window geometry to {}: {error}",
+            path.display()
+        );
+    }
+}
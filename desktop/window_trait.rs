@@ -5,14 +5,19 @@
 //! Definition of Window.
 //! Implemented by headless and headed windows.
 
+use std::time::Instant;
+
 use euclid::{Length, Scale};
 use servo::compositing::windowing::{EmbedderEvent, WindowMethods};
 use servo::config::opts;
 use servo::embedder_traits::Cursor;
+use servo::script_traits::TraversalDirection;
 use servo::servo_geometry::DeviceIndependentPixel;
 use servo::style_traits::DevicePixel;
-use servo::webrender_api::units::{DeviceIntPoint, DeviceIntSize};
+use servo::webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+use winit::keyboard::ModifiersState;
 
+use super::device_emulation::Emulation;
 use super::events_loop::WakerEvent;
 
 // This should vary by zoom level and maybe actual text size (focused or under cursor)
@@ -22,6 +27,9 @@ pub trait WindowPortsMethods: WindowMethods {
     fn get_events(&self) -> Vec<EmbedderEvent>;
     fn id(&self) -> winit::window::WindowId;
     fn hidpi_factor(&self) -> Scale<f32, DeviceIndependentPixel, DevicePixel> {
+        if let Some(emulation) = self.emulation() {
+            return Scale::new(emulation.device_pixel_ratio);
+        }
         self.device_pixel_ratio_override()
             .unwrap_or_else(|| match opts::get().output_file {
                 Some(_) => Scale::new(1.0),
@@ -32,6 +40,9 @@ pub trait WindowPortsMethods: WindowMethods {
     fn device_pixel_ratio_override(
         &self,
     ) -> Option<Scale<f32, DeviceIndependentPixel, DevicePixel>>;
+    /// Force the device pixel ratio instead of the screen's, or stop if None, queueing a resize
+    /// so that Servo repaints at the new ratio.
+    fn set_device_pixel_ratio_override(&self, ratio: Option<f32>);
     fn page_height(&self) -> f32;
     fn get_fullscreen(&self) -> bool;
     fn queue_embedder_events_for_winit_event(&self, event: winit::event::WindowEvent);
@@ -40,7 +51,42 @@ pub trait WindowPortsMethods: WindowMethods {
     fn request_inner_size(&self, size: DeviceIntSize) -> Option<DeviceIntSize>;
     fn set_position(&self, _point: DeviceIntPoint) {}
     fn set_fullscreen(&self, _state: bool) {}
+    /// Make the window fullscreen or not for the user, as F11 does, whether or not the page is.
+    fn set_browser_fullscreen(&self, _state: bool) {}
+    fn get_browser_fullscreen(&self) -> bool {
+        false
+    }
+    /// Whether the window manager took the window out of the fullscreen the page asked for, since
+    /// the last call.
+    fn take_fullscreen_exited(&self) -> bool {
+        false
+    }
+    /// How far a trackpad swipe has gone towards going back (1) or forward (-1), while there is
+    /// one.
+    fn swipe_progress(&self) -> Option<f32> {
+        None
+    }
+    /// Where a trackpad swipe that ended since the last call goes in history.
+    fn take_swipe(&self) -> Option<TraversalDirection> {
+        None
+    }
+    /// The modifier keys that are held down.
+    fn modifiers_state(&self) -> ModifiersState {
+        ModifiersState::empty()
+    }
+    /// When the page was last clicked, tapped or typed into, if ever.
+    fn last_user_activation(&self) -> Option<Instant> {
+        None
+    }
+    /// The device being emulated, if any, see [`super::device_emulation`].
+    fn emulation(&self) -> Option<Emulation> {
+        None
+    }
+    /// Start or stop emulating a device, queueing a resize so that Servo sees the new screen.
+    fn set_emulation(&self, _emulation: Option<Emulation>) {}
     fn set_cursor(&self, _cursor: Cursor) {}
+    /// Show the IME for the field at `area` in the window, or hide it if None.
+    fn set_ime_area(&self, _area: Option<DeviceIntRect>) {}
     fn new_glwindow(
         &self,
         events_loop: &winit::event_loop::EventLoopWindowTarget<WakerEvent>,
@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Page zoom factors, remembered per origin across restarts.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use log::warn;
+use servo::servo_url::ServoUrl;
+
+use super::config_dir;
+
+/// The zoom factors that zooming in and out goes through, like in other browsers, so that
+/// zooming in and back out again always gets back to the same factor.
+const PAGE_ZOOM_LEVELS: [f32; 17] = [
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
+
+pub const MIN_PAGE_ZOOM: f32 = PAGE_ZOOM_LEVELS[0];
+pub const MAX_PAGE_ZOOM: f32 = PAGE_ZOOM_LEVELS[PAGE_ZOOM_LEVELS.len() - 1];
+
+/// Zoom factors closer than this are the same, as they may have been rounded, like those saved
+/// by older versions, which multiplied the factor on every step.
+const PAGE_ZOOM_EPSILON: f32 = 0.001;
+
+/// The zoom level after zooming in from `factor`.
+pub fn zoom_in_level(factor: f32) -> f32 {
+    PAGE_ZOOM_LEVELS
+        .into_iter()
+        .find(|level| *level > factor + PAGE_ZOOM_EPSILON)
+        .unwrap_or(MAX_PAGE_ZOOM)
+}
+
+/// The zoom level after zooming out from `factor`.
+pub fn zoom_out_level(factor: f32) -> f32 {
+    PAGE_ZOOM_LEVELS
+        .into_iter()
+        .rev()
+        .find(|level| *level < factor - PAGE_ZOOM_EPSILON)
+        .unwrap_or(MIN_PAGE_ZOOM)
+}
+
+/// Whether `factor` is 100%, give or take rounding.
+pub fn is_default_zoom(factor: f32) -> bool {
+    (factor - 1.0).abs() < PAGE_ZOOM_EPSILON
+}
+
+/// The zoom factor of each origin that is not at 100%, kept in `zoom.json` in the config dir.
+#[derive(Default)]
+pub struct PageZooms {
+    path: Option<PathBuf>,
+    factors: HashMap<String, f32>,
+    dirty: bool,
+}
+
+impl PageZooms {
+    /// Read the saved zoom factors. Private windows show every page at 100% at first.
+    pub fn load(private: bool) -> Self {
+        let path = config_dir::get()
+            .filter(|_| !private)
+            .map(|dir| dir.join("zoom.json"));
+        let factors = path
+            .as_deref()
+            .and_then(config_dir::read_json)
+            .unwrap_or_default();
+        Self {
+            path,
+            factors,
+            dirty: false,
+        }
+    }
+
+    /// The zoom factor for pages with the same origin as `url`.
+    pub fn get(&self, url: &ServoUrl) -> f32 {
+        Self::key(url)
+            .and_then(|key| self.factors.get(&key).copied())
+            .unwrap_or(1.0)
+    }
+
+    /// Remember the zoom factor for pages with the same origin as `url`.
+    pub fn set(&mut self, url: &ServoUrl, factor: f32) {
+        let Some(key) = Self::key(url) else {
+            return;
+        };
+        if is_default_zoom(factor) {
+            self.dirty |= self.factors.remove(&key).is_some();
+        } else if self.factors.insert(key, factor) != Some(factor) {
+            self.dirty = true;
+        }
+    }
+
+    /// Write the zoom factors to disk, if they have changed since they were loaded.
+    pub fn save(&mut self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        if !self.dirty {
+            return;
+        }
+        let result = config_dir::write_json(path, &self.factors);
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(error) => warn!("Failed to save zoom factors to {}: {error}", path.display()),
+        }
+    }
+
+    /// Only tuple origins are remembered, since opaque origins like those of `file:` and
+    /// `data:` URLs have no serialization to tell them apart.
+    fn key(url: &ServoUrl) -> Option<String> {
+        let origin = url.origin();
+        origin.is_tuple().then(|| origin.ascii_serialization())
+    }
+}
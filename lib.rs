@@ -22,6 +22,9 @@ mod parser;
 mod prefs;
 #[cfg(not(any(target_os = "android", target_env = "ohos")))]
 mod resources;
+mod version;
+
+pub use version::servo_version;
 
 pub mod platform {
     #[cfg(target_os = "macos")]
@@ -51,11 +54,3 @@ pub fn main() {
 pub fn main() {
     println!("You shouldn't start /ports/servoshell/ on OpenHarmony.");
 }
-
-pub fn servo_version() -> String {
-    format!(
-        "Servo {}-{}",
-        env!("CARGO_PKG_VERSION"),
-        env!("VERGEN_GIT_SHA")
-    )
-}
@@ -65,20 +65,67 @@ pub fn get_default_url(
 /// If this is not a valid URL, try to "fix" it by adding a scheme or if all else fails,
 /// interpret the string as a search term.
 pub fn location_bar_input_to_url(request: &str) -> Option<ServoUrl> {
+    fix_up_url(request).or_else(|| search_url(&pref!(shell.searchpage), request.trim()))
+}
+
+/// The URL of a search for `query`, given the template of a search engine's URLs, where `%s`
+/// is the query.
+pub fn search_url(template: &str, query: &str) -> Option<ServoUrl> {
+    let query: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    ServoUrl::parse(&template.replace("%s", &query)).ok()
+}
+
+/// The URL that an input means, unless it is a search term.
+pub fn fix_up_url(request: &str) -> Option<ServoUrl> {
     let request = request.trim();
-    ServoUrl::parse(request)
-        .ok()
-        .or_else(|| {
-            if request.starts_with('/') {
-                ServoUrl::parse(&format!("file://{}", request)).ok()
-            } else if request.contains('/') || is_reg_domain(request) {
-                ServoUrl::parse(&format!("https://{}", request)).ok()
-            } else {
-                None
-            }
-        })
-        .or_else(|| {
-            let url = pref!(shell.searchpage).replace("%s", request);
-            ServoUrl::parse(&url).ok()
-        })
+    if request == "localhost" || is_host_and_port(request) {
+        let scheme = if request.starts_with("localhost") {
+            "http"
+        } else {
+            "https"
+        };
+        return ServoUrl::parse(&format!("{scheme}://{request}")).ok();
+    }
+    ServoUrl::parse(request).ok().or_else(|| {
+        if request.starts_with('/') {
+            ServoUrl::parse(&format!("file://{}", request)).ok()
+        } else if request.contains('/') || is_reg_domain(request) {
+            ServoUrl::parse(&format!("https://{}", request)).ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Schemes whose URLs can be all digits after the colon, like `tel:5551234`, and so look like
+/// a host and port.
+const SCHEMES_LIKE_HOSTS: &[&str] = &[
+    "about",
+    "blob",
+    "data",
+    "file",
+    "ftp",
+    "geo",
+    "http",
+    "https",
+    "javascript",
+    "mailto",
+    "sms",
+    "tel",
+    "urn",
+    "ws",
+    "wss",
+];
+
+/// Whether the input is a host and port, like `localhost:3000`, which would otherwise parse as
+/// a URL with the host as its scheme.
+fn is_host_and_port(request: &str) -> bool {
+    let Some((host, rest)) = request.split_once(':') else {
+        return false;
+    };
+    let port = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    !host.is_empty() &&
+        !SCHEMES_LIKE_HOSTS.contains(&host.to_ascii_lowercase().as_str()) &&
+        !port.is_empty() &&
+        port.bytes().all(|byte| byte.is_ascii_digit())
 }
@@ -11,3 +11,13 @@ int macos_count_running_threads() {
   task_threads(task, &threads, &tcnt);
   return tcnt;
 }
+
+unsigned long long macos_resident_size() {
+  struct mach_task_basic_info info;
+  mach_msg_type_number_t count = MACH_TASK_BASIC_INFO_COUNT;
+  if (task_info(mach_task_self(), MACH_TASK_BASIC_INFO, (task_info_t)&info,
+                &count) != KERN_SUCCESS) {
+    return 0;
+  }
+  return info.resident_size;
+}
@@ -35,6 +35,12 @@ pub fn deinit(clean_shutdown: bool) {
     }
 }
 
+/// The physical memory used by this process, in bytes.
+pub fn resident_size() -> Option<u64> {
+    let size = unsafe { macos_resident_size() };
+    (size != 0).then_some(size)
+}
+
 #[link_section = "__TEXT,__info_plist"]
 #[no_mangle]
 pub static INFO_PLIST: [u8; 619] = *include_bytes!("Info.plist");
@@ -42,4 +48,5 @@ pub static INFO_PLIST: [u8; 619] = *include_bytes!("Info.plist");
 #[link(name = "count_threads")]
 extern "C" {
     fn macos_count_running_threads() -> i32;
+    fn macos_resident_size() -> u64;
 }
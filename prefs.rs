@@ -5,12 +5,17 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::mem;
 
 use getopts::Matches;
+use log::warn;
 use servo::config::opts;
 use servo::config::prefs::{self, PrefValue};
 use servo::servo_config::basedir;
 
+/// Set the prefs in `prefs.json` in the config dir, then those given with `--pref`, which take
+/// precedence. Prefs that don't exist, or whose value is of the wrong type, are ignored with a
+/// warning.
 pub fn register_user_prefs(opts_matches: &Matches) {
     // Read user's prefs.json and then parse --pref command line args.
 
@@ -57,9 +62,32 @@ pub fn register_user_prefs(opts_matches: &Matches) {
     // --pref overrides user prefs.json
     userprefs.extend(argprefs);
 
+    let userprefs = userprefs
+        .into_iter()
+        .filter_map(|(name, value)| match checked_pref_value(&name, value) {
+            Ok(value) => Some((name, value)),
+            Err(error) => {
+                warn!("Ignoring preference {name:?}: {error}");
+                None
+            },
+        })
+        .collect();
     prefs::add_user_prefs(userprefs);
 }
 
+/// The value to set the pref `name` to, or why it can't be set to `value`. Integers are
+/// accepted for prefs that are floats.
+fn checked_pref_value(name: &str, value: PrefValue) -> Result<PrefValue, String> {
+    if prefs::pref_map().is_default(name).is_err() {
+        return Err("There is no such preference".to_owned());
+    }
+    match (prefs::pref_map().get(name), value) {
+        (PrefValue::Float(_), PrefValue::Int(int)) => Ok(PrefValue::Float(int as f64)),
+        (current, value) if mem::discriminant(&current) == mem::discriminant(&value) => Ok(value),
+        (current, value) => Err(format!("{value:?} is not the same type as {current:?}")),
+    }
+}
+
 #[cfg(test)]
 fn test_parse_pref(arg: &str) {
     let mut opts = getopts::Options::new();
@@ -107,19 +135,13 @@ fn test_parse_pref_from_command_line() {
 }
 
 #[test]
-fn test_invalid_prefs_from_command_line_panics() {
-    let err_msg = std::panic::catch_unwind(|| {
-        test_parse_pref("doesntexist=true");
-    })
-    .err()
-    .and_then(|a| a.downcast_ref::<String>().cloned())
-    .expect("Should panic");
-    assert!(
-        err_msg.starts_with("Error setting preference"),
-        "Message should describe the problem"
-    );
-    assert!(
-        err_msg.contains("doesntexist"),
-        "Message should mention the name of the preference"
-    );
+fn test_invalid_prefs_from_command_line_are_ignored() {
+    use servo::servo_config::pref;
+    // Test with a preference that doesn't exist.
+    test_parse_pref("doesntexist=true");
+    assert!(prefs::pref_map().is_default("doesntexist").is_err());
+
+    // Test with a value of the wrong type.
+    test_parse_pref("dom.bluetooth.testing.enabled=maybe");
+    assert_eq!(pref!(dom.bluetooth.testing.enabled), false);
 }
@@ -4,7 +4,9 @@
 
 use std::path::Path;
 
-use crate::parser::{get_default_url, location_bar_input_to_url, parse_url_or_filename};
+use crate::parser::{
+    get_default_url, location_bar_input_to_url, parse_url_or_filename, search_url,
+};
 
 #[cfg(not(target_os = "windows"))]
 const FAKE_CWD: &str = "/fake/cwd";
@@ -208,6 +210,42 @@ fn test_cmdline_and_location_bar_url() {
     );
 }
 
+#[test]
+fn test_location_bar_url_fix_up() {
+    let fix_up = |input| location_bar_input_to_url(input).unwrap().into_string();
+    assert_eq!(fix_up("localhost"), "http://localhost/");
+    assert_eq!(fix_up("localhost:3000"), "http://localhost:3000/");
+    assert_eq!(
+        fix_up("localhost:3000/path?q"),
+        "http://localhost:3000/path?q"
+    );
+    assert_eq!(fix_up("example.com:8080"), "https://example.com:8080/");
+    assert_eq!(
+        fix_up("mailto:someone@example.com"),
+        "mailto:someone@example.com"
+    );
+    // A scheme followed by digits is not a host and port.
+    assert_eq!(fix_up("tel:5551234"), "tel:5551234");
+    assert_eq!(fix_up("TEL:5551234"), "tel:5551234");
+    assert_eq!(fix_up("  servo.org  "), "https://servo.org/");
+}
+
+#[test]
+fn test_search_url() {
+    let search = |query| {
+        search_url("https://example.com/search?q=%s", query)
+            .unwrap()
+            .into_string()
+    };
+    assert_eq!(search("cats"), "https://example.com/search?q=cats");
+    assert_eq!(
+        search("cats & dogs"),
+        "https://example.com/search?q=cats+%26+dogs"
+    );
+    assert_eq!(search("100%"), "https://example.com/search?q=100%25");
+    assert_eq!(search("#1"), "https://example.com/search?q=%231");
+}
+
 #[cfg(target_os = "linux")]
 #[test]
 fn test_cmd_and_location_bar_url() {
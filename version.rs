@@ -0,0 +1,35 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Version information about the build, as emitted by vergen in build.rs.
+
+/// Placeholder branch set by build.rs when git information is unavailable.
+const NO_BRANCH: &str = "nobranch";
+
+/// The short git SHA of the build, e.g. `1a2b3c4`, or `nogit`.
+pub fn git_sha() -> &'static str {
+    env!("VERGEN_GIT_SHA")
+}
+
+/// The git branch of the build, if known.
+pub fn git_branch() -> Option<&'static str> {
+    Some(env!("VERGEN_GIT_BRANCH")).filter(|branch| *branch != NO_BRANCH)
+}
+
+/// Whether the working tree had uncommitted changes at build time.
+pub fn git_dirty() -> bool {
+    env!("VERGEN_GIT_DIRTY") == "true"
+}
+
+/// A display string for the build, e.g. `Servo 0.1.0-1a2b3c4-dirty (main)`.
+pub fn servo_version() -> String {
+    let mut version = format!("Servo {}-{}", env!("CARGO_PKG_VERSION"), git_sha());
+    if git_dirty() {
+        version.push_str("-dirty");
+    }
+    if let Some(branch) = git_branch() {
+        version.push_str(&format!(" ({})", branch));
+    }
+    version
+}